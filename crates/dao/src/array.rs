@@ -0,0 +1,377 @@
+use crate::{
+    value::{
+        FromValue,
+        ToValue,
+        Value,
+    },
+    ConvertError,
+};
+use bigdecimal::BigDecimal;
+use chrono::{
+    DateTime,
+    NaiveDate,
+    SecondsFormat,
+    Utc,
+};
+use serde_derive::{
+    Deserialize,
+    Serialize,
+};
+use serde_json::Value as Json;
+use std::fmt;
+use uuid::Uuid;
+
+/// An array of values, a multi-dimensional array is an array of arrays
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Array {
+    Bool(Vec<bool>),
+
+    Smallint(Vec<i16>),
+    Int(Vec<i32>),
+    Bigint(Vec<i64>),
+
+    Float(Vec<f32>),
+    Double(Vec<f64>),
+    BigDecimal(Vec<BigDecimal>),
+
+    Text(Vec<String>),
+
+    Uuid(Vec<Uuid>),
+    Date(Vec<NaiveDate>),
+    Timestamp(Vec<DateTime<Utc>>),
+
+    /// the elements of an array which has null elements or elements of a type
+    /// not listed above, each element is `Value::Nil` or a scalar value
+    Nullable(Vec<Value>),
+    /// a multi-dimensional array, the sub arrays have the same dimensions
+    Nested(Vec<Array>),
+}
+
+/// evaluate `$typed` for the typed arrays, `$nullable` and `$nested` for the others
+macro_rules! typed_arrays {
+    (
+        $array:expr,
+        $values:ident => $typed:expr,
+        $nullable:ident => $nullable_expr:expr,
+        $nested:ident => $nested_expr:expr
+    ) => {
+        match $array {
+            Array::Bool($values) => $typed,
+            Array::Smallint($values) => $typed,
+            Array::Int($values) => $typed,
+            Array::Bigint($values) => $typed,
+            Array::Float($values) => $typed,
+            Array::Double($values) => $typed,
+            Array::BigDecimal($values) => $typed,
+            Array::Text($values) => $typed,
+            Array::Uuid($values) => $typed,
+            Array::Date($values) => $typed,
+            Array::Timestamp($values) => $typed,
+            Array::Nullable($nullable) => $nullable_expr,
+            Array::Nested($nested) => $nested_expr,
+        }
+    };
+}
+
+impl Array {
+    /// the number of elements in the first dimension
+    pub fn len(&self) -> usize {
+        typed_arrays!(self, v => v.len(), v => v.len(), v => v.len())
+    }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    /// the elements of the first dimension, the elements of a nested array are `Value::Array`
+    pub fn to_values(&self) -> Vec<Value> {
+        typed_arrays!(
+            self,
+            v => v.iter().map(ToValue::to_value).collect(),
+            v => v.clone(),
+            v => v.iter().cloned().map(Value::Array).collect()
+        )
+    }
+
+    /// the array of these elements, it is a typed array when the elements are not null
+    /// and of the same type
+    pub fn from_values(values: Vec<Value>) -> Array {
+        macro_rules! typed {
+            ($($variant:ident),*) => {
+                match values.first() {
+                    $(Some(Value::$variant(_)) => {
+                        let typed: Option<Vec<_>> = values
+                            .iter()
+                            .map(|v| match v {
+                                Value::$variant(v) => Some(v.clone()),
+                                _ => None,
+                            })
+                            .collect();
+                        typed.map(Array::$variant)
+                    })*
+                    Some(Value::Array(_)) => {
+                        let nested: Option<Vec<_>> = values
+                            .iter()
+                            .map(|v| match v {
+                                Value::Array(v) => Some(v.clone()),
+                                _ => None,
+                            })
+                            .collect();
+                        nested.map(Array::Nested)
+                    }
+                    _ => None,
+                }
+            };
+        }
+        let typed = typed!(
+            Bool, Smallint, Int, Bigint, Float, Double, BigDecimal, Text, Uuid, Date, Timestamp
+        );
+        typed.unwrap_or(Array::Nullable(values))
+    }
+
+    /// the length of each dimension, an error when the sub arrays have different dimensions
+    pub fn dimensions(&self) -> Result<Vec<usize>, ConvertError> {
+        match self {
+            Array::Nested(arrays) => {
+                let mut dimensions = vec![arrays.len()];
+                if let Some(first) = arrays.first() {
+                    let sub_dimensions = first.dimensions()?;
+                    for array in arrays.iter().skip(1) {
+                        if array.dimensions()? != sub_dimensions {
+                            return Err(ConvertError::NotSupported(
+                                self.to_string(),
+                                "a multi-dimensional array with sub arrays of the same length"
+                                    .into(),
+                            ));
+                        }
+                    }
+                    dimensions.extend(sub_dimensions);
+                }
+                Ok(dimensions)
+            }
+            _ => Ok(vec![self.len()]),
+        }
+    }
+
+    /// the elements of all the dimensions in row major order
+    pub fn flatten(&self) -> Vec<Value> {
+        match self {
+            Array::Nested(arrays) => arrays.iter().flat_map(Array::flatten).collect(),
+            _ => self.to_values(),
+        }
+    }
+
+    /// the array as a json array, which is how arrays are stored in sqlite and mysql.
+    /// Decimals, uuids, dates and timestamps are json strings.
+    pub fn to_json(&self) -> String { to_json(&Value::Array(self.clone())).to_string() }
+
+    /// parse an array stored as a json array, the json numbers are `Bigint` or `Double`
+    pub fn from_json(json: &str) -> Result<Array, ConvertError> {
+        match serde_json::from_str(json) {
+            Ok(Json::Array(elements)) => Ok(from_json(elements)),
+            _ => Err(ConvertError::NotSupported(json.to_string(), "Array".into())),
+        }
+    }
+}
+
+fn to_json(value: &Value) -> Json {
+    match value {
+        Value::Nil => Json::Null,
+        Value::Bool(v) => Json::from(*v),
+        Value::Tinyint(v) => Json::from(*v),
+        Value::Smallint(v) => Json::from(*v),
+        Value::Int(v) => Json::from(*v),
+        Value::Bigint(v) => Json::from(*v),
+        Value::Float(v) => Json::from(*v),
+        Value::Double(v) => Json::from(*v),
+        Value::Json(v) => v.clone(),
+        Value::Timestamp(v) => Json::from(v.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+        Value::Array(array) => Json::Array(array.to_values().iter().map(to_json).collect()),
+        value => Json::from(value.to_string()),
+    }
+}
+
+fn from_json(elements: Vec<Json>) -> Array {
+    let values = elements
+        .into_iter()
+        .map(|element| {
+            match element {
+                Json::Null => Value::Nil,
+                Json::Bool(v) => Value::Bool(v),
+                Json::Number(v) => {
+                    match v.as_i64() {
+                        Some(v) => Value::Bigint(v),
+                        None => Value::Double(v.as_f64().unwrap_or(f64::NAN)),
+                    }
+                }
+                Json::String(v) => Value::Text(v),
+                Json::Array(v) => Value::Array(from_json(v)),
+                Json::Object(_) => Value::Json(element),
+            }
+        })
+        .collect();
+    Array::from_values(values)
+}
+
+impl fmt::Display for Array {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { write!(f, "{}", self.to_json()) }
+}
+
+/// The types which can be the elements of an `Array`, so that `Vec<T>`, `Vec<Option<T>>`
+/// and `Vec<Vec<T>>` can be converted to and from a `Value`
+pub trait ArrayElement: Sized + Clone {
+    fn to_array(elements: &[Self]) -> Array;
+
+    fn from_array(array: &Array) -> Result<Vec<Self>, ConvertError>;
+}
+
+macro_rules! impl_array_element {
+    ($ty:ty, $variant:ident) => {
+        impl ArrayElement for $ty {
+            fn to_array(elements: &[Self]) -> Array { Array::$variant(elements.to_vec()) }
+
+            fn from_array(array: &Array) -> Result<Vec<Self>, ConvertError> {
+                match array {
+                    Array::$variant(elements) => Ok(elements.clone()),
+                    array => array.to_values().iter().map(FromValue::from_value).collect(),
+                }
+            }
+        }
+    };
+}
+
+impl_array_element!(bool, Bool);
+impl_array_element!(i16, Smallint);
+impl_array_element!(i32, Int);
+impl_array_element!(i64, Bigint);
+impl_array_element!(f32, Float);
+impl_array_element!(f64, Double);
+impl_array_element!(BigDecimal, BigDecimal);
+impl_array_element!(String, Text);
+impl_array_element!(Uuid, Uuid);
+impl_array_element!(NaiveDate, Date);
+impl_array_element!(DateTime<Utc>, Timestamp);
+
+impl<T> ArrayElement for Option<T>
+where
+    T: ArrayElement + ToValue + FromValue,
+{
+    fn to_array(elements: &[Self]) -> Array {
+        let not_null: Option<Vec<T>> = elements.iter().cloned().collect();
+        match not_null {
+            Some(elements) => T::to_array(&elements),
+            None => Array::Nullable(elements.iter().map(ToValue::to_value).collect()),
+        }
+    }
+
+    fn from_array(array: &Array) -> Result<Vec<Self>, ConvertError> {
+        array.to_values().iter().map(FromValue::from_value).collect()
+    }
+}
+
+impl<T> ArrayElement for Vec<T>
+where
+    T: ArrayElement,
+{
+    fn to_array(elements: &[Self]) -> Array {
+        Array::Nested(elements.iter().map(|v| T::to_array(v)).collect())
+    }
+
+    fn from_array(array: &Array) -> Result<Vec<Self>, ConvertError> {
+        match array {
+            Array::Nested(arrays) => arrays.iter().map(T::from_array).collect(),
+            array if array.is_empty() => Ok(vec![]),
+            array => {
+                Err(ConvertError::NotSupported(
+                    array.to_string(),
+                    "a multi-dimensional array".into(),
+                ))
+            }
+        }
+    }
+}
+
+impl<T> ToValue for Vec<T>
+where
+    T: ArrayElement,
+{
+    fn to_value(&self) -> Value { Value::Array(T::to_array(self)) }
+}
+
+impl<T> FromValue for Vec<T>
+where
+    T: ArrayElement,
+{
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match v {
+            Value::Array(array) => T::from_array(array),
+            // the arrays are stored as json in sqlite and mysql
+            Value::Text(json) => T::from_array(&Array::from_json(json)?),
+            Value::Json(Json::Array(elements)) => T::from_array(&from_json(elements.clone())),
+            _ => Err(ConvertError::NotSupported(format!("{:?}", v), "Vec".into())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::str::FromStr;
+
+    fn round_trip<T>(v: T)
+    where
+        T: ToValue + FromValue + PartialEq + fmt::Debug,
+    {
+        let value = v.to_value();
+        assert_eq!(T::from_value(&value).unwrap(), v);
+        let json = match value {
+            Value::Array(ref array) => array.to_json(),
+            _ => panic!("expecting an array, got {:?}", value),
+        };
+        assert_eq!(T::from_value(&Value::Text(json)).unwrap(), v);
+    }
+
+    #[test]
+    fn element_types() {
+        round_trip(vec![true, false]);
+        round_trip(vec![1i16, 2]);
+        round_trip(vec![1i32, 2]);
+        round_trip(vec![i64::MAX, i64::MIN]);
+        round_trip(vec![1.5f32, -2.0]);
+        round_trip(vec![1.5f64, -2.25]);
+        round_trip(vec![BigDecimal::from_str("12345678901234567890.123").unwrap()]);
+        round_trip(vec!["a".to_string(), "b\"c".to_string()]);
+        round_trip(vec![Uuid::nil()]);
+        let date = NaiveDate::from_ymd_opt(2021, 1, 31).unwrap();
+        round_trip(vec![date]);
+        let time = date.and_hms_micro_opt(10, 20, 30, 123_456).unwrap();
+        round_trip(vec![Utc.from_utc_datetime(&time)]);
+        round_trip(Vec::<i32>::new());
+    }
+
+    #[test]
+    fn null_elements() {
+        round_trip(vec![Some(1), None, Some(3)]);
+        round_trip(vec![None::<String>]);
+        assert_eq!(
+            vec![Some(1), Some(2)].to_value(),
+            Value::Array(Array::Int(vec![1, 2]))
+        );
+        assert!(Vec::<i32>::from_value(&vec![Some(1), None].to_value()).is_err());
+    }
+
+    #[test]
+    fn multi_dimensional() {
+        let matrix = vec![vec![1, 2, 3], vec![4, 5, 6]];
+        round_trip(matrix.clone());
+        round_trip(vec![vec![Some("a".to_string()), None]]);
+        let array = match matrix.to_value() {
+            Value::Array(array) => array,
+            _ => unreachable!(),
+        };
+        assert_eq!(array.dimensions().unwrap(), vec![2, 3]);
+        assert_eq!(array.flatten().len(), 6);
+        assert_eq!(array.to_string(), "[[1,2,3],[4,5,6]]");
+        let ragged = Array::Nested(vec![Array::Int(vec![1]), Array::Int(vec![1, 2])]);
+        assert!(ragged.dimensions().is_err());
+    }
+}
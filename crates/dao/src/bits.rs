@@ -0,0 +1,104 @@
+use crate::{
+    value::{
+        FromValue,
+        ToValue,
+        Value,
+    },
+    ConvertError,
+};
+use serde_derive::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    fmt,
+    str::FromStr,
+};
+
+/// A string of bits, like the `bit` and `bit varying` of postgresql, ie: `B'1010'`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BitString(pub Vec<bool>);
+
+impl BitString {
+    /// the bits packed into bytes, the first bit is the highest bit of the first byte
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.0
+            .chunks(8)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .fold(0, |byte, (i, bit)| byte | (u8::from(*bit) << (7 - i)))
+            })
+            .collect()
+    }
+
+    /// the first `len` bits of the bytes
+    pub fn from_bytes(bytes: &[u8], len: usize) -> Self {
+        BitString(
+            (0..len.min(bytes.len() * 8))
+                .map(|i| bytes[i / 8] & (0x80 >> (i % 8)) != 0)
+                .collect(),
+        )
+    }
+}
+
+impl fmt::Display for BitString {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for bit in self.0.iter() {
+            write!(f, "{}", if *bit { '1' } else { '0' })?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for BitString {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.chars()
+            .map(|c| {
+                match c {
+                    '0' => Ok(false),
+                    '1' => Ok(true),
+                    _ => Err(ConvertError::NotSupported(s.to_string(), "BitString".into())),
+                }
+            })
+            .collect::<Result<_, _>>()
+            .map(BitString)
+    }
+}
+
+impl ToValue for BitString {
+    fn to_value(&self) -> Value { Value::Bit(self.clone()) }
+}
+
+impl FromValue for BitString {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Bit(ref v) => Ok(v.clone()),
+            Value::Text(ref v) => v.parse(),
+            _ => {
+                Err(ConvertError::NotSupported(
+                    format!("{:?}", v),
+                    "BitString".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bit_strings() {
+        let bits: BitString = "1010000011".parse().unwrap();
+        assert_eq!(bits.to_bytes(), vec![0b1010_0000, 0b1100_0000]);
+        assert_eq!(BitString::from_bytes(&bits.to_bytes(), 10), bits);
+        assert_eq!(bits.to_string(), "1010000011");
+        assert_eq!(BitString::from_value(&bits.to_value()).unwrap(), bits);
+        assert!("102".parse::<BitString>().is_err());
+    }
+}
@@ -1,4 +1,3 @@
-use crate::common;
 use serde_derive::{
     Deserialize,
     Serialize,
@@ -37,20 +36,14 @@ impl ColumnName {
         }
     }
 
-    /// return the long name of the table using schema.table_name
+    /// return the long name of the column using table.column_name, to be displayed.
+    /// Use `identifier::Dialect::column_name` of rustorm for the name in a sql statement
     pub fn complete_name(&self) -> String {
         match self.table {
             Some(ref table) => format!("{}.{}", table, self.name),
             None => self.name.to_owned(),
         }
     }
-
-    pub fn safe_complete_name(&self) -> String {
-        match self.table {
-            Some(ref table) => format!("{}.{}", common::keywords_safe(table), self.name),
-            None => self.name.to_owned(),
-        }
-    }
 }
 
 pub trait ToColumnNames {
@@ -0,0 +1,112 @@
+use crate::{
+    value::{
+        FromValue,
+        ToValue,
+        Value,
+    },
+    ConvertError,
+    Dao,
+    FromDao,
+    ToDao,
+};
+use std::ops::{
+    Deref,
+    DerefMut,
+};
+
+/// A value of a postgresql composite type, to use a `ToDao` and `FromDao` struct as a field of
+/// another one. The fields of the struct are the fields of the composite type.
+///
+/// ```rust
+/// # use clia_rustorm_dao::{Composite, Dao, FromDao, FromValue, ToDao, ToValue};
+/// #[derive(Debug, PartialEq)]
+/// struct Address {
+///     city: String,
+/// }
+/// # impl ToDao for Address {
+/// #     fn to_dao(&self) -> Dao {
+/// #         let mut dao = Dao::new();
+/// #         dao.insert("city", &self.city);
+/// #         dao
+/// #     }
+/// # }
+/// # impl FromDao for Address {
+/// #     fn from_dao(dao: &Dao) -> Self { Address { city: dao.get("city").unwrap() } }
+/// # }
+/// let address = Composite(Address { city: "Lethbridge".into() });
+/// let value = address.to_value();
+/// assert_eq!(Composite::from_value(&value).unwrap(), address);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Composite<T>(pub T);
+
+impl<T> Composite<T> {
+    pub fn into_inner(self) -> T { self.0 }
+}
+
+impl<T> Deref for Composite<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T { &self.0 }
+}
+
+impl<T> DerefMut for Composite<T> {
+    fn deref_mut(&mut self) -> &mut T { &mut self.0 }
+}
+
+impl<T> From<T> for Composite<T> {
+    fn from(v: T) -> Self { Composite(v) }
+}
+
+impl<T> ToValue for Composite<T>
+where
+    T: ToDao,
+{
+    fn to_value(&self) -> Value { Value::Composite(Box::new(self.0.to_dao())) }
+}
+
+impl<T> FromValue for Composite<T>
+where
+    T: FromDao,
+{
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        Dao::from_value(v).map(|dao| Composite(T::from_dao(&dao)))
+    }
+}
+
+impl ToValue for Dao {
+    fn to_value(&self) -> Value { Value::Composite(Box::new(self.clone())) }
+}
+
+/// the composites stored as json in sqlite and mysql are parsed
+impl FromValue for Dao {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        let not_supported = || ConvertError::NotSupported(format!("{:?}", v), "Dao".into());
+        match *v {
+            Value::Composite(ref v) => Ok((**v).clone()),
+            Value::Json(_) | Value::Text(_) => {
+                let json = serde_json::Value::from_value(v)?;
+                serde_json::from_value(json).map_err(|_| not_supported())
+            }
+            _ => Err(not_supported()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn composites() {
+        let mut dao = Dao::new();
+        dao.insert("city", "Lethbridge");
+        dao.insert("postal_code", None::<String>);
+        let value = dao.to_value();
+        assert_eq!(value.to_string(), r#"{"city":"Lethbridge","postal_code":null}"#);
+        assert_eq!(Dao::from_value(&value).unwrap(), dao);
+        let json = Value::Text(serde_json::to_string(&dao).unwrap());
+        assert_eq!(Dao::from_value(&json).unwrap(), dao);
+        assert!(Dao::from_value(&Value::Int(1)).is_err());
+    }
+}
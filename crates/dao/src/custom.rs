@@ -0,0 +1,49 @@
+use crate::{
+    value::{
+        FromValue,
+        ToValue,
+        Value,
+    },
+    ConvertError,
+};
+use serde_derive::{
+    Deserialize,
+    Serialize,
+};
+use std::fmt;
+
+/// A value of a database type unknown to rustorm, ie: a postgresql enum or domain,
+/// decoded by the decoder registered for its type
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Custom {
+    /// the name of the database type
+    pub type_name: String,
+    /// the value in one of the known variants, used when the type has no registered encoder
+    pub value: Value,
+}
+
+impl Custom {
+    pub fn new(type_name: impl Into<String>, value: impl Into<Value>) -> Self {
+        Custom {
+            type_name: type_name.into(),
+            value: value.into(),
+        }
+    }
+}
+
+impl fmt::Display for Custom {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { self.value.fmt(f) }
+}
+
+impl ToValue for Custom {
+    fn to_value(&self) -> Value { Value::Custom(Box::new(self.clone())) }
+}
+
+impl FromValue for Custom {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Custom(ref v) => Ok((**v).clone()),
+            _ => Err(ConvertError::NotSupported(format!("{:?}", v), "Custom".into())),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! The conversions of `rust_decimal::Decimal`, which is stored as a `Value::BigDecimal` to
+//! keep every digit.
+use crate::{
+    ConvertError,
+    FromValue,
+    ToValue,
+    Value,
+};
+use bigdecimal::BigDecimal;
+use rust_decimal::Decimal;
+use std::{
+    convert::TryFrom,
+    str::FromStr,
+};
+
+impl ToValue for Decimal {
+    fn to_value(&self) -> Value {
+        let v = BigDecimal::from_str(&self.to_string()).expect("a decimal is a valid big decimal");
+        Value::BigDecimal(v)
+    }
+}
+
+/// the decimals whose digits or scale do not fit into a `Decimal` are not supported
+impl FromValue for Decimal {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        let not_supported = || ConvertError::NotSupported(format!("{:?}", v), "Decimal".into());
+        let parse = |s: &str| {
+            Decimal::from_str_exact(s)
+                .or_else(|_| Decimal::from_scientific(s))
+                .map_err(|_| not_supported())
+        };
+        match *v {
+            Value::BigDecimal(ref v) => parse(&v.to_string()),
+            Value::Tinyint(v) => Ok(Decimal::from(v)),
+            Value::Smallint(v) => Ok(Decimal::from(v)),
+            Value::Int(v) => Ok(Decimal::from(v)),
+            Value::Bigint(v) => Ok(Decimal::from(v)),
+            Value::Float(v) => Decimal::try_from(v).map_err(|_| not_supported()),
+            Value::Double(v) => Decimal::try_from(v).map_err(|_| not_supported()),
+            Value::Text(ref v) => parse(v),
+            _ => Err(not_supported()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimals() {
+        let price = Decimal::from_str("12345678901234567.8901").unwrap();
+        let value = price.to_value();
+        assert_eq!(
+            value,
+            Value::BigDecimal(BigDecimal::from_str("12345678901234567.8901").unwrap())
+        );
+        assert_eq!(Decimal::from_value(&value).unwrap(), price);
+        assert_eq!(
+            Decimal::from_value(&Value::Text("0.10".into())).unwrap().to_string(),
+            "0.10"
+        );
+        assert_eq!(Decimal::from_value(&Value::Int(7)).unwrap(), Decimal::from(7));
+        assert_eq!(
+            Decimal::from_value(&Value::Double(0.1)).unwrap(),
+            Decimal::from_str("0.1").unwrap()
+        );
+        assert!(Decimal::from_value(&Value::Text("1e40".into())).is_err());
+        assert!(Decimal::from_value(&Value::Bool(true)).is_err());
+    }
+}
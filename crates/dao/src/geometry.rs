@@ -0,0 +1,755 @@
+//! The geometries of `geo_types` with their spatial reference id, in the well-known binary
+//! (WKB), the extended WKB of PostGIS (EWKB) and the well-known text (WKT) formats.
+use crate::{
+    value::{
+        FromValue,
+        ToValue,
+        Value,
+    },
+    ConvertError,
+};
+use geo_types::{
+    Coord,
+    GeometryCollection,
+    Line,
+    LineString,
+    MultiLineString,
+    MultiPoint,
+    MultiPolygon,
+    Point,
+    Polygon,
+    Rect,
+};
+use serde_derive::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    convert::TryFrom,
+    fmt,
+    iter::Peekable,
+    str::{
+        Chars,
+        FromStr,
+    },
+};
+
+const WKB_POINT: u32 = 1;
+const WKB_LINE_STRING: u32 = 2;
+const WKB_POLYGON: u32 = 3;
+const WKB_MULTI_POINT: u32 = 4;
+const WKB_MULTI_LINE_STRING: u32 = 5;
+const WKB_MULTI_POLYGON: u32 = 6;
+const WKB_GEOMETRY_COLLECTION: u32 = 7;
+/// the flags of the EWKB geometry type
+const EWKB_Z: u32 = 0x8000_0000;
+const EWKB_M: u32 = 0x4000_0000;
+const EWKB_SRID: u32 = 0x2000_0000;
+
+/// A geometry with its optional spatial reference id, ie: 4326 for WGS 84
+///
+/// The lines, rectangles and triangles are written as line strings and polygons. Only the x
+/// and y coordinates are read from the geometries with a Z or M coordinate, the geometries
+/// written are two dimensional.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Geometry {
+    pub geometry: geo_types::Geometry<f64>,
+    pub srid: Option<i32>,
+}
+
+impl Geometry {
+    pub fn new(geometry: impl Into<geo_types::Geometry<f64>>) -> Self {
+        Geometry {
+            geometry: geometry.into(),
+            srid: None,
+        }
+    }
+
+    pub fn with_srid(mut self, srid: i32) -> Self {
+        self.srid = Some(srid);
+        self
+    }
+
+    /// the little endian WKB, without the srid
+    pub fn to_wkb(&self) -> Vec<u8> {
+        let mut out = vec![];
+        write_wkb(&mut out, &self.geometry, None);
+        out
+    }
+
+    /// the little endian EWKB of PostGIS, with the srid when there is one
+    pub fn to_ewkb(&self) -> Vec<u8> {
+        let mut out = vec![];
+        write_wkb(&mut out, &self.geometry, self.srid);
+        out
+    }
+
+    /// read the WKB or the EWKB in either byte order, the Z and M coordinates are dropped
+    pub fn from_wkb(bytes: &[u8]) -> Result<Self, ConvertError> {
+        let mut reader = WkbReader {
+            bytes,
+            extra_dimensions: 0,
+        };
+        let (geometry, srid) = reader.geometry()?;
+        if reader.bytes.is_empty() {
+            Ok(Geometry { geometry, srid })
+        } else {
+            Err(invalid_wkb(bytes))
+        }
+    }
+}
+
+impl<T> From<T> for Geometry
+where
+    T: Into<geo_types::Geometry<f64>>,
+{
+    fn from(geometry: T) -> Self { Geometry::new(geometry) }
+}
+
+fn invalid_wkb(bytes: &[u8]) -> ConvertError {
+    ConvertError::NotSupported(format!("{:?}", bytes), "Geometry".to_string())
+}
+
+/// the polygon of the rectangle, counter clockwise from the min corner
+fn rect_to_polygon(rect: &Rect<f64>) -> Polygon<f64> {
+    let (min, max) = (rect.min(), rect.max());
+    Polygon::new(
+        LineString::from(vec![
+            (min.x, min.y),
+            (max.x, min.y),
+            (max.x, max.y),
+            (min.x, max.y),
+            (min.x, min.y),
+        ]),
+        vec![],
+    )
+}
+
+/// the lines, rectangles and triangles as the geometries of the WKB and WKT
+fn normalize(geometry: &geo_types::Geometry<f64>) -> Option<geo_types::Geometry<f64>> {
+    match geometry {
+        geo_types::Geometry::Line(line) => {
+            Some(LineString::from(vec![line.start, line.end]).into())
+        }
+        geo_types::Geometry::Rect(rect) => Some(rect_to_polygon(rect).into()),
+        geo_types::Geometry::Triangle(triangle) => Some(triangle.to_polygon().into()),
+        _ => None,
+    }
+}
+
+fn write_wkb(out: &mut Vec<u8>, geometry: &geo_types::Geometry<f64>, srid: Option<i32>) {
+    if let Some(geometry) = normalize(geometry) {
+        return write_wkb(out, &geometry, srid);
+    }
+    let ty = match geometry {
+        geo_types::Geometry::Point(_) => WKB_POINT,
+        geo_types::Geometry::LineString(_) => WKB_LINE_STRING,
+        geo_types::Geometry::Polygon(_) => WKB_POLYGON,
+        geo_types::Geometry::MultiPoint(_) => WKB_MULTI_POINT,
+        geo_types::Geometry::MultiLineString(_) => WKB_MULTI_LINE_STRING,
+        geo_types::Geometry::MultiPolygon(_) => WKB_MULTI_POLYGON,
+        _ => WKB_GEOMETRY_COLLECTION,
+    };
+    // little endian
+    out.push(1);
+    match srid {
+        Some(srid) => {
+            out.extend_from_slice(&(ty | EWKB_SRID).to_le_bytes());
+            out.extend_from_slice(&srid.to_le_bytes());
+        }
+        None => out.extend_from_slice(&ty.to_le_bytes()),
+    }
+    let put_len =
+        |out: &mut Vec<u8>, len: usize| out.extend_from_slice(&(len as u32).to_le_bytes());
+    let put_coords = |out: &mut Vec<u8>, line: &LineString<f64>| {
+        put_len(out, line.0.len());
+        for coord in line.0.iter() {
+            out.extend_from_slice(&coord.x.to_le_bytes());
+            out.extend_from_slice(&coord.y.to_le_bytes());
+        }
+    };
+    let put_polygon = |out: &mut Vec<u8>, polygon: &Polygon<f64>| {
+        put_len(out, 1 + polygon.interiors().len());
+        put_coords(out, polygon.exterior());
+        for interior in polygon.interiors() {
+            put_coords(out, interior);
+        }
+    };
+    match geometry {
+        geo_types::Geometry::Point(point) => {
+            out.extend_from_slice(&point.x().to_le_bytes());
+            out.extend_from_slice(&point.y().to_le_bytes());
+        }
+        geo_types::Geometry::LineString(line) => put_coords(out, line),
+        geo_types::Geometry::Polygon(polygon) => put_polygon(out, polygon),
+        geo_types::Geometry::MultiPoint(points) => {
+            put_len(out, points.0.len());
+            for point in points.0.iter() {
+                write_wkb(out, &(*point).into(), None);
+            }
+        }
+        geo_types::Geometry::MultiLineString(lines) => {
+            put_len(out, lines.0.len());
+            for line in lines.0.iter() {
+                write_wkb(out, &line.clone().into(), None);
+            }
+        }
+        geo_types::Geometry::MultiPolygon(polygons) => {
+            put_len(out, polygons.0.len());
+            for polygon in polygons.0.iter() {
+                write_wkb(out, &polygon.clone().into(), None);
+            }
+        }
+        geo_types::Geometry::GeometryCollection(geometries) => {
+            put_len(out, geometries.0.len());
+            for geometry in geometries.0.iter() {
+                write_wkb(out, geometry, None);
+            }
+        }
+        _ => unreachable!("normalized above"),
+    }
+}
+
+struct WkbReader<'a> {
+    bytes: &'a [u8],
+    /// the number of Z and M coordinates of the geometry being read, which are skipped
+    extra_dimensions: usize,
+}
+
+impl<'a> WkbReader<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], ConvertError> {
+        if self.bytes.len() < N {
+            return Err(invalid_wkb(self.bytes));
+        }
+        let (taken, rest) = self.bytes.split_at(N);
+        self.bytes = rest;
+        Ok(<[u8; N]>::try_from(taken).expect("N bytes"))
+    }
+
+    fn u32(&mut self, little_endian: bool) -> Result<u32, ConvertError> {
+        let bytes = self.take::<4>()?;
+        Ok(if little_endian {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        })
+    }
+
+    fn f64(&mut self, little_endian: bool) -> Result<f64, ConvertError> {
+        let bytes = self.take::<8>()?;
+        Ok(if little_endian {
+            f64::from_le_bytes(bytes)
+        } else {
+            f64::from_be_bytes(bytes)
+        })
+    }
+
+    /// the number of elements, bounded by the remaining bytes
+    fn len(&mut self, little_endian: bool, min_size: usize) -> Result<usize, ConvertError> {
+        let len = self.u32(little_endian)? as usize;
+        if len.saturating_mul(min_size) > self.bytes.len() {
+            Err(invalid_wkb(self.bytes))
+        } else {
+            Ok(len)
+        }
+    }
+
+    /// the x and y of a coordinate, skipping its Z and M
+    fn coord(&mut self, little_endian: bool) -> Result<Coord<f64>, ConvertError> {
+        let x = self.f64(little_endian)?;
+        let y = self.f64(little_endian)?;
+        for _ in 0..self.extra_dimensions {
+            self.take::<8>()?;
+        }
+        Ok(Coord { x, y })
+    }
+
+    fn coords(&mut self, little_endian: bool) -> Result<LineString<f64>, ConvertError> {
+        let len = self.len(little_endian, 16 + 8 * self.extra_dimensions)?;
+        (0..len)
+            .map(|_| self.coord(little_endian))
+            .collect::<Result<Vec<_>, _>>()
+            .map(LineString)
+    }
+
+    fn polygon(&mut self, little_endian: bool) -> Result<Polygon<f64>, ConvertError> {
+        let len = self.len(little_endian, 4)?;
+        let mut rings = (0..len)
+            .map(|_| self.coords(little_endian))
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter();
+        let exterior = rings.next().unwrap_or_else(|| LineString(vec![]));
+        Ok(Polygon::new(exterior, rings.collect()))
+    }
+
+    /// the geometries of a multi geometry or a collection
+    fn members(
+        &mut self,
+        little_endian: bool,
+    ) -> Result<Vec<geo_types::Geometry<f64>>, ConvertError> {
+        let len = self.len(little_endian, 5)?;
+        (0..len)
+            .map(|_| self.geometry().map(|(geometry, _srid)| geometry))
+            .collect()
+    }
+
+    fn geometry(&mut self) -> Result<(geo_types::Geometry<f64>, Option<i32>), ConvertError> {
+        let little_endian = match self.take::<1>()? {
+            [0] => false,
+            [1] => true,
+            _ => return Err(invalid_wkb(self.bytes)),
+        };
+        let ty = self.u32(little_endian)?;
+        // the ISO WKB adds 1000, 2000 or 3000 to the type for Z, M and ZM
+        let iso_dimensions = match (ty & 0xffff) / 1000 {
+            0 => 0,
+            1 | 2 => 1,
+            3 => 2,
+            _ => return Err(invalid_wkb(self.bytes)),
+        };
+        let ewkb_dimensions = (ty & EWKB_Z != 0) as usize + (ty & EWKB_M != 0) as usize;
+        self.extra_dimensions = iso_dimensions + ewkb_dimensions;
+        let srid = if ty & EWKB_SRID != 0 {
+            Some(self.u32(little_endian)? as i32)
+        } else {
+            None
+        };
+        let members = |reader: &mut Self| reader.members(little_endian);
+        let geometry = match (ty & 0xffff) % 1000 {
+            WKB_POINT => Point::from(self.coord(little_endian)?).into(),
+            WKB_LINE_STRING => self.coords(little_endian)?.into(),
+            WKB_POLYGON => self.polygon(little_endian)?.into(),
+            WKB_MULTI_POINT => {
+                members(self)?
+                    .into_iter()
+                    .map(Point::try_from)
+                    .collect::<Result<MultiPoint<f64>, _>>()
+                    .map_err(|_| invalid_wkb(self.bytes))?
+                    .into()
+            }
+            WKB_MULTI_LINE_STRING => {
+                members(self)?
+                    .into_iter()
+                    .map(LineString::try_from)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(MultiLineString)
+                    .map_err(|_| invalid_wkb(self.bytes))?
+                    .into()
+            }
+            WKB_MULTI_POLYGON => {
+                members(self)?
+                    .into_iter()
+                    .map(Polygon::try_from)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(MultiPolygon)
+                    .map_err(|_| invalid_wkb(self.bytes))?
+                    .into()
+            }
+            WKB_GEOMETRY_COLLECTION => {
+                geo_types::Geometry::GeometryCollection(GeometryCollection(members(self)?))
+            }
+            _ => return Err(invalid_wkb(self.bytes)),
+        };
+        Ok((geometry, srid))
+    }
+}
+
+/// The WKT, prefixed with the srid like the EWKT of PostGIS, ie: `SRID=4326;POINT(1 2)`
+impl fmt::Display for Geometry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(srid) = self.srid {
+            write!(f, "SRID={};", srid)?;
+        }
+        write_wkt(f, &self.geometry)
+    }
+}
+
+fn write_wkt(f: &mut fmt::Formatter, geometry: &geo_types::Geometry<f64>) -> fmt::Result {
+    if let Some(geometry) = normalize(geometry) {
+        return write_wkt(f, &geometry);
+    }
+    /// the items separated with commas within parenthesis, or EMPTY
+    fn list<T>(
+        f: &mut fmt::Formatter,
+        items: &[T],
+        mut write_item: impl FnMut(&mut fmt::Formatter, &T) -> fmt::Result,
+    ) -> fmt::Result {
+        if items.is_empty() {
+            return write!(f, " EMPTY");
+        }
+        write!(f, "(")?;
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write_item(f, item)?;
+        }
+        write!(f, ")")
+    }
+    fn coords(f: &mut fmt::Formatter, line: &LineString<f64>) -> fmt::Result {
+        list(f, &line.0, |f, c| write!(f, "{} {}", c.x, c.y))
+    }
+    fn polygon(f: &mut fmt::Formatter, polygon: &Polygon<f64>) -> fmt::Result {
+        if polygon.exterior().0.is_empty() {
+            return write!(f, " EMPTY");
+        }
+        let rings: Vec<&LineString<f64>> =
+            Some(polygon.exterior()).into_iter().chain(polygon.interiors()).collect();
+        list(f, &rings, |f, ring| coords(f, ring))
+    }
+    match geometry {
+        geo_types::Geometry::Point(point) => write!(f, "POINT({} {})", point.x(), point.y()),
+        geo_types::Geometry::LineString(line) => {
+            write!(f, "LINESTRING")?;
+            coords(f, line)
+        }
+        geo_types::Geometry::Polygon(p) => {
+            write!(f, "POLYGON")?;
+            polygon(f, p)
+        }
+        geo_types::Geometry::MultiPoint(points) => {
+            write!(f, "MULTIPOINT")?;
+            list(f, &points.0, |f, p| write!(f, "({} {})", p.x(), p.y()))
+        }
+        geo_types::Geometry::MultiLineString(lines) => {
+            write!(f, "MULTILINESTRING")?;
+            list(f, &lines.0, coords)
+        }
+        geo_types::Geometry::MultiPolygon(polygons) => {
+            write!(f, "MULTIPOLYGON")?;
+            list(f, &polygons.0, polygon)
+        }
+        geo_types::Geometry::GeometryCollection(geometries) => {
+            write!(f, "GEOMETRYCOLLECTION")?;
+            list(f, &geometries.0, write_wkt)
+        }
+        _ => unreachable!("normalized above"),
+    }
+}
+
+/// Parse the WKT or the EWKT of PostGIS
+impl FromStr for Geometry {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ConvertError::NotSupported(s.to_string(), "Geometry".to_string());
+        let (srid, wkt) = match s.trim().split_once(';') {
+            Some((srid, wkt)) => {
+                let srid = srid
+                    .trim()
+                    .strip_prefix("SRID=")
+                    .and_then(|srid| srid.parse().ok())
+                    .ok_or_else(invalid)?;
+                (Some(srid), wkt)
+            }
+            None => (None, s),
+        };
+        let mut parser = WktParser {
+            chars: wkt.chars().peekable(),
+        };
+        let geometry = parser.geometry().ok_or_else(invalid)?;
+        parser.skip_whitespace();
+        match parser.chars.next() {
+            None => Ok(Geometry { geometry, srid }),
+            Some(_) => Err(invalid()),
+        }
+    }
+}
+
+struct WktParser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl<'a> WktParser<'a> {
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some(c) if c.is_whitespace()) {
+            self.chars.next();
+        }
+    }
+
+    /// the next token, a word or a number
+    fn word(&mut self) -> String {
+        self.skip_whitespace();
+        let mut word = String::new();
+        while let Some(c) = self.chars.peek() {
+            if c.is_alphanumeric() || "+-.".contains(*c) {
+                word.push(*c);
+                self.chars.next();
+            } else {
+                break;
+            }
+        }
+        word
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.chars.peek() == Some(&c) {
+            self.chars.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// the items within parenthesis separated with commas, none for EMPTY
+    fn list<T>(&mut self, mut item: impl FnMut(&mut Self) -> Option<T>) -> Option<Vec<T>> {
+        if !self.eat('(') {
+            return if self.word().eq_ignore_ascii_case("EMPTY") {
+                Some(vec![])
+            } else {
+                None
+            };
+        }
+        let mut items = vec![item(self)?];
+        while self.eat(',') {
+            items.push(item(self)?);
+        }
+        if self.eat(')') {
+            Some(items)
+        } else {
+            None
+        }
+    }
+
+    fn coord(&mut self) -> Option<Coord<f64>> {
+        let x = self.word().parse().ok()?;
+        let y = self.word().parse().ok()?;
+        Some(Coord { x, y })
+    }
+
+    fn coords(&mut self) -> Option<LineString<f64>> { self.list(Self::coord).map(LineString) }
+
+    fn polygon(&mut self) -> Option<Polygon<f64>> {
+        let mut rings = self.list(Self::coords)?.into_iter();
+        let exterior = rings.next().unwrap_or_else(|| LineString(vec![]));
+        Some(Polygon::new(exterior, rings.collect()))
+    }
+
+    /// the points of a multi point, with or without their parenthesis
+    fn point(&mut self) -> Option<Point<f64>> {
+        if self.eat('(') {
+            let coord = self.coord()?;
+            self.eat(')').then(|| coord.into())
+        } else {
+            self.coord().map(Point::from)
+        }
+    }
+
+    fn geometry(&mut self) -> Option<geo_types::Geometry<f64>> {
+        let geometry = match &*self.word().to_uppercase() {
+            "POINT" => {
+                let mut coords = self.list(Self::coord)?;
+                if coords.len() != 1 {
+                    return None;
+                }
+                Point::from(coords.remove(0)).into()
+            }
+            "LINESTRING" => self.coords()?.into(),
+            "POLYGON" => self.polygon()?.into(),
+            "MULTIPOINT" => MultiPoint(self.list(Self::point)?).into(),
+            "MULTILINESTRING" => MultiLineString(self.list(Self::coords)?).into(),
+            "MULTIPOLYGON" => MultiPolygon(self.list(Self::polygon)?).into(),
+            "GEOMETRYCOLLECTION" => {
+                geo_types::Geometry::GeometryCollection(GeometryCollection(
+                    self.list(Self::geometry)?,
+                ))
+            }
+            _ => return None,
+        };
+        Some(geometry)
+    }
+}
+
+impl ToValue for Geometry {
+    fn to_value(&self) -> Value { Value::Geometry(Box::new(self.clone())) }
+}
+
+impl ToValue for Point<f64> {
+    fn to_value(&self) -> Value { Value::Point(*self) }
+}
+
+macro_rules! impl_geometry_value {
+    ($($ty:ident),*) => {
+        $(
+            impl ToValue for $ty<f64> {
+                fn to_value(&self) -> Value { Geometry::new(self.clone()).to_value() }
+            }
+
+            impl FromValue for $ty<f64> {
+                fn from_value(v: &Value) -> Result<Self, ConvertError> {
+                    $ty::try_from(Geometry::from_value(v)?.geometry).map_err(|_| {
+                        ConvertError::NotSupported(v.to_string(), stringify!($ty).to_string())
+                    })
+                }
+            }
+        )*
+    };
+}
+
+impl_geometry_value!(
+    Line,
+    LineString,
+    Polygon,
+    MultiPoint,
+    MultiLineString,
+    MultiPolygon,
+    Rect
+);
+
+impl ToValue for GeometryCollection<f64> {
+    fn to_value(&self) -> Value {
+        Geometry::new(geo_types::Geometry::GeometryCollection(self.clone())).to_value()
+    }
+}
+
+impl FromValue for GeometryCollection<f64> {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match Geometry::from_value(v)?.geometry {
+            geo_types::Geometry::GeometryCollection(v) => Ok(v),
+            _ => Err(ConvertError::NotSupported(v.to_string(), "GeometryCollection".to_string())),
+        }
+    }
+}
+
+impl ToValue for geo_types::Geometry<f64> {
+    fn to_value(&self) -> Value { Geometry::new(self.clone()).to_value() }
+}
+
+/// the geometries stored as WKT in sqlite and as WKB in a blob are parsed
+impl FromValue for Geometry {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Geometry(ref v) => Ok(v.as_ref().clone()),
+            Value::Point(v) => Ok(Geometry::new(v)),
+            Value::Text(ref v) => v.parse(),
+            Value::Blob(ref v) => Geometry::from_wkb(v),
+            _ => {
+                Err(ConvertError::NotSupported(
+                    format!("{:?}", v),
+                    "Geometry".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+impl FromValue for geo_types::Geometry<f64> {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        Geometry::from_value(v).map(|v| v.geometry)
+    }
+}
+
+impl FromValue for Point<f64> {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Point(v) => Ok(v),
+            _ => {
+                Point::try_from(Geometry::from_value(v)?.geometry).map_err(|_| {
+                    ConvertError::NotSupported(v.to_string(), "Point".to_string())
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo_types::polygon;
+
+    fn geometries() -> Vec<(Geometry, &'static str)> {
+        let square = polygon![(x: 0., y: 0.), (x: 4., y: 0.), (x: 4., y: 4.), (x: 0., y: 0.)];
+        let line = LineString::from(vec![(1., 2.), (3.5, -4.)]);
+        vec![
+            (Geometry::new(Point::new(1., 2.)), "POINT(1 2)"),
+            (Geometry::new(line.clone()).with_srid(4326), "SRID=4326;LINESTRING(1 2,3.5 -4)"),
+            (Geometry::new(square.clone()), "POLYGON((0 0,4 0,4 4,0 0))"),
+            (
+                Geometry::new(MultiPoint(vec![Point::new(1., 2.), Point::new(3., 4.)])),
+                "MULTIPOINT((1 2),(3 4))",
+            ),
+            (Geometry::new(MultiLineString(vec![line.clone()])), "MULTILINESTRING((1 2,3.5 -4))"),
+            (
+                Geometry::new(MultiPolygon(vec![square])).with_srid(3857),
+                "SRID=3857;MULTIPOLYGON(((0 0,4 0,4 4,0 0)))",
+            ),
+            (
+                Geometry::new(geo_types::Geometry::GeometryCollection(GeometryCollection(vec![
+                    Point::new(1., 2.).into(),
+                    line.into(),
+                ]))),
+                "GEOMETRYCOLLECTION(POINT(1 2),LINESTRING(1 2,3.5 -4))",
+            ),
+            (
+                Geometry::new(geo_types::Geometry::GeometryCollection(GeometryCollection(vec![]))),
+                "GEOMETRYCOLLECTION EMPTY",
+            ),
+        ]
+    }
+
+    #[test]
+    fn well_known_text() {
+        for (geometry, wkt) in geometries() {
+            assert_eq!(geometry.to_string(), wkt);
+            assert_eq!(wkt.parse::<Geometry>().unwrap(), geometry);
+        }
+        assert_eq!(
+            "multipoint (1 2, 3 4)".parse::<Geometry>().unwrap(),
+            Geometry::new(MultiPoint(vec![Point::new(1., 2.), Point::new(3., 4.)]))
+        );
+        for invalid in &["POINT(1)", "POINT Z(1 2 3)", "LINESTRING(1 2", "SRID=x;POINT(1 2)"] {
+            assert!(invalid.parse::<Geometry>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn well_known_binary() {
+        for (geometry, _) in geometries() {
+            let ewkb = geometry.to_ewkb();
+            assert_eq!(Geometry::from_wkb(&ewkb).unwrap(), geometry);
+            let wkb = Geometry::from_wkb(&geometry.to_wkb()).unwrap();
+            assert_eq!(wkb.geometry, geometry.geometry);
+            assert_eq!(wkb.srid, None);
+        }
+        // POINT(1 2) in big endian
+        let mut big_endian = vec![0, 0, 0, 0, 1];
+        big_endian.extend_from_slice(&1f64.to_be_bytes());
+        big_endian.extend_from_slice(&2f64.to_be_bytes());
+        assert_eq!(Geometry::from_wkb(&big_endian).unwrap(), Geometry::new(Point::new(1., 2.)));
+        assert!(Geometry::from_wkb(&big_endian[..10]).is_err());
+        // POINT Z (1 2 3) with the Z flag of EWKB and as ISO WKB
+        let mut point_z = vec![1, 1, 0, 0, 0x80];
+        let mut iso_point_z = vec![1, 0xe9, 0x03, 0, 0];
+        for coordinate in &[1f64, 2., 3.] {
+            point_z.extend_from_slice(&coordinate.to_le_bytes());
+            iso_point_z.extend_from_slice(&coordinate.to_le_bytes());
+        }
+        assert_eq!(Geometry::from_wkb(&point_z).unwrap(), Geometry::new(Point::new(1., 2.)));
+        assert_eq!(Geometry::from_wkb(&iso_point_z).unwrap(), Geometry::new(Point::new(1., 2.)));
+        assert!(Geometry::from_wkb(&point_z[..21]).is_err());
+        // SRID=4326;LINESTRING ZM (1 2 3 4,5 6 7 8)
+        let mut line_zm = vec![1, 2, 0, 0, 0xe0];
+        line_zm.extend_from_slice(&4326u32.to_le_bytes());
+        line_zm.extend_from_slice(&2u32.to_le_bytes());
+        for coordinate in 1..=8 {
+            line_zm.extend_from_slice(&f64::from(coordinate).to_le_bytes());
+        }
+        assert_eq!(
+            Geometry::from_wkb(&line_zm).unwrap(),
+            Geometry::new(LineString::from(vec![(1., 2.), (5., 6.)])).with_srid(4326)
+        );
+    }
+
+    #[test]
+    fn geometry_values() {
+        let rect = Rect::new((0., 0.), (1., 2.));
+        let value = rect.to_value();
+        assert_eq!(value.to_string(), "POLYGON((0 0,1 0,1 2,0 2,0 0))");
+        let polygon = Polygon::from_value(&Value::Text(value.to_string())).unwrap();
+        assert_eq!(polygon, rect_to_polygon(&rect));
+        let point = Value::Blob(Geometry::new(Point::new(1., 2.)).to_wkb());
+        assert_eq!(Point::from_value(&point).unwrap(), Point::new(1., 2.));
+        assert!(LineString::<f64>::from_value(&point).is_err());
+    }
+}
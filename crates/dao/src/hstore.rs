@@ -0,0 +1,62 @@
+use crate::{
+    value::{
+        FromValue,
+        ToValue,
+        Value,
+    },
+    ConvertError,
+};
+use std::collections::{
+    BTreeMap,
+    HashMap,
+};
+
+/// the keys with their optional values of a postgresql `hstore`
+impl ToValue for BTreeMap<String, Option<String>> {
+    fn to_value(&self) -> Value { Value::Hstore(self.clone()) }
+}
+
+impl ToValue for HashMap<String, Option<String>> {
+    fn to_value(&self) -> Value {
+        Value::Hstore(self.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+    }
+}
+
+/// the hstores stored as a json object in sqlite and mysql are parsed
+impl FromValue for BTreeMap<String, Option<String>> {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        let not_supported = || ConvertError::NotSupported(format!("{:?}", v), "hstore".into());
+        match *v {
+            Value::Hstore(ref v) => Ok(v.clone()),
+            Value::Json(_) | Value::Text(_) => {
+                let json = serde_json::Value::from_value(v)?;
+                serde_json::from_value(json).map_err(|_| not_supported())
+            }
+            _ => Err(not_supported()),
+        }
+    }
+}
+
+impl FromValue for HashMap<String, Option<String>> {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        BTreeMap::from_value(v).map(|map| map.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hstores() {
+        let mut map = HashMap::new();
+        map.insert("a".to_string(), Some("1".to_string()));
+        map.insert("b".to_string(), None);
+        let value = map.to_value();
+        assert_eq!(value.to_string(), r#"{"a":"1","b":null}"#);
+        assert_eq!(HashMap::from_value(&value).unwrap(), map);
+        let text = Value::Text(value.to_string());
+        assert_eq!(HashMap::from_value(&text).unwrap(), map);
+        assert!(BTreeMap::from_value(&Value::Text(r#"{"a":1}"#.into())).is_err());
+    }
+}
@@ -1,8 +1,33 @@
+use crate::{
+    value::{
+        FromValue,
+        ToValue,
+        Value,
+    },
+    ConvertError,
+};
+use chrono::Timelike;
 use serde_derive::{
     Deserialize,
     Serialize,
 };
+use std::{
+    convert::TryFrom,
+    fmt,
+    str::FromStr,
+    time::Duration,
+};
 
+const MICROS_PER_SECOND: i64 = 1_000_000;
+const MICROS_PER_MINUTE: i64 = 60 * MICROS_PER_SECOND;
+const MICROS_PER_HOUR: i64 = 60 * MICROS_PER_MINUTE;
+const MICROS_PER_DAY: i64 = 24 * MICROS_PER_HOUR;
+
+/// A duration in months, days and microseconds, like the postgresql interval.
+///
+/// The months and days are kept apart from the microseconds since their length depends on the
+/// date they are added to. The conversions into `chrono::Duration` and `std::time::Duration`
+/// take a day to be 24 hours and fail when there are months.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Interval {
     pub microseconds: i64,
@@ -18,4 +43,284 @@ impl Interval {
             months,
         }
     }
+
+    /// the microseconds of the days and the time, none when there are months or on overflow
+    fn total_microseconds(&self) -> Option<i64> {
+        if self.months != 0 {
+            return None;
+        }
+        i64::from(self.days)
+            .checked_mul(MICROS_PER_DAY)
+            .and_then(|days| days.checked_add(self.microseconds))
+    }
+
+    fn not_supported(&self, ty: &str) -> ConvertError {
+        ConvertError::NotSupported(self.to_string(), ty.to_string())
+    }
+}
+
+/// The ISO 8601 duration, ie: `P1Y2M3DT4H5M6.5S`, each part has its own sign like the
+/// `iso_8601` interval style of postgresql
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.months == 0 && self.days == 0 && self.microseconds == 0 {
+            return write!(f, "PT0S");
+        }
+        write!(f, "P")?;
+        let (years, months) = (self.months / 12, self.months % 12);
+        for (value, unit) in [(years, 'Y'), (months, 'M'), (self.days, 'D')].iter() {
+            if *value != 0 {
+                write!(f, "{}{}", value, unit)?;
+            }
+        }
+        if self.microseconds != 0 {
+            write!(f, "T")?;
+            let micros = self.microseconds;
+            let hours = micros / MICROS_PER_HOUR;
+            let minutes = micros % MICROS_PER_HOUR / MICROS_PER_MINUTE;
+            let seconds = micros % MICROS_PER_MINUTE;
+            for (value, unit) in [(hours, 'H'), (minutes, 'M')].iter() {
+                if *value != 0 {
+                    write!(f, "{}{}", value, unit)?;
+                }
+            }
+            if seconds != 0 {
+                let sign = if seconds < 0 { "-" } else { "" };
+                let seconds = seconds.abs();
+                let fraction = seconds % MICROS_PER_SECOND;
+                write!(f, "{}{}", sign, seconds / MICROS_PER_SECOND)?;
+                if fraction != 0 {
+                    let fraction = format!("{:06}", fraction);
+                    write!(f, ".{}", fraction.trim_end_matches('0'))?;
+                }
+                write!(f, "S")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parse an ISO 8601 duration, the parts can be negative and only the seconds can have
+/// a fraction. Fails when a part overflows
+impl FromStr for Interval {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ConvertError::NotSupported(s.to_string(), "Interval".to_string());
+        let rest = s.strip_prefix('P').ok_or_else(invalid)?;
+        if rest.is_empty() {
+            return Err(invalid());
+        }
+        let mut interval = Interval::new(0, 0, 0);
+        let mut in_time = false;
+        let mut number = String::new();
+        for c in rest.chars() {
+            match c {
+                'T' if !in_time && number.is_empty() => in_time = true,
+                '0'..='9' | '-' | '+' | '.' | ',' => number.push(if c == ',' { '.' } else { c }),
+                unit => {
+                    if unit == 'S' && in_time {
+                        let seconds: f64 = number.parse().map_err(|_| invalid())?;
+                        let micros = (seconds * MICROS_PER_SECOND as f64).round();
+                        if !(i64::MIN as f64..i64::MAX as f64).contains(&micros) {
+                            return Err(invalid());
+                        }
+                        interval.microseconds = interval
+                            .microseconds
+                            .checked_add(micros as i64)
+                            .ok_or_else(invalid)?;
+                    } else {
+                        let value: i32 = number.parse().map_err(|_| invalid())?;
+                        match (in_time, unit) {
+                            (false, 'Y') => {
+                                interval.months = value
+                                    .checked_mul(12)
+                                    .and_then(|months| interval.months.checked_add(months))
+                                    .ok_or_else(invalid)?
+                            }
+                            (false, 'M') => {
+                                interval.months =
+                                    interval.months.checked_add(value).ok_or_else(invalid)?
+                            }
+                            (false, 'W') => {
+                                interval.days = value
+                                    .checked_mul(7)
+                                    .and_then(|days| interval.days.checked_add(days))
+                                    .ok_or_else(invalid)?
+                            }
+                            (false, 'D') => {
+                                interval.days =
+                                    interval.days.checked_add(value).ok_or_else(invalid)?
+                            }
+                            (true, 'H') | (true, 'M') => {
+                                let unit_micros =
+                                    if unit == 'H' { MICROS_PER_HOUR } else { MICROS_PER_MINUTE };
+                                interval.microseconds = i64::from(value)
+                                    .checked_mul(unit_micros)
+                                    .and_then(|micros| interval.microseconds.checked_add(micros))
+                                    .ok_or_else(invalid)?
+                            }
+                            _ => return Err(invalid()),
+                        }
+                    }
+                    number.clear();
+                }
+            }
+        }
+        if number.is_empty() && !rest.ends_with('T') {
+            Ok(interval)
+        } else {
+            Err(invalid())
+        }
+    }
+}
+
+impl TryFrom<chrono::Duration> for Interval {
+    type Error = ConvertError;
+
+    /// the duration in microseconds, fails when it does not fit in 64 bits
+    fn try_from(duration: chrono::Duration) -> Result<Self, Self::Error> {
+        duration
+            .num_microseconds()
+            .map(|micros| Interval::new(micros, 0, 0))
+            .ok_or_else(|| ConvertError::NotSupported(duration.to_string(), "Interval".into()))
+    }
+}
+
+impl TryFrom<Duration> for Interval {
+    type Error = ConvertError;
+
+    /// the duration in microseconds, fails when it does not fit in 64 bits
+    fn try_from(duration: Duration) -> Result<Self, Self::Error> {
+        i64::try_from(duration.as_micros())
+            .map(|micros| Interval::new(micros, 0, 0))
+            .map_err(|_| ConvertError::NotSupported(format!("{:?}", duration), "Interval".into()))
+    }
+}
+
+impl TryFrom<Interval> for chrono::Duration {
+    type Error = ConvertError;
+
+    /// a day is 24 hours, fails when there are months
+    fn try_from(interval: Interval) -> Result<Self, Self::Error> {
+        interval
+            .total_microseconds()
+            .map(chrono::Duration::microseconds)
+            .ok_or_else(|| interval.not_supported("chrono::Duration"))
+    }
+}
+
+impl TryFrom<Interval> for Duration {
+    type Error = ConvertError;
+
+    /// a day is 24 hours, fails when there are months or when it is negative
+    fn try_from(interval: Interval) -> Result<Self, Self::Error> {
+        match interval.total_microseconds() {
+            Some(micros) if micros >= 0 => Ok(Duration::from_micros(micros as u64)),
+            _ => Err(interval.not_supported("std::time::Duration")),
+        }
+    }
+}
+
+impl ToValue for Interval {
+    fn to_value(&self) -> Value { Value::Interval(self.clone()) }
+}
+
+/// the intervals stored as ISO 8601 text in sqlite and as TIME in mysql
+impl FromValue for Interval {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Interval(ref v) => Ok(v.clone()),
+            Value::Text(ref v) => v.parse(),
+            Value::Time(v) => {
+                let micros = i64::from(v.num_seconds_from_midnight()) * MICROS_PER_SECOND
+                    + i64::from(v.nanosecond() / 1000);
+                Ok(Interval::new(micros, 0, 0))
+            }
+            _ => {
+                Err(ConvertError::NotSupported(
+                    format!("{:?}", v),
+                    "Interval".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+impl FromValue for chrono::Duration {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        chrono::Duration::try_from(Interval::from_value(v)?)
+    }
+}
+
+impl FromValue for Duration {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        Duration::try_from(Interval::from_value(v)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iso_8601() {
+        let cases = [
+            (Interval::new(0, 0, 0), "PT0S"),
+            (Interval::new(0, 3, 14), "P1Y2M3D"),
+            (
+                Interval::new(4 * MICROS_PER_HOUR + 5 * MICROS_PER_MINUTE + 6_500_000, 0, 0),
+                "PT4H5M6.5S",
+            ),
+            (Interval::new(-1_250_000, -1, 0), "P-1DT-1.25S"),
+            (Interval::new(-MICROS_PER_HOUR - 1, 0, -13), "P-1Y-1MT-1H-0.000001S"),
+        ];
+        for (interval, text) in cases.iter() {
+            assert_eq!(interval.to_string(), *text);
+            assert_eq!(text.parse::<Interval>().unwrap(), *interval);
+            let displayed = Value::Text(interval.to_value().to_string());
+            assert_eq!(Interval::from_value(&displayed).unwrap(), *interval);
+        }
+        assert_eq!("P2W".parse::<Interval>().unwrap(), Interval::new(0, 14, 0));
+        assert_eq!(
+            "PT0,5S".parse::<Interval>().unwrap(),
+            Interval::new(500_000, 0, 0)
+        );
+        for invalid in &["", "P", "PT", "1D", "P1", "P1H", "PT1D", "P1.5D"] {
+            assert!(invalid.parse::<Interval>().is_err(), "{}", invalid);
+        }
+        let overflows = [
+            "P200000000Y",
+            "P2000000000M1000000000M",
+            "P400000000W",
+            "PT2562048000H",
+            "PT10000000000000S",
+        ];
+        for overflow in overflows.iter() {
+            assert!(overflow.parse::<Interval>().is_err(), "{}", overflow);
+        }
+    }
+
+    #[test]
+    fn durations() {
+        let interval = Interval::new(1_500_000, 1, 0);
+        let duration = chrono::Duration::try_from(interval.clone()).unwrap();
+        assert_eq!(duration, chrono::Duration::days(1) + chrono::Duration::milliseconds(1500));
+        assert_eq!(
+            Duration::try_from(interval).unwrap(),
+            Duration::from_millis(86_401_500)
+        );
+        assert!(chrono::Duration::try_from(Interval::new(0, 0, 1)).is_err());
+        assert!(Duration::try_from(Interval::new(-1, 0, 0)).is_err());
+        assert_eq!(
+            Interval::try_from(Duration::from_secs(90)).unwrap(),
+            Interval::new(90_000_000, 0, 0)
+        );
+        assert!(Interval::try_from(chrono::Duration::milliseconds(i64::MAX)).is_err());
+        let value = Interval::new(0, 2, 0).to_value();
+        assert_eq!(
+            Duration::from_value(&Value::Text("P2D".into())).unwrap(),
+            Duration::from_value(&value).unwrap()
+        );
+    }
 }
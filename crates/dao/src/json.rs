@@ -0,0 +1,165 @@
+use crate::{
+    value::{
+        FromValue,
+        ToValue,
+        Value,
+    },
+    ConvertError,
+};
+use serde::{
+    de::DeserializeOwned,
+    Serialize,
+};
+use std::ops::{
+    Deref,
+    DerefMut,
+};
+
+/// A value stored as json, to use any serializable type as a field of a `ToDao` and `FromDao`
+/// struct
+///
+/// ```rust
+/// # use clia_rustorm_dao::{Json, FromValue, ToValue};
+/// # use std::collections::BTreeMap;
+/// let settings: Json<BTreeMap<String, i32>> = Json(BTreeMap::new());
+/// let value = settings.to_value();
+/// assert_eq!(Json::from_value(&value).unwrap(), settings);
+/// ```
+#[derive(serde_derive::Serialize, serde_derive::Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(transparent)]
+pub struct Json<T>(pub T);
+
+impl<T> Json<T> {
+    pub fn into_inner(self) -> T { self.0 }
+}
+
+impl<T> Json<T>
+where
+    T: Serialize,
+{
+    /// the json value, or the error when it can not be serialized as json
+    pub fn try_to_value(&self) -> Result<Value, ConvertError> {
+        serde_json::to_value(&self.0)
+            .map(Value::Json)
+            .map_err(|e| ConvertError::NotSupported(e.to_string(), "Json".to_string()))
+    }
+}
+
+impl<T> Deref for Json<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T { &self.0 }
+}
+
+impl<T> DerefMut for Json<T> {
+    fn deref_mut(&mut self) -> &mut T { &mut self.0 }
+}
+
+impl<T> From<T> for Json<T> {
+    fn from(v: T) -> Self { Json(v) }
+}
+
+/// panics when the value can not be serialized as json, ie: a map with non string keys,
+/// rather than writing a null over the stored data. Use `Json::try_to_value` to get the error
+impl<T> ToValue for Json<T>
+where
+    T: Serialize,
+{
+    fn to_value(&self) -> Value {
+        match self.try_to_value() {
+            Ok(value) => value,
+            Err(e) => panic!("{}, use Json::try_to_value to handle the error", e),
+        }
+    }
+}
+
+impl<T> FromValue for Json<T>
+where
+    T: DeserializeOwned,
+{
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        let json = serde_json::Value::from_value(v)?;
+        serde_json::from_value(json)
+            .map(Json)
+            .map_err(|e| ConvertError::NotSupported(v.to_string(), format!("Json: {}", e)))
+    }
+}
+
+impl ToValue for serde_json::Value {
+    fn to_value(&self) -> Value { Value::Json(self.clone()) }
+}
+
+/// the json stored as text in sqlite and mysql is parsed
+impl FromValue for serde_json::Value {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Json(ref v) => Ok(v.clone()),
+            Value::Text(ref v) => {
+                serde_json::from_str(v).map_err(|e| {
+                    ConvertError::NotSupported(v.to_string(), format!("serde_json::Value: {}", e))
+                })
+            }
+            _ => {
+                Err(ConvertError::NotSupported(
+                    format!("{:?}", v),
+                    "serde_json::Value".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_derive::Deserialize;
+    use serde_json::json;
+    use std::collections::BTreeMap;
+
+    #[derive(serde_derive::Serialize, Deserialize, Debug, PartialEq)]
+    struct Settings {
+        theme: String,
+        font_size: u8,
+    }
+
+    #[test]
+    fn json_values() {
+        let json = json!({"a": [1, 2, null]});
+        let value = json.to_value();
+        assert_eq!(value, Value::Json(json.clone()));
+        assert_eq!(value.to_string(), r#"{"a":[1,2,null]}"#);
+        assert_eq!(serde_json::Value::from_value(&value).unwrap(), json);
+        let text = Value::Text(r#"{"a": [1, 2, null]}"#.into());
+        assert_eq!(serde_json::Value::from_value(&text).unwrap(), json);
+        assert!(serde_json::Value::from_value(&Value::Text("{".into())).is_err());
+    }
+
+    #[test]
+    fn json_wrapper() {
+        let settings = Json(Settings {
+            theme: "dark".into(),
+            font_size: 12,
+        });
+        let value = settings.to_value();
+        assert_eq!(value, Value::Json(json!({"theme": "dark", "font_size": 12})));
+        assert_eq!(Json::<Settings>::from_value(&value).unwrap(), settings);
+        let invalid = Value::Json(json!({"theme": "dark"}));
+        assert!(Json::<Settings>::from_value(&invalid).is_err());
+    }
+
+    #[test]
+    fn json_serialize_error() {
+        let mut map = BTreeMap::new();
+        map.insert((1, 2), "a");
+        let json = Json(map);
+        assert!(json.try_to_value().is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "use Json::try_to_value")]
+    fn json_serialize_panic() {
+        let mut map = BTreeMap::new();
+        map.insert((1, 2), "a");
+        Json(map).to_value();
+    }
+}
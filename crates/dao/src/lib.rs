@@ -14,12 +14,26 @@ pub use error::{
     ConvertError,
     DaoError,
 };
+pub use bits::BitString;
+pub use composite::Composite;
+pub use custom::Custom;
+pub use geometry::Geometry;
 pub use interval::Interval;
+pub use json::Json;
+pub use network::{
+    IpNetwork,
+    MacAddress,
+};
+pub use range::{
+    Range,
+    RangeBound,
+};
 pub use rows::Rows;
 pub use table_name::{
     TableName,
     ToTableName,
 };
+pub use array::ArrayElement;
 pub use value::{
     Array,
     FromValue,
@@ -27,11 +41,22 @@ pub use value::{
     Value,
 };
 
+mod array;
+mod bits;
 mod column_name;
-mod common;
+mod composite;
+mod custom;
 mod dao;
+#[cfg(feature = "with-rust-decimal")]
+mod decimal;
 mod error;
+mod geometry;
+mod hstore;
 mod interval;
+mod json;
+mod network;
+mod range;
 mod rows;
 mod table_name;
+mod temporal;
 pub mod value;
@@ -0,0 +1,204 @@
+//! The network addresses of postgresql, `inet`, `cidr` and `macaddr`.
+use crate::{
+    value::{
+        FromValue,
+        ToValue,
+        Value,
+    },
+    ConvertError,
+};
+use serde_derive::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    fmt,
+    net::IpAddr,
+    str::FromStr,
+};
+
+/// An ip address with the length of its network prefix, ie: `192.168.0.1/24`
+///
+/// The `inet` columns are read as `Value::Inet` and the `cidr` columns as `Value::Cidr`, a
+/// `cidr` has no bits set at the right of its prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct IpNetwork {
+    pub addr: IpAddr,
+    pub prefix: u8,
+}
+
+impl IpNetwork {
+    /// fails when the prefix is longer than the address
+    pub fn new(addr: IpAddr, prefix: u8) -> Result<Self, ConvertError> {
+        if prefix > max_prefix(&addr) {
+            Err(ConvertError::NotSupported(
+                format!("{}/{}", addr, prefix),
+                "IpNetwork".to_string(),
+            ))
+        } else {
+            Ok(IpNetwork { addr, prefix })
+        }
+    }
+
+    /// whether the prefix covers the whole address
+    pub fn is_host(&self) -> bool { self.prefix == max_prefix(&self.addr) }
+}
+
+fn max_prefix(addr: &IpAddr) -> u8 {
+    match addr {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+/// a single host
+impl From<IpAddr> for IpNetwork {
+    fn from(addr: IpAddr) -> Self {
+        IpNetwork {
+            addr,
+            prefix: max_prefix(&addr),
+        }
+    }
+}
+
+/// the prefix is omitted for a single host, like the `inet` of postgresql
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_host() {
+            write!(f, "{}", self.addr)
+        } else {
+            write!(f, "{}/{}", self.addr, self.prefix)
+        }
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ConvertError::NotSupported(s.to_string(), "IpNetwork".to_string());
+        match s.split_once('/') {
+            Some((addr, prefix)) => {
+                let addr = addr.parse().map_err(|_| invalid())?;
+                let prefix = prefix.parse().map_err(|_| invalid())?;
+                IpNetwork::new(addr, prefix)
+            }
+            None => s.parse::<IpAddr>().map(IpNetwork::from).map_err(|_| invalid()),
+        }
+    }
+}
+
+impl ToValue for IpNetwork {
+    fn to_value(&self) -> Value { Value::Inet(*self) }
+}
+
+impl ToValue for IpAddr {
+    fn to_value(&self) -> Value { Value::Inet(IpNetwork::from(*self)) }
+}
+
+/// the addresses stored as text in sqlite and mysql are parsed
+impl FromValue for IpNetwork {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Inet(v) | Value::Cidr(v) => Ok(v),
+            Value::Text(ref v) => v.parse(),
+            _ => {
+                Err(ConvertError::NotSupported(
+                    format!("{:?}", v),
+                    "IpNetwork".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+/// the prefix of the network is dropped
+impl FromValue for IpAddr {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        IpNetwork::from_value(v).map(|v| v.addr)
+    }
+}
+
+/// A mac address, ie: `08:00:2b:01:02:03`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MacAddress(pub [u8; 6]);
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(
+            f,
+            "{:02x}:{:02x}:{:02x}:{:02x}:{:02x}:{:02x}",
+            a, b, c, d, e, g
+        )
+    }
+}
+
+/// the bytes can be separated with `:` or `-`
+impl FromStr for MacAddress {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ConvertError::NotSupported(s.to_string(), "MacAddress".to_string());
+        let mut bytes = [0; 6];
+        let mut parts = s.split([':', '-']);
+        for byte in bytes.iter_mut() {
+            let part = parts.next().filter(|part| part.len() == 2).ok_or_else(invalid)?;
+            *byte = u8::from_str_radix(part, 16).map_err(|_| invalid())?;
+        }
+        match parts.next() {
+            Some(_) => Err(invalid()),
+            None => Ok(MacAddress(bytes)),
+        }
+    }
+}
+
+impl ToValue for MacAddress {
+    fn to_value(&self) -> Value { Value::MacAddr(*self) }
+}
+
+impl FromValue for MacAddress {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::MacAddr(v) => Ok(v),
+            Value::Text(ref v) => v.parse(),
+            _ => {
+                Err(ConvertError::NotSupported(
+                    format!("{:?}", v),
+                    "MacAddress".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ip_networks() {
+        let network: IpNetwork = "192.168.0.0/24".parse().unwrap();
+        assert_eq!(network.prefix, 24);
+        assert_eq!(network.to_string(), "192.168.0.0/24");
+        let host: IpNetwork = "::1".parse().unwrap();
+        assert!(host.is_host());
+        assert_eq!(host.to_string(), "::1");
+        assert_eq!(IpAddr::from_value(&Value::Cidr(network)).unwrap(), network.addr);
+        for invalid in &["10.0.0.1/33", "10.0.0/8", "::1/129", "10.0.0.1/"] {
+            assert!(invalid.parse::<IpNetwork>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn mac_addresses() {
+        let mac: MacAddress = "08:00:2B:01:02:03".parse().unwrap();
+        assert_eq!(mac, MacAddress([8, 0, 0x2b, 1, 2, 3]));
+        assert_eq!(mac.to_string(), "08:00:2b:01:02:03");
+        assert_eq!("08-00-2b-01-02-03".parse::<MacAddress>().unwrap(), mac);
+        assert_eq!(MacAddress::from_value(&Value::Text(mac.to_string())).unwrap(), mac);
+        for invalid in &["08:00:2b:01:02", "08:00:2b:01:02:03:04", "8:0:2b:1:2:3"] {
+            assert!(invalid.parse::<MacAddress>().is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+use crate::{
+    value::{
+        FromValue,
+        ToValue,
+        Value,
+    },
+    ConvertError,
+};
+use serde_derive::{
+    Deserialize,
+    Serialize,
+};
+use std::{
+    fmt,
+    ops,
+    str::FromStr,
+};
+
+/// A range of values like the range types of postgresql, ie: `int4range`, `tstzrange`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Range {
+    Empty,
+    Bounds(RangeBound, RangeBound),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RangeBound {
+    Inclusive(Value),
+    Exclusive(Value),
+    Unbounded,
+}
+
+impl Range {
+    pub fn is_empty(&self) -> bool { *self == Range::Empty }
+}
+
+impl RangeBound {
+    pub fn value(&self) -> Option<&Value> {
+        match self {
+            RangeBound::Inclusive(v) | RangeBound::Exclusive(v) => Some(v),
+            RangeBound::Unbounded => None,
+        }
+    }
+}
+
+/// the text format of postgresql, ie: `[1,10)`, `(,2021-01-31]` or `empty`
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Range::Empty => write!(f, "empty"),
+            Range::Bounds(lower, upper) => {
+                let open = if let RangeBound::Inclusive(_) = lower { '[' } else { '(' };
+                let close = if let RangeBound::Inclusive(_) = upper { ']' } else { ')' };
+                write!(f, "{}", open)?;
+                write_bound(f, lower)?;
+                write!(f, ",")?;
+                write_bound(f, upper)?;
+                write!(f, "{}", close)
+            }
+        }
+    }
+}
+
+/// the bounds with special characters are quoted
+fn write_bound(f: &mut fmt::Formatter, bound: &RangeBound) -> fmt::Result {
+    let value = match bound.value() {
+        Some(value) => value.to_string(),
+        None => return Ok(()),
+    };
+    let special = |c: char| "\"\\,()[] ".contains(c) || c.is_whitespace();
+    if value.is_empty() || value.contains(special) {
+        let escaped = value.replace('\\', "\\\\").replace('"', "\\\"");
+        write!(f, "\"{}\"", escaped)
+    } else {
+        write!(f, "{}", value)
+    }
+}
+
+/// Parse the text format of postgresql, the bounds are parsed as `Value::Text`
+impl FromStr for Range {
+    type Err = ConvertError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ConvertError::NotSupported(s.to_string(), "Range".to_string());
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("empty") {
+            return Ok(Range::Empty);
+        }
+        let mut chars = s.chars();
+        let lower_inclusive = match chars.next() {
+            Some('[') => true,
+            Some('(') => false,
+            _ => return Err(invalid()),
+        };
+        let upper_inclusive = match chars.next_back() {
+            Some(']') => true,
+            Some(')') => false,
+            _ => return Err(invalid()),
+        };
+        let (lower, upper) = split_bounds(chars.as_str()).ok_or_else(invalid)?;
+        let bound = |value: Option<String>, inclusive: bool| {
+            match value {
+                None => RangeBound::Unbounded,
+                Some(v) if inclusive => RangeBound::Inclusive(Value::Text(v)),
+                Some(v) => RangeBound::Exclusive(Value::Text(v)),
+            }
+        };
+        Ok(Range::Bounds(
+            bound(lower, lower_inclusive),
+            bound(upper, upper_inclusive),
+        ))
+    }
+}
+
+/// split the lower and upper bounds at the comma outside of the quotes, an empty bound is
+/// unbounded
+fn split_bounds(s: &str) -> Option<(Option<String>, Option<String>)> {
+    let mut bounds = vec![];
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => current.push(chars.next()?),
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                current.push('"');
+            }
+            '"' => {
+                in_quotes = !in_quotes;
+                quoted = true;
+            }
+            ',' if !in_quotes => {
+                bounds.push((!current.is_empty() || quoted).then(|| current.clone()));
+                current.clear();
+                quoted = false;
+            }
+            c => current.push(c),
+        }
+    }
+    bounds.push((!current.is_empty() || quoted).then_some(current));
+    match (in_quotes, bounds.as_slice()) {
+        (false, [lower, upper]) => Some((lower.clone(), upper.clone())),
+        _ => None,
+    }
+}
+
+impl ToValue for Range {
+    fn to_value(&self) -> Value { Value::Range(Box::new(self.clone())) }
+}
+
+/// the ranges stored as text in sqlite and mysql are parsed
+impl FromValue for Range {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Range(ref v) => Ok(v.as_ref().clone()),
+            Value::Text(ref v) => v.parse(),
+            _ => Err(ConvertError::NotSupported(format!("{:?}", v), "Range".to_string())),
+        }
+    }
+}
+
+/// the start is inclusive and the end exclusive, the canonical form of the discrete ranges
+impl<T> ToValue for ops::Range<T>
+where
+    T: ToValue,
+{
+    fn to_value(&self) -> Value {
+        Range::Bounds(
+            RangeBound::Inclusive(self.start.to_value()),
+            RangeBound::Exclusive(self.end.to_value()),
+        )
+        .to_value()
+    }
+}
+
+impl<T> FromValue for ops::Range<T>
+where
+    T: FromValue,
+{
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match Range::from_value(v)? {
+            Range::Bounds(RangeBound::Inclusive(start), RangeBound::Exclusive(end)) => {
+                Ok(T::from_value(&start)?..T::from_value(&end)?)
+            }
+            range => Err(ConvertError::NotSupported(range.to_string(), "Range".to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn range_text() {
+        let range = (1..10).to_value();
+        assert_eq!(range.to_string(), "[1,10)");
+        assert_eq!(Range::Empty.to_string(), "empty");
+        let unbounded = Range::Bounds(
+            RangeBound::Unbounded,
+            RangeBound::Inclusive(Value::Text("a \"b\"".into())),
+        );
+        assert_eq!(unbounded.to_string(), r#"(,"a \"b\""]"#);
+        assert_eq!(unbounded.to_string().parse::<Range>().unwrap(), unbounded);
+        assert_eq!(
+            r#"["2021-01-01 10:00:00+00","")"#.parse::<Range>().unwrap(),
+            Range::Bounds(
+                RangeBound::Inclusive(Value::Text("2021-01-01 10:00:00+00".into())),
+                RangeBound::Exclusive(Value::Text("".into())),
+            )
+        );
+        for invalid in &["", "[1,2", "1,2)", "[1,2,3)", "[\"1,2)"] {
+            assert!(invalid.parse::<Range>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn std_ranges() {
+        let start = NaiveDate::from_ymd_opt(2021, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2021, 2, 1).unwrap();
+        let text = Value::Text((start..end).to_value().to_string());
+        assert_eq!(text, Value::Text("[2021-01-01,2021-02-01)".into()));
+        assert_eq!(ops::Range::<NaiveDate>::from_value(&text).unwrap(), start..end);
+        let inclusive = Range::Bounds(
+            RangeBound::Inclusive(Value::Int(1)),
+            RangeBound::Inclusive(Value::Int(2)),
+        );
+        assert!(ops::Range::<i32>::from_value(&inclusive.to_value()).is_err());
+    }
+}
@@ -32,7 +32,7 @@ impl Rows {
     pub fn push(&mut self, row: Vec<Value>) { self.data.push(row) }
 
     /// Returns an iterator over the `Row`s.
-    pub fn iter(&self) -> Iter {
+    pub fn iter(&self) -> Iter<'_> {
         Iter {
             columns: self.columns.clone(),
             iter: self.data.iter(),
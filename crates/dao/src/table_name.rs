@@ -1,4 +1,3 @@
-use crate::common;
 use serde_derive::{
     Deserialize,
     Serialize,
@@ -41,22 +40,14 @@ impl TableName {
 
     pub fn name(&self) -> String { self.name.to_owned() }
 
-    pub fn safe_name(&self) -> String { common::keywords_safe(&self.name) }
-
-    /// return the long name of the table using schema.table_name
+    /// return the long name of the table using schema.table_name, to be displayed.
+    /// Use `identifier::Dialect::table_name` of rustorm for the name in a sql statement
     pub fn complete_name(&self) -> String {
         match self.schema {
             Some(ref schema) => format!("{}.{}", schema, self.name),
             None => self.name.to_owned(),
         }
     }
-
-    pub fn safe_complete_name(&self) -> String {
-        match self.schema {
-            Some(ref schema) => format!("{}.{}", schema, self.safe_name()),
-            None => self.name.to_owned(),
-        }
-    }
 }
 
 impl Hash for TableName {
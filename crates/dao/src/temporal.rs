@@ -0,0 +1,258 @@
+//! Conversions of the temporal values with an offset and of the types of the `time` crate.
+use crate::{
+    value::{
+        parse_naive_date_time,
+        FromValue,
+        ToValue,
+        Value,
+    },
+    ConvertError,
+};
+use chrono::{
+    DateTime,
+    Datelike,
+    FixedOffset,
+    NaiveDate,
+    NaiveDateTime,
+    NaiveTime,
+    TimeZone,
+    Timelike,
+};
+use std::convert::TryFrom;
+
+fn not_supported(v: &Value, ty: &str) -> ConvertError {
+    ConvertError::NotSupported(format!("{:?}", v), ty.to_string())
+}
+
+/// a timestamp without an offset is taken to be in utc
+impl FromValue for DateTime<FixedOffset> {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        let utc = FixedOffset::east_opt(0).expect("utc offset");
+        match *v {
+            Value::TimestampTz(v) => Ok(v),
+            Value::Timestamp(v) => Ok(v.with_timezone(&utc)),
+            Value::DateTime(v) => Ok(utc.from_utc_datetime(&v)),
+            Value::Text(ref text) => {
+                DateTime::parse_from_rfc3339(text)
+                    .or_else(|_| parse_naive_date_time(text).map(|v| utc.from_utc_datetime(&v)))
+                    .map_err(|_| not_supported(v, "DateTime<FixedOffset>"))
+            }
+            _ => Err(not_supported(v, "DateTime<FixedOffset>")),
+        }
+    }
+}
+
+/// the offset of a time with time zone is dropped
+impl FromValue for NaiveTime {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Time(v) => Ok(v),
+            Value::TimeTz(v, _) => Ok(v),
+            Value::Text(ref text) => {
+                NaiveTime::parse_from_str(text, "%H:%M:%S%.f")
+                    .map_err(|_| not_supported(v, "NaiveTime"))
+            }
+            _ => Err(not_supported(v, "NaiveTime")),
+        }
+    }
+}
+
+/// a time of day with its offset from utc
+impl ToValue for (NaiveTime, FixedOffset) {
+    fn to_value(&self) -> Value { Value::TimeTz(self.0, self.1) }
+}
+
+impl FromValue for (NaiveTime, FixedOffset) {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::TimeTz(time, offset) => Ok((time, offset)),
+            Value::Time(time) => Ok((time, FixedOffset::east_opt(0).expect("utc offset"))),
+            // ie: 10:20:30.5+05:30, the offset is parsed with a date as chrono only parses
+            // the offsets of timestamps
+            Value::Text(ref text) => {
+                let text = format!("1970-01-01 {}", text);
+                DateTime::parse_from_str(&text, "%Y-%m-%d %H:%M:%S%.f%:z")
+                    .map(|v| (v.time(), *v.offset()))
+                    .map_err(|_| not_supported(v, "(NaiveTime, FixedOffset)"))
+            }
+            _ => Err(not_supported(v, "(NaiveTime, FixedOffset)")),
+        }
+    }
+}
+
+fn from_time_date(date: time::Date) -> NaiveDate {
+    NaiveDate::from_ymd_opt(date.year(), u8::from(date.month()) as u32, date.day() as u32)
+        .expect("the dates of time are valid dates of chrono")
+}
+
+fn from_time_time(time: time::Time) -> NaiveTime {
+    NaiveTime::from_hms_nano_opt(
+        time.hour() as u32,
+        time.minute() as u32,
+        time.second() as u32,
+        time.nanosecond(),
+    )
+    .expect("the times of time are valid times of chrono")
+}
+
+fn to_time_date(date: NaiveDate) -> Result<time::Date, ConvertError> {
+    let month = time::Month::try_from(date.month() as u8);
+    month
+        .and_then(|month| time::Date::from_calendar_date(date.year(), month, date.day() as u8))
+        .map_err(|e| ConvertError::NotSupported(date.to_string(), format!("time::Date: {}", e)))
+}
+
+fn to_time_time(v: NaiveTime) -> Result<time::Time, ConvertError> {
+    time::Time::from_hms_nano(
+        v.hour() as u8,
+        v.minute() as u8,
+        v.second() as u8,
+        v.nanosecond(),
+    )
+    .map_err(|e| ConvertError::NotSupported(v.to_string(), format!("time::Time: {}", e)))
+}
+
+fn to_primitive_date_time(v: NaiveDateTime) -> Result<time::PrimitiveDateTime, ConvertError> {
+    Ok(time::PrimitiveDateTime::new(
+        to_time_date(v.date())?,
+        to_time_time(v.time())?,
+    ))
+}
+
+impl ToValue for time::Date {
+    fn to_value(&self) -> Value { Value::Date(from_time_date(*self)) }
+}
+
+impl ToValue for time::Time {
+    fn to_value(&self) -> Value { Value::Time(from_time_time(*self)) }
+}
+
+impl ToValue for time::PrimitiveDateTime {
+    fn to_value(&self) -> Value {
+        Value::DateTime(from_time_date(self.date()).and_time(from_time_time(self.time())))
+    }
+}
+
+impl ToValue for time::OffsetDateTime {
+    fn to_value(&self) -> Value {
+        let offset = FixedOffset::east_opt(self.offset().whole_seconds())
+            .expect("the offsets of time are valid offsets of chrono");
+        let local = from_time_date(self.date()).and_time(from_time_time(self.time()));
+        Value::TimestampTz(
+            offset
+                .from_local_datetime(&local)
+                .single()
+                .expect("a fixed offset has a single local time"),
+        )
+    }
+}
+
+impl FromValue for time::Date {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        to_time_date(NaiveDate::from_value(v)?)
+    }
+}
+
+impl FromValue for time::Time {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        to_time_time(NaiveTime::from_value(v)?)
+    }
+}
+
+impl FromValue for time::PrimitiveDateTime {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        to_primitive_date_time(NaiveDateTime::from_value(v)?)
+    }
+}
+
+impl FromValue for time::OffsetDateTime {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        let v = DateTime::<FixedOffset>::from_value(v)?;
+        let offset = time::UtcOffset::from_whole_seconds(v.offset().local_minus_utc())
+            .map_err(|e| {
+                ConvertError::NotSupported(v.to_string(), format!("time::UtcOffset: {}", e))
+            })?;
+        Ok(to_primitive_date_time(v.naive_local())?.assume_offset(offset))
+    }
+}
+
+/// serialize the offsets as the seconds east of utc, chrono doesn't serialize them
+pub(crate) mod offset_seconds {
+    use chrono::FixedOffset;
+    use serde::{
+        de::Error,
+        Deserialize,
+        Deserializer,
+        Serializer,
+    };
+
+    pub fn serialize<S>(offset: &FixedOffset, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_i32(offset.local_minus_utc())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<FixedOffset, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let seconds = i32::deserialize(deserializer)?;
+        FixedOffset::east_opt(seconds)
+            .ok_or_else(|| D::Error::custom(format!("invalid offset: {}", seconds)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn offsets() {
+        let value = Value::TimeTz(
+            NaiveTime::from_hms_opt(1, 2, 3).unwrap(),
+            FixedOffset::west_opt(3600).unwrap(),
+        );
+        let json = serde_json::to_string(&value).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&json).unwrap(), value);
+
+        let offset = FixedOffset::east_opt(5 * 3600 + 1800).unwrap();
+        let time = NaiveTime::from_hms_milli_opt(10, 20, 30, 500).unwrap();
+        let value = (time, offset).to_value();
+        assert_eq!(value.to_string(), "10:20:30.500+05:30");
+        let text = Value::Text(value.to_string());
+        assert_eq!(<(NaiveTime, FixedOffset)>::from_value(&text).unwrap(), (time, offset));
+        assert_eq!(NaiveTime::from_value(&value).unwrap(), time);
+
+        let stamp = DateTime::parse_from_rfc3339("2021-01-31T10:20:30+05:30").unwrap();
+        let value = stamp.to_value();
+        let text = Value::Text(value.to_string());
+        assert_eq!(DateTime::<FixedOffset>::from_value(&text).unwrap(), stamp);
+        let utc = DateTime::<Utc>::from_value(&value).unwrap();
+        assert_eq!(utc.to_rfc3339(), "2021-01-31T04:50:30+00:00");
+        assert_eq!(NaiveDateTime::from_value(&value).unwrap(), utc.naive_utc());
+    }
+
+    #[test]
+    fn time_crate() {
+        let date = time::Date::from_calendar_date(2021, time::Month::January, 31).unwrap();
+        let time = time::Time::from_hms_micro(10, 20, 30, 123_456).unwrap();
+        let primitive = time::PrimitiveDateTime::new(date, time);
+        let offset = time::UtcOffset::from_hms(-3, 0, 0).unwrap();
+        let offset_date_time = primitive.assume_offset(offset);
+
+        assert_eq!(date.to_value().to_string(), "2021-01-31");
+        assert_eq!(time::Date::from_value(&date.to_value()).unwrap(), date);
+        assert_eq!(time::Time::from_value(&time.to_value()).unwrap(), time);
+        assert_eq!(
+            time::PrimitiveDateTime::from_value(&primitive.to_value()).unwrap(),
+            primitive
+        );
+        let value = offset_date_time.to_value();
+        assert_eq!(value.to_string(), "2021-01-31T10:20:30.123456-03:00");
+        let from_value = time::OffsetDateTime::from_value(&value).unwrap();
+        assert_eq!(from_value, offset_date_time);
+        assert_eq!(from_value.offset(), offset);
+    }
+}
@@ -1,17 +1,30 @@
 #![allow(clippy::cast_lossless)]
+pub use crate::array::Array;
 use crate::{
+    bits::BitString,
+    custom::Custom,
+    geometry::Geometry,
     interval::Interval,
+    network::{
+        IpNetwork,
+        MacAddress,
+    },
+    range::Range,
     ConvertError,
+    Dao,
 };
 use bigdecimal::{
     BigDecimal,
+    FromPrimitive,
     ToPrimitive,
 };
 use chrono::{
     DateTime,
+    FixedOffset,
     NaiveDate,
     NaiveDateTime,
     NaiveTime,
+    TimeZone,
     Utc,
 };
 use geo_types::Point;
@@ -19,12 +32,35 @@ use serde_derive::{
     Deserialize,
     Serialize,
 };
-use std::fmt;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    str::FromStr,
+};
 use uuid::Uuid;
 
 /// Generic value storage 32 byte in size
 /// Some contains the same value container, but the variant is more
 /// important for type hinting and view presentation hinting purposes
+///
+/// The temporal values read from each database:
+///
+/// | column type                   | postgresql    | sqlite          | mysql                      |
+/// |-------------------------------|---------------|-----------------|----------------------------|
+/// | date                          | `Date`        | `Date`          | `Date`                     |
+/// | time                          | `Time`        | `Time`          | `Time`                     |
+/// | time with time zone           | `TimeTz`      | `Text`          |                            |
+/// | timestamp / datetime          | `DateTime`    | `DateTime`      | `DateTime`                 |
+/// | timestamp with time zone      | `Timestamp`   | `Timestamp`     | `Timestamp` for TIMESTAMP  |
+///
+/// Postgresql stores the `timestamp with time zone` as an instant in utc, so a `TimestampTz`
+/// parameter is read back as a `Timestamp`. Sqlite stores the values as text, the timestamps
+/// with an offset in RFC 3339, and decodes them from the type declared for their column: a
+/// `timestamp` or `datetime` column stored with an offset is read as a `Timestamp`, the
+/// `timestamptz` columns as a `Timestamp` and the columns of an expression or without a
+/// declared temporal type as `Text`. Mysql stores the `TIMESTAMP` in utc, the connections use
+/// the utc time zone so a `TimestampTz` parameter is converted to utc. Mysql has no time with
+/// time zone.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum Value {
     Nil, // no value
@@ -42,18 +78,40 @@ pub enum Value {
     Blob(Vec<u8>),
     Char(char),
     Text(String),
-    Json(String),
+    Json(serde_json::Value),
 
     Uuid(Uuid),
     Date(NaiveDate),
     Time(NaiveTime),
+    /// a time of day with its offset from utc
+    TimeTz(NaiveTime, #[serde(with = "crate::temporal::offset_seconds")] FixedOffset),
     DateTime(NaiveDateTime),
     Timestamp(DateTime<Utc>),
+    /// a timestamp with its offset from utc
+    TimestampTz(DateTime<FixedOffset>),
     Interval(Interval),
 
     Point(Point<f64>),
+    /// a geometry of any type with its spatial reference id
+    Geometry(Box<Geometry>),
 
     Array(Array),
+
+    /// the address of a host or network, ie: `192.168.0.1/24`
+    Inet(IpNetwork),
+    /// the address of a network, without bits set at the right of its prefix
+    Cidr(IpNetwork),
+    MacAddr(MacAddress),
+    /// an amount of money in the smallest unit of the currency, ie: cents
+    Money(i64),
+    Bit(BitString),
+    Range(Box<Range>),
+    /// the keys with their optional values of a postgresql `hstore`
+    Hstore(BTreeMap<String, Option<String>>),
+    /// a value of a type unknown to rustorm
+    Custom(Box<Custom>),
+    /// the fields of a postgresql composite type
+    Composite(Box<Dao>),
 }
 
 impl Value {
@@ -78,55 +136,36 @@ impl fmt::Display for Value {
             Value::Uuid(v) => write!(f, "{}", v),
             Value::Date(v) => write!(f, "{}", v),
             Value::Time(v) => write!(f, "{}", v),
+            Value::TimeTz(v, offset) => write!(f, "{}{}", v, offset),
             Value::DateTime(v) => write!(f, "{}", v.format("%Y-%m-%d %H:%M:%S").to_string()),
             Value::Timestamp(v) => write!(f, "{}", v.to_rfc3339()),
+            Value::TimestampTz(v) => write!(f, "{}", v.to_rfc3339()),
             Value::Array(array) => array.fmt(f),
             Value::Blob(v) => {
                 let encoded = base64::encode_config(&v, base64::STANDARD);
                 write!(f, "{}", encoded)
             }
-            _ => panic!("not yet implemented: {:?}", self),
-        }
-    }
-}
-
-#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
-pub enum Array {
-    /*
-    Bool(Vec<bool>),
-
-    Tinyint(Vec<i8>),
-    Smallint(Vec<i16>),
-    */
-    Int(Vec<i32>),
-    Float(Vec<f32>),
-    /*
-    Bigint(Vec<i64>),
-
-    Double(Vec<f64>),
-    BigDecimal(Vec<BigDecimal>),
-    */
-    Text(Vec<String>),
-    /*
-    Char(Vec<char>),
-    Uuid(Vec<Uuid>),
-    Date(Vec<NaiveDate>),
-    Timestamp(Vec<DateTime<Utc>>),
-    */
-}
-
-impl fmt::Display for Array {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            Array::Text(texts) => {
-                let json_arr = serde_json::to_string(texts).expect("must serialize");
-                write!(f, "{}", json_arr)
+            Value::Interval(v) => write!(f, "{}", v),
+            Value::Point(v) => write!(f, "({},{})", v.x(), v.y()),
+            Value::Geometry(v) => v.fmt(f),
+            Value::Custom(v) => v.fmt(f),
+            Value::Inet(v) | Value::Cidr(v) => v.fmt(f),
+            Value::MacAddr(v) => v.fmt(f),
+            Value::Money(v) => write!(f, "{}", v),
+            Value::Bit(v) => v.fmt(f),
+            Value::Range(v) => v.fmt(f),
+            Value::Hstore(v) => {
+                write!(f, "{}", serde_json::to_string(v).map_err(|_| fmt::Error)?)
             }
-            Array::Float(floats) => {
-                let json_arr = serde_json::to_string(floats).expect("must serialize");
-                write!(f, "{}", json_arr)
+            // the fields displayed as a json object
+            Value::Composite(v) => {
+                let fields: BTreeMap<&String, Option<String>> = v
+                    .0
+                    .iter()
+                    .map(|(k, v)| (k, Some(v).filter(|v| !v.is_nil()).map(Value::to_string)))
+                    .collect();
+                write!(f, "{}", serde_json::to_string(&fields).map_err(|_| fmt::Error)?)
             }
-            _ => panic!("not yet implemented: {:?}", self),
         }
     }
 }
@@ -152,6 +191,7 @@ impl_to_value!(i32, Int);
 impl_to_value!(i64, Bigint);
 impl_to_value!(f32, Float);
 impl_to_value!(f64, Double);
+impl_to_value!(BigDecimal, BigDecimal);
 impl_to_value!(Vec<u8>, Blob);
 impl_to_value!(char, Char);
 impl_to_value!(String, Text);
@@ -159,16 +199,13 @@ impl_to_value!(Uuid, Uuid);
 impl_to_value!(NaiveDate, Date);
 impl_to_value!(NaiveTime, Time);
 impl_to_value!(DateTime<Utc>, Timestamp);
+impl_to_value!(DateTime<FixedOffset>, TimestampTz);
 impl_to_value!(NaiveDateTime, DateTime);
 
 impl ToValue for &str {
     fn to_value(&self) -> Value { Value::Text(self.to_string()) }
 }
 
-impl ToValue for Vec<String> {
-    fn to_value(&self) -> Value { Value::Array(Array::Text(self.to_owned())) }
-}
-
 impl<T> ToValue for Option<T>
 where
     T: ToValue,
@@ -214,16 +251,18 @@ macro_rules! impl_from_value {
     }
 }
 
+/// the values which don't fit in the type are an error instead of being truncated
 macro_rules! impl_from_value_numeric {
     ($ty: ty, $method:ident, $ty_name: tt, $($variant: ident),*) => {
         impl FromValue for $ty {
             fn from_value(v: &Value) -> Result<Self, ConvertError> {
                 match *v {
-                    $(Value::$variant(ref v) => Ok(v.to_owned() as $ty),
+                    $(Value::$variant(ref n) => n.$method(),
                     )*
-                    Value::BigDecimal(ref v) => Ok(v.$method().unwrap()),
-                    _ => Err(ConvertError::NotSupported(format!("{:?}", v), $ty_name.into())),
+                    Value::BigDecimal(ref d) => d.$method(),
+                    _ => None,
                 }
+                .ok_or_else(|| ConvertError::NotSupported(format!("{:?}", v), $ty_name.into()))
             }
         }
     }
@@ -231,14 +270,99 @@ macro_rules! impl_from_value_numeric {
 
 impl_from_value!(Vec<u8>, "Vec<u8>", Blob);
 impl_from_value!(char, "char", Char);
-impl_from_value!(Uuid, "Uuid", Uuid);
-impl_from_value!(NaiveDate, "NaiveDate", Date);
 impl_from_value_numeric!(i8, to_i8, "i8", Tinyint);
-impl_from_value_numeric!(i16, to_i16, "i16", Tinyint, Smallint);
+impl_from_value_numeric!(i16, to_i16, "i16", Tinyint, Smallint, Int, Bigint);
 impl_from_value_numeric!(i32, to_i32, "i32", Tinyint, Smallint, Int, Bigint);
-impl_from_value_numeric!(i64, to_i64, "i64", Tinyint, Smallint, Int, Bigint);
-impl_from_value_numeric!(f32, to_f32, "f32", Float);
-impl_from_value_numeric!(f64, to_f64, "f64", Float, Double);
+impl_from_value_numeric!(i64, to_i64, "i64", Tinyint, Smallint, Int, Bigint, Money);
+
+/// whether the float is exactly the integer, the floats from 2^63 are out of range
+fn is_exact(f: f64, n: i64) -> bool {
+    (-9_223_372_036_854_775_808.0..9_223_372_036_854_775_808.0).contains(&f) && f as i64 == n
+}
+
+/// the integers and doubles are only converted when they are exactly represented
+impl FromValue for f32 {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Tinyint(n) => Some(f32::from(n)),
+            Value::Smallint(n) => Some(f32::from(n)),
+            Value::Int(n) => Some(n as f32).filter(|f| f64::from(*f) == f64::from(n)),
+            Value::Bigint(n) => Some(n as f32).filter(|f| is_exact(f64::from(*f), n)),
+            Value::Float(n) => Some(n),
+            Value::Double(n) => Some(n as f32).filter(|f| n.is_nan() || f64::from(*f) == n),
+            Value::BigDecimal(ref d) => d.to_f32(),
+            _ => None,
+        }
+        .ok_or_else(|| ConvertError::NotSupported(format!("{:?}", v), "f32".into()))
+    }
+}
+
+/// the integers are only converted when they are exactly represented
+impl FromValue for f64 {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Tinyint(n) => Some(f64::from(n)),
+            Value::Smallint(n) => Some(f64::from(n)),
+            Value::Int(n) => Some(f64::from(n)),
+            Value::Bigint(n) => Some(n as f64).filter(|f| is_exact(*f, n)),
+            Value::Float(n) => Some(f64::from(n)),
+            Value::Double(n) => Some(n),
+            Value::BigDecimal(ref d) => d.to_f64(),
+            _ => None,
+        }
+        .ok_or_else(|| ConvertError::NotSupported(format!("{:?}", v), "f64".into()))
+    }
+}
+
+/// the uuids, dates and decimals in a json array are strings
+impl FromValue for Uuid {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Uuid(v) => Ok(v),
+            Value::Text(ref v) => {
+                Uuid::parse_str(v)
+                    .map_err(|_| ConvertError::NotSupported(v.to_string(), "Uuid".to_string()))
+            }
+            _ => Err(ConvertError::NotSupported(format!("{:?}", v), "Uuid".to_string())),
+        }
+    }
+}
+
+impl FromValue for NaiveDate {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        match *v {
+            Value::Date(v) => Ok(v),
+            Value::Text(ref v) => {
+                NaiveDate::parse_from_str(v, "%Y-%m-%d").map_err(|_| {
+                    ConvertError::NotSupported(v.to_string(), "NaiveDate".to_string())
+                })
+            }
+            _ => {
+                Err(ConvertError::NotSupported(
+                    format!("{:?}", v),
+                    "NaiveDate".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+impl FromValue for BigDecimal {
+    fn from_value(v: &Value) -> Result<Self, ConvertError> {
+        let not_supported = || ConvertError::NotSupported(format!("{:?}", v), "BigDecimal".into());
+        match *v {
+            Value::BigDecimal(ref v) => Ok(v.clone()),
+            Value::Tinyint(v) => Ok(BigDecimal::from(v)),
+            Value::Smallint(v) => Ok(BigDecimal::from(v)),
+            Value::Int(v) => Ok(BigDecimal::from(v)),
+            Value::Bigint(v) => Ok(BigDecimal::from(v)),
+            Value::Float(v) => BigDecimal::from_f32(v).ok_or_else(not_supported),
+            Value::Double(v) => BigDecimal::from_f64(v).ok_or_else(not_supported),
+            Value::Text(ref v) => BigDecimal::from_str(v).map_err(|_| not_supported()),
+            _ => Err(not_supported()),
+        }
+    }
+}
 
 /// Char can be casted into String
 /// and they havea separate implementation for extracting data
@@ -246,6 +370,7 @@ impl FromValue for String {
     fn from_value(v: &Value) -> Result<Self, ConvertError> {
         match *v {
             Value::Text(ref v) => Ok(v.to_owned()),
+            Value::Json(ref v) => Ok(v.to_string()),
             Value::Char(ref v) => {
                 let mut s = String::new();
                 s.push(*v);
@@ -266,20 +391,6 @@ impl FromValue for String {
     }
 }
 
-impl FromValue for Vec<String> {
-    fn from_value(v: &Value) -> Result<Self, ConvertError> {
-        match *v {
-            Value::Array(Array::Text(ref t)) => Ok(t.to_owned()),
-            _ => {
-                Err(ConvertError::NotSupported(
-                    format!("{:?}", v),
-                    "Vec<String>".to_string(),
-                ))
-            }
-        }
-    }
-}
-
 impl FromValue for bool {
     fn from_value(v: &Value) -> Result<Self, ConvertError> {
         match *v {
@@ -301,9 +412,15 @@ impl FromValue for bool {
 impl FromValue for DateTime<Utc> {
     fn from_value(v: &Value) -> Result<Self, ConvertError> {
         match *v {
-            Value::Text(ref v) => Ok(DateTime::<Utc>::from_utc(parse_naive_date_time(v), Utc)),
-            Value::DateTime(v) => Ok(DateTime::<Utc>::from_utc(v, Utc)),
+            Value::Text(ref v) => {
+                match DateTime::parse_from_rfc3339(v) {
+                    Ok(v) => Ok(v.with_timezone(&Utc)),
+                    Err(_) => Ok(Utc.from_utc_datetime(&parse_naive_date_time(v)?)),
+                }
+            }
+            Value::DateTime(v) => Ok(Utc.from_utc_datetime(&v)),
             Value::Timestamp(v) => Ok(v),
+            Value::TimestampTz(v) => Ok(v.with_timezone(&Utc)),
             _ => {
                 Err(ConvertError::NotSupported(
                     format!("{:?}", v),
@@ -317,8 +434,10 @@ impl FromValue for DateTime<Utc> {
 impl FromValue for NaiveDateTime {
     fn from_value(v: &Value) -> Result<Self, ConvertError> {
         match *v {
-            Value::Text(ref v) => Ok(parse_naive_date_time(v)),
+            Value::Text(ref v) => parse_naive_date_time(v),
             Value::DateTime(v) => Ok(v),
+            Value::Timestamp(v) => Ok(v.naive_utc()),
+            Value::TimestampTz(v) => Ok(v.naive_utc()),
             _ => {
                 Err(ConvertError::NotSupported(
                     format!("{:?}", v),
@@ -341,18 +460,10 @@ where
     }
 }
 
-fn parse_naive_date_time(v: &str) -> NaiveDateTime {
-    let ts = NaiveDateTime::parse_from_str(&v, "%Y-%m-%d %H:%M:%S");
-    if let Ok(ts) = ts {
-        ts
-    } else {
-        let ts = NaiveDateTime::parse_from_str(&v, "%Y-%m-%d %H:%M:%S%.3f");
-        if let Ok(ts) = ts {
-            ts
-        } else {
-            panic!("unable to parse timestamp: {}", v);
-        }
-    }
+pub(crate) fn parse_naive_date_time(v: &str) -> Result<NaiveDateTime, ConvertError> {
+    NaiveDateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S%.f")
+        .or_else(|_| NaiveDateTime::parse_from_str(v, "%Y-%m-%dT%H:%M:%S%.f"))
+        .map_err(|_| ConvertError::NotSupported(v.to_string(), "NaiveDateTime".to_string()))
 }
 
 #[cfg(test)]
@@ -386,6 +497,31 @@ mod tests {
         let _v6: Value = vec![1u8, 2, 255, 3].to_value();
     }
 
+    #[test]
+    fn decimal_out_of_range() {
+        use std::str::FromStr;
+
+        let decimal = Value::BigDecimal(BigDecimal::from_str("1000").unwrap());
+        assert_eq!(i16::from_value(&decimal).unwrap(), 1000);
+        assert!(matches!(i8::from_value(&decimal), Err(ConvertError::NotSupported(_, _))));
+    }
+
+    #[test]
+    fn integer_out_of_range() {
+        assert_eq!(i16::from_value(&Value::Int(7000)).unwrap(), 7000);
+        assert!(i16::from_value(&Value::Int(70000)).is_err());
+        assert!(i32::from_value(&Value::Bigint(i64::MAX)).is_err());
+        assert_eq!(f32::from_value(&Value::Smallint(-3)).unwrap(), -3.0);
+        assert_eq!(f32::from_value(&Value::Bigint(16_777_216)).unwrap(), 16_777_216.0);
+        assert!(f32::from_value(&Value::Bigint(16_777_217)).is_err());
+        assert_eq!(f32::from_value(&Value::Double(1.5)).unwrap(), 1.5);
+        assert!(f32::from_value(&Value::Double(0.1)).is_err());
+        assert!(f32::from_value(&Value::Double(1e300)).is_err());
+        assert_eq!(f64::from_value(&Value::Int(i32::MAX)).unwrap(), 2_147_483_647.0);
+        assert!(f64::from_value(&Value::Bigint(i64::MAX)).is_err());
+        assert_eq!(f64::from_value(&Value::Bigint(i64::MIN)).unwrap(), -9.223372036854775808e18);
+    }
+
     #[test]
     fn naive_date_parse() {
         let v = "2018-01-29";
@@ -408,6 +544,19 @@ mod tests {
         let ts = NaiveDateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S");
         println!("{:?}", ts);
         assert!(ts.is_ok());
-        DateTime::<Utc>::from_utc(ts.unwrap(), Utc);
+        Utc.from_utc_datetime(&ts.unwrap());
+    }
+
+    #[test]
+    fn invalid_timestamp_is_an_error() {
+        let ts: Result<NaiveDateTime, _> = FromValue::from_value(&Value::Text("29/01/2018".into()));
+        assert!(ts.is_err());
+    }
+
+    #[test]
+    fn display_all_variants() {
+        assert_eq!(Value::Interval(Interval::new(5, 2, 1)).to_string(), "P1M2DT0.000005S");
+        assert_eq!(Value::Point(Point::new(1.5, -2.0)).to_string(), "(1.5,-2)");
+        assert_eq!(Value::Array(Array::Int(vec![1, 2])).to_string(), "[1,2]");
     }
 }
@@ -0,0 +1,309 @@
+//! An opt-in cache of the table definitions.
+//!
+//! Introspecting a table takes several round trips to the database catalog, which is too slow
+//! to do on every request of a web application. The cache is keyed by the db_url and the table
+//! name and is shared by all the connections in the pool.
+use crate::{
+    Database,
+    DbError,
+    TableDef,
+    TableName,
+};
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        RwLock,
+        RwLockReadGuard,
+        RwLockWriteGuard,
+    },
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// A cache of table definitions shared across the pooled connections.
+///
+/// Entries are evicted when they are older than the time to live, when explicitly invalidated
+/// or, with `auto_invalidate` enabled, when the database reports that its schema has changed
+/// since the entries were cached.
+#[derive(Clone, Default)]
+pub struct MetadataCache {
+    store: Arc<RwLock<Store>>,
+    ttl: Option<Duration>,
+    auto_invalidate: bool,
+}
+
+#[derive(Default)]
+struct Store {
+    tables: HashMap<(String, TableName), Entry<Option<TableDef>>>,
+    all_tables: HashMap<String, Entry<Vec<TableDef>>>,
+    /// the schema version of each database at the time its entries are cached
+    versions: HashMap<String, String>,
+}
+
+struct Entry<T> {
+    value: T,
+    cached_at: Instant,
+}
+
+impl<T: Clone> Entry<T> {
+    fn new(value: T) -> Self {
+        Entry {
+            value,
+            cached_at: Instant::now(),
+        }
+    }
+
+    fn get(&self, ttl: Option<Duration>) -> Option<T> {
+        match ttl {
+            Some(ttl) if self.cached_at.elapsed() >= ttl => None,
+            _ => Some(self.value.clone()),
+        }
+    }
+}
+
+impl Store {
+    fn invalidate_db(&mut self, db_url: &str) {
+        self.tables.retain(|(url, _), _| url != db_url);
+        self.all_tables.remove(db_url);
+        self.versions.remove(db_url);
+    }
+}
+
+impl MetadataCache {
+    /// create a cache which entries don't expire until invalidated
+    pub fn new() -> Self { Default::default() }
+
+    /// create a cache which entries expire after `ttl`
+    pub fn with_ttl(ttl: Duration) -> Self {
+        MetadataCache {
+            ttl: Some(ttl),
+            ..Default::default()
+        }
+    }
+
+    /// check the schema version of the database each time an entry is used and evict
+    /// the entries of that database when the schema has changed.
+    ///
+    /// This costs a single cheap query per lookup and is supported on postgresql, which uses
+    /// the transaction ids of the catalog rows, and sqlite, which uses `PRAGMA schema_version`.
+    /// On mysql, only the time to live and the explicit invalidation applies.
+    pub fn auto_invalidate(mut self, auto_invalidate: bool) -> Self {
+        self.auto_invalidate = auto_invalidate;
+        self
+    }
+
+    /// remove the cached definition of this table
+    pub fn invalidate_table(&self, db_url: &str, table_name: &TableName) {
+        let mut store = self.write();
+        store.tables.remove(&(db_url.to_string(), table_name.clone()));
+        store.all_tables.remove(db_url);
+    }
+
+    /// remove all the cached definitions of this database
+    pub fn invalidate_db(&self, db_url: &str) { self.write().invalidate_db(db_url) }
+
+    /// remove all the cached definitions
+    pub fn clear(&self) {
+        let mut store = self.write();
+        store.tables.clear();
+        store.all_tables.clear();
+        store.versions.clear();
+    }
+
+    /// the view of this cache used by the entity managers connected to `db_url`
+    pub(crate) fn for_db(&self, db_url: &str) -> DbMetadataCache {
+        DbMetadataCache {
+            db_url: db_url.to_string(),
+            cache: self.clone(),
+        }
+    }
+
+    /// get the table definition from the cache, introspecting the database when missing
+    fn get_table(
+        &self,
+        db_url: &str,
+        db: &mut dyn Database,
+        table_name: &TableName,
+    ) -> Result<Option<TableDef>, DbError> {
+        let version = self.check_version(db_url, db)?;
+        let key = (db_url.to_string(), table_name.clone());
+        if let Some(table) = self.read().tables.get(&key).and_then(|e| e.get(self.ttl)) {
+            return Ok(table);
+        }
+        let table = db.get_table(table_name)?;
+        let mut store = self.write();
+        store.tables.insert(key, Entry::new(table.clone()));
+        Self::set_version(&mut store, db_url, version);
+        Ok(table)
+    }
+
+    /// get all the table definitions from the cache, introspecting the database when missing
+    fn get_all_tables(
+        &self,
+        db_url: &str,
+        db: &mut dyn Database,
+    ) -> Result<Vec<TableDef>, DbError> {
+        let version = self.check_version(db_url, db)?;
+        if let Some(tables) = self.read().all_tables.get(db_url).and_then(|e| e.get(self.ttl)) {
+            return Ok(tables);
+        }
+        let tables = db.get_all_tables()?;
+        let mut store = self.write();
+        for table in tables.iter() {
+            let key = (db_url.to_string(), table.name.clone());
+            store.tables.insert(key, Entry::new(Some(table.clone())));
+        }
+        store
+            .all_tables
+            .insert(db_url.to_string(), Entry::new(tables.clone()));
+        Self::set_version(&mut store, db_url, version);
+        Ok(tables)
+    }
+
+    /// evict the entries of this database when its schema version has changed,
+    /// returns the current schema version
+    fn check_version(
+        &self,
+        db_url: &str,
+        db: &mut dyn Database,
+    ) -> Result<Option<String>, DbError> {
+        if !self.auto_invalidate {
+            return Ok(None);
+        }
+        let version = db.get_schema_version()?;
+        let changed = version_changed(version.as_ref(), self.read().versions.get(db_url));
+        if changed {
+            self.write().invalidate_db(db_url);
+        }
+        Ok(version)
+    }
+
+    fn set_version(store: &mut Store, db_url: &str, version: Option<String>) {
+        if let Some(version) = version {
+            store.versions.insert(db_url.to_string(), version);
+        }
+    }
+
+    /// the cached values are still usable even if a thread panicked while holding the lock
+    fn read(&self) -> RwLockReadGuard<'_, Store> {
+        self.store.read().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, Store> {
+        self.store.write().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// whether the schema version differs from the cached one, the databases which do not report
+/// a version, such as mysql, are only invalidated by the ttl and explicitly
+fn version_changed(version: Option<&String>, cached: Option<&String>) -> bool {
+    match (version, cached) {
+        (Some(version), Some(cached)) => version != cached,
+        (Some(_), None) => true,
+        (None, _) => false,
+    }
+}
+
+/// The metadata cache of a single database
+#[derive(Clone)]
+pub(crate) struct DbMetadataCache {
+    db_url: String,
+    cache: MetadataCache,
+}
+
+impl DbMetadataCache {
+    pub(crate) fn get_table(
+        &self,
+        db: &mut dyn Database,
+        table_name: &TableName,
+    ) -> Result<Option<TableDef>, DbError> {
+        self.cache.get_table(&self.db_url, db, table_name)
+    }
+
+    pub(crate) fn get_all_tables(&self, db: &mut dyn Database) -> Result<Vec<TableDef>, DbError> {
+        self.cache.get_all_tables(&self.db_url, db)
+    }
+
+    pub(crate) fn invalidate_table(&self, table_name: &TableName) {
+        self.cache.invalidate_table(&self.db_url, table_name)
+    }
+
+    pub(crate) fn invalidate(&self) { self.cache.invalidate_db(&self.db_url) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::thread;
+
+    fn cached_table(cache: &MetadataCache, db_url: &str, table_name: &TableName) -> bool {
+        let key = (db_url.to_string(), table_name.clone());
+        cache
+            .read()
+            .tables
+            .get(&key)
+            .and_then(|e| e.get(cache.ttl))
+            .is_some()
+    }
+
+    fn put_table(cache: &MetadataCache, db_url: &str, table_name: &TableName) {
+        let key = (db_url.to_string(), table_name.clone());
+        cache.write().tables.insert(key, Entry::new(None));
+    }
+
+    #[test]
+    fn invalidate_only_the_matching_entries() {
+        let cache = MetadataCache::new();
+        let actor = TableName::from("public.actor");
+        let film = TableName::from("public.film");
+        put_table(&cache, "sqlite://a.db", &actor);
+        put_table(&cache, "sqlite://a.db", &film);
+        put_table(&cache, "sqlite://b.db", &actor);
+
+        cache.invalidate_table("sqlite://a.db", &actor);
+        assert!(!cached_table(&cache, "sqlite://a.db", &actor));
+        assert!(cached_table(&cache, "sqlite://a.db", &film));
+        assert!(cached_table(&cache, "sqlite://b.db", &actor));
+
+        cache.invalidate_db("sqlite://a.db");
+        assert!(!cached_table(&cache, "sqlite://a.db", &film));
+        assert!(cached_table(&cache, "sqlite://b.db", &actor));
+
+        cache.clear();
+        assert!(!cached_table(&cache, "sqlite://b.db", &actor));
+    }
+
+    #[test]
+    fn entries_expire_after_ttl() {
+        let cache = MetadataCache::with_ttl(Duration::from_millis(20));
+        let actor = TableName::from("actor");
+        put_table(&cache, "sqlite://a.db", &actor);
+        assert!(cached_table(&cache, "sqlite://a.db", &actor));
+        thread::sleep(Duration::from_millis(30));
+        assert!(!cached_table(&cache, "sqlite://a.db", &actor));
+    }
+
+    #[test]
+    fn only_a_reported_version_invalidates() {
+        let (one, two) = ("1".to_string(), "2".to_string());
+        assert!(!version_changed(Some(&one), Some(&one)));
+        assert!(version_changed(Some(&two), Some(&one)));
+        assert!(version_changed(Some(&one), None));
+        assert!(!version_changed(None, None));
+        assert!(!version_changed(None, Some(&one)));
+    }
+
+    #[test]
+    fn clones_share_the_entries() {
+        let cache = MetadataCache::new();
+        let cloned = cache.clone();
+        let actor = TableName::from("actor");
+        put_table(&cache, "sqlite://a.db", &actor);
+        assert!(cached_table(&cloned, "sqlite://a.db", &actor));
+        cloned.invalidate_table("sqlite://a.db", &actor);
+        assert!(!cached_table(&cache, "sqlite://a.db", &actor));
+    }
+}
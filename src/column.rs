@@ -112,6 +112,8 @@ pub enum ColumnConstraint {
     DefaultValue(Literal),
     /// the string contains the sequence name of this serial column
     AutoIncrement(Option<String>),
+    /// the check constraint of the domain of the column, ie: `CHECK ((VALUE >= 0))`
+    Check(String),
 }
 
 #[derive(Debug, PartialEq, Clone)]
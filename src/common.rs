@@ -1,5 +1,6 @@
 use crate::{
     column::Capacity,
+    error::ConvertError,
     types::SqlType,
     Value,
 };
@@ -9,7 +10,7 @@ use bigdecimal::{
 };
 use chrono::NaiveDateTime;
 use log::*;
-use std::str::FromStr;
+use std::convert::TryFrom;
 
 pub fn extract_datatype_with_capacity(data_type: &str) -> (String, Option<Capacity>) {
     let start = data_type.find('(');
@@ -66,133 +67,108 @@ pub fn extract_datatype_with_capacity(data_type: &str) -> (String, Option<Capaci
     }
 }
 
-pub fn cast_type(value: &Value, required_type: &SqlType) -> Value {
+/// cast the value into the value variant of the required sql type
+pub fn cast_type(value: &Value, required_type: &SqlType) -> Result<Value, ConvertError> {
     if *value == Value::Nil || required_type.same_type(value) {
-        value.to_owned()
-    } else {
-        match *value {
-            Value::Smallint(v) => {
-                match *required_type {
-                    SqlType::Tinyint => Value::Tinyint(v as i8),
-                    SqlType::Int => Value::Int(i32::from(v)),
-                    SqlType::Bigint => Value::Bigint(i64::from(v)),
-                    _ => {
-                        panic!(
-                            "unsupported conversion from {:?} to {:?}",
-                            value, required_type
-                        )
-                    }
-                }
+        return Ok(value.to_owned());
+    }
+    let unsupported = || {
+        ConvertError::UnsupportedConversion(format!("{:?}", value), format!("{:?}", required_type))
+    };
+    let casted = match *value {
+        Value::Smallint(v) => {
+            match *required_type {
+                SqlType::Tinyint => Value::Tinyint(i8::try_from(v).map_err(|_| unsupported())?),
+                SqlType::Int => Value::Int(i32::from(v)),
+                SqlType::Bigint => Value::Bigint(i64::from(v)),
+                _ => return Err(unsupported()),
             }
-            Value::Int(v) => {
-                match *required_type {
-                    SqlType::Tinyint => Value::Tinyint(v as i8),
-                    SqlType::Smallint => Value::Smallint(v as i16),
-                    SqlType::Bigint => Value::Bigint(i64::from(v)),
-                    _ => {
-                        panic!(
-                            "unsupported conversion from {:?} to {:?}",
-                            value, required_type
-                        )
-                    }
-                }
+        }
+        Value::Int(v) => {
+            match *required_type {
+                SqlType::Tinyint => Value::Tinyint(i8::try_from(v).map_err(|_| unsupported())?),
+                SqlType::Smallint => Value::Smallint(i16::try_from(v).map_err(|_| unsupported())?),
+                SqlType::Bigint => Value::Bigint(i64::from(v)),
+                _ => return Err(unsupported()),
             }
-            Value::Bigint(v) => {
-                match *required_type {
-                    SqlType::Tinyint => Value::Tinyint(v as i8),
-                    SqlType::Smallint => Value::Smallint(v as i16),
-                    SqlType::Int => Value::Int(v as i32),
-                    SqlType::Numeric => {
-                        let bigdecimal = BigDecimal::from_str(&format!("{}", v));
-                        assert!(bigdecimal.is_ok());
-                        Value::BigDecimal(bigdecimal.unwrap())
-                    }
-                    SqlType::Varchar => Value::Text(format!("{}", v)),
-                    _ => {
-                        panic!(
-                            "unsupported conversion from {:?} to {:?}",
-                            value, required_type
-                        )
-                    }
-                }
+        }
+        Value::Bigint(v) => {
+            match *required_type {
+                SqlType::Tinyint => Value::Tinyint(i8::try_from(v).map_err(|_| unsupported())?),
+                SqlType::Smallint => Value::Smallint(i16::try_from(v).map_err(|_| unsupported())?),
+                SqlType::Int => Value::Int(i32::try_from(v).map_err(|_| unsupported())?),
+                SqlType::Numeric => Value::BigDecimal(BigDecimal::from(v)),
+                SqlType::Varchar => Value::Text(format!("{}", v)),
+                _ => return Err(unsupported()),
             }
-            Value::BigDecimal(ref v) => {
-                match *required_type {
-                    SqlType::Int => {
-                        let ival = v.to_i32();
-                        assert!(ival.is_some());
-                        let ival = ival.unwrap();
-                        Value::Int(ival)
-                    }
-                    SqlType::Varchar => Value::Text(format!("{}", v)),
-                    _ => {
-                        panic!(
-                            "unsupported conversion from {:?} to {:?}",
-                            value, required_type
-                        )
-                    }
-                }
+        }
+        Value::BigDecimal(ref v) => {
+            match *required_type {
+                SqlType::Int => Value::Int(v.to_i32().ok_or_else(unsupported)?),
+                SqlType::Varchar => Value::Text(format!("{}", v)),
+                _ => return Err(unsupported()),
             }
-            Value::Text(ref v) => {
-                match *required_type {
-                    SqlType::Timestamp => {
-                        let ts = NaiveDateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S");
-                        let ts = if let Ok(ts) = ts {
-                            ts
-                        } else {
-                            let ts = NaiveDateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S%.3f");
-                            if let Ok(ts) = ts {
-                                ts
-                            } else {
-                                panic!("unable to parse timestamp: {}", v);
-                            }
-                        };
-                        Value::DateTime(ts)
-                    }
-                    SqlType::Char => {
-                        assert_eq!(v.len(), 1);
-                        Value::Char(v.chars().next().unwrap())
-                    }
-                    SqlType::Int => {
-                        if let Ok(v) = v.parse::<i32>() {
-                            Value::Int(v)
-                        } else {
-                            panic!(
-                                "unsupported conversion from {:?} to {:?}",
-                                value, required_type
-                            );
-                        }
-                    }
-                    // enums will be just Text
-                    SqlType::Enum(_, _) => Value::Text(v.to_string()),
-                    // ts vector is casted into text and then we just
-                    // return them as text as well
-                    SqlType::TsVector => Value::Text(v.to_string()),
-                    _ => {
-                        panic!(
-                            "unsupported conversion from {:?} to {:?}",
-                            value, required_type
-                        )
-                    }
+        }
+        Value::Text(ref v) => {
+            match *required_type {
+                SqlType::Timestamp => {
+                    let ts = NaiveDateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S")
+                        .or_else(|_| NaiveDateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S%.3f"))
+                        .map_err(|_| unsupported())?;
+                    Value::DateTime(ts)
                 }
-            }
-            Value::Char(v) => {
-                match *required_type {
-                    SqlType::Varchar => Value::Text(format!("{}", v)),
-                    _ => {
-                        panic!(
-                            "unsupported conversion from {:?} to {:?}",
-                            value, required_type
-                        )
+                SqlType::Char => {
+                    let mut chars = v.chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(c), None) => Value::Char(c),
+                        _ => return Err(unsupported()),
                     }
                 }
+                SqlType::Int => Value::Int(v.parse::<i32>().map_err(|_| unsupported())?),
+                // enums will be just Text
+                SqlType::Enum(_, _) => Value::Text(v.to_string()),
+                // ts vector is casted into text and then we just
+                // return them as text as well
+                SqlType::TsVector => Value::Text(v.to_string()),
+                _ => return Err(unsupported()),
             }
-            _ => {
-                panic!(
-                    "unsupported conversion from {:?} to {:?}",
-                    value, required_type
-                )
+        }
+        Value::Char(v) => {
+            match *required_type {
+                SqlType::Varchar => Value::Text(format!("{}", v)),
+                _ => return Err(unsupported()),
             }
         }
+        _ => return Err(unsupported()),
+    };
+    Ok(casted)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cast_supported_types() {
+        assert_eq!(
+            cast_type(&Value::Bigint(42), &SqlType::Int).unwrap(),
+            Value::Int(42)
+        );
+        assert_eq!(
+            cast_type(&Value::Text("x".into()), &SqlType::Char).unwrap(),
+            Value::Char('x')
+        );
+        assert_eq!(
+            cast_type(&Value::Nil, &SqlType::Uuid).unwrap(),
+            Value::Nil
+        );
+    }
+
+    #[test]
+    fn unsupported_cast_is_an_error() {
+        assert!(cast_type(&Value::Bool(true), &SqlType::Date).is_err());
+        assert!(cast_type(&Value::Bigint(i64::MAX), &SqlType::Int).is_err());
+        assert!(cast_type(&Value::Text("xy".into()), &SqlType::Char).is_err());
+        assert!(cast_type(&Value::Text("yesterday".into()), &SqlType::Timestamp).is_err());
     }
 }
@@ -1,4 +1,9 @@
 use crate::{
+    observer::{
+        QueryObserver,
+        QueryObservers,
+    },
+    statement_cache::StatementCacheStats,
     DBPlatform,
     Dao,
     DataError,
@@ -6,24 +11,82 @@ use crate::{
     Rows,
     Value,
 };
+use std::{
+    sync::Arc,
+    time::Duration,
+};
 
 /// an interface executing sql statement and getting the results as generic DAO values
 /// without any further conversion.
-pub struct DaoManager(pub DBPlatform);
+pub struct DaoManager(pub DBPlatform, pub(crate) QueryObservers, pub(crate) Option<Duration>);
 
 impl DaoManager {
-    pub fn begin_transaction(&mut self) -> Result<(), DbError> { self.0.begin_transaction() }
+    /// a dao manager of this database, without the query observers and the timeout of a `Pool`
+    pub fn new(db: DBPlatform) -> Self { DaoManager(db, QueryObservers::default(), None) }
+
+    pub fn begin_transaction(&mut self) -> Result<(), DbError> {
+        self.0.begin_transaction()?;
+        self.1.set_in_transaction(true);
+        Ok(())
+    }
+
+    pub fn commit_transaction(&mut self) -> Result<(), DbError> {
+        self.1.set_in_transaction(false);
+        self.0.commit_transaction()
+    }
+
+    pub fn rollback_transaction(&mut self) -> Result<(), DbError> {
+        self.1.set_in_transaction(false);
+        self.0.rollback_transaction()
+    }
 
-    pub fn commit_transaction(&mut self) -> Result<(), DbError> { self.0.commit_transaction() }
+    /// notify this observer of the statements executed by this dao manager,
+    /// in addition to the observers of the pool
+    pub fn add_query_observer<O>(&mut self, observer: O)
+    where
+        O: QueryObserver + 'static,
+    {
+        self.1.add(Arc::new(observer));
+    }
+
+    /// the hits and misses of the prepared statement cache of the connection
+    pub fn statement_cache_stats(&self) -> StatementCacheStats { self.0.statement_cache_stats() }
 
-    pub fn rollback_transaction(&mut self) -> Result<(), DbError> { self.0.rollback_transaction() }
+    /// cancel the statements of this dao manager running longer than the timeout,
+    /// None to let them run until they complete
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) { self.2 = timeout; }
+
+    /// execute `f` with this timeout for each statement instead of the timeout of this
+    /// dao manager
+    pub fn with_timeout<T, F>(&mut self, timeout: Duration, f: F) -> T
+    where
+        F: FnOnce(&mut Self) -> T,
+    {
+        let previous = self.2.replace(timeout);
+        let result = f(self);
+        self.2 = previous;
+        result
+    }
+
+    /// execute the statement, notifying the query observers
+    fn execute(&mut self, sql: &str, params: &[&Value]) -> Result<Rows, DbError> {
+        let explain_prefix = self.0.explain_prefix();
+        let timeout = self.2;
+        let db = &mut self.0;
+        self.1.observe(sql, params, explain_prefix, |sql, params| {
+            match timeout {
+                Some(timeout) => db.execute_sql_with_timeout(sql, params, timeout),
+                None => db.execute_sql_with_return(sql, params),
+            }
+        })
+    }
 
     pub fn execute_sql_with_return(
         &mut self,
         sql: &str,
         params: &[&Value],
     ) -> Result<Rows, DbError> {
-        let rows = self.0.execute_sql_with_return(sql, params)?;
+        let rows = self.execute(sql, params)?;
         Ok(rows)
     }
 
@@ -32,7 +95,7 @@ impl DaoManager {
         sql: &str,
         params: &[&Value],
     ) -> Result<Vec<Dao>, DbError> {
-        let rows = self.0.execute_sql_with_return(sql, params)?;
+        let rows = self.execute(sql, params)?;
         let daos: Vec<Dao> = rows.iter().collect();
         Ok(daos)
     }
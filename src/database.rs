@@ -4,7 +4,9 @@ use crate::db_auth::{
     User,
 };
 use crate::{
+    statement_cache::StatementCacheStats,
     table::SchemaContent,
+    type_registry::TypeRegistry,
     DbError,
     Rows,
     TableDef,
@@ -13,6 +15,7 @@ use crate::{
 };
 use clia_rustorm_codegen::FromDao;
 use serde::Serialize;
+use std::time::Duration;
 
 /// The current database name and its comment
 #[derive(Serialize, FromDao)]
@@ -30,6 +33,15 @@ pub trait Database {
 
     fn execute_sql_with_return(&mut self, sql: &str, param: &[&Value]) -> Result<Rows, DbError>;
 
+    /// execute the statement, cancelling it with `DbError::Timeout` when it runs longer than
+    /// the timeout
+    fn execute_sql_with_timeout(
+        &mut self,
+        sql: &str,
+        param: &[&Value],
+        timeout: Duration,
+    ) -> Result<Rows, DbError>;
+
     fn get_table(&mut self, table_name: &TableName) -> Result<Option<TableDef>, DbError>;
 
     fn set_autoincrement_value(
@@ -43,6 +55,13 @@ pub trait Database {
         table_name: &TableName,
     ) -> Result<Option<i64>, DbError>;
 
+    /// get the greatest value currently stored in the autoincrement column of this table.
+    /// Returns Ok(None) if the table has no autoincrement column or has no records yet.
+    fn get_autoincrement_max_value(
+        &mut self,
+        table_name: &TableName,
+    ) -> Result<Option<i64>, DbError>;
+
     fn get_all_tables(&mut self) -> Result<Vec<TableDef>, DbError>;
 
     fn get_tablenames(&mut self) -> Result<Vec<TableName>, DbError>;
@@ -51,6 +70,19 @@ pub trait Database {
 
     fn get_database_name(&mut self) -> Result<Option<DatabaseName>, DbError>;
 
+    /// a value which changes whenever a table, column or constraint is created, altered or
+    /// dropped in this database. Returns Ok(None) if the platform can not detect schema changes.
+    fn get_schema_version(&mut self) -> Result<Option<String>, DbError>;
+
+    /// set the number of prepared statements cached by this connection, 0 to disable the cache
+    fn set_statement_cache_capacity(&mut self, capacity: usize);
+
+    /// the counters of the prepared statement cache of this connection
+    fn statement_cache_stats(&self) -> StatementCacheStats;
+
+    /// decode and encode the values of the custom types with the conversions of this registry
+    fn set_type_registry(&mut self, registry: TypeRegistry);
+
     #[cfg(feature = "db-auth")]
     fn get_users(&mut self) -> Result<Vec<User>, DbError>;
 
@@ -0,0 +1,714 @@
+//! Render the tables as an entity relationship diagram in Mermaid, Graphviz DOT or PlantUML.
+//!
+//! ```rust,ignore
+//! let tables = em.get_all_tables()?;
+//! let mermaid = ErDiagram::new(&tables)
+//!     .schema("public")
+//!     .around(&TableName::from("public.film"), 1)
+//!     .to_mermaid();
+//! ```
+use crate::{
+    column::Capacity,
+    table::{
+        ForeignKey,
+        TableKey,
+    },
+    types::SqlType,
+    ColumnDef,
+    SchemaGraph,
+    TableDef,
+    TableName,
+};
+use std::{
+    collections::{
+        BTreeMap,
+        BTreeSet,
+        HashMap,
+        HashSet,
+    },
+    fmt::Write,
+};
+
+/// An entity relationship diagram of the tables which pass all the filters
+pub struct ErDiagram<'a> {
+    tables: &'a [TableDef],
+    schema: Option<String>,
+    table_pattern: Option<String>,
+    around: Option<(TableName, usize)>,
+}
+
+impl<'a> ErDiagram<'a> {
+    pub fn new(tables: &'a [TableDef]) -> Self {
+        ErDiagram {
+            tables,
+            schema: None,
+            table_pattern: None,
+            around: None,
+        }
+    }
+
+    /// only include the tables in this schema
+    pub fn schema(mut self, schema: &str) -> Self {
+        self.schema = Some(schema.to_string());
+        self
+    }
+
+    /// only include the tables which name matches this pattern, where `*` matches any
+    /// characters and `?` matches a single character.
+    /// The pattern is matched against `schema.table` if it contains a `.`
+    pub fn table_pattern(mut self, pattern: &str) -> Self {
+        self.table_pattern = Some(pattern.to_string());
+        self
+    }
+
+    /// only include this table and the tables which are at most `depth` foreign keys away
+    /// from it, in either direction
+    pub fn around(mut self, table_name: &TableName, depth: usize) -> Self {
+        self.around = Some((table_name.clone(), depth));
+        self
+    }
+
+    /// render as a Mermaid `erDiagram`
+    pub fn to_mermaid(&self) -> String {
+        let tables = self.included_tables();
+        let ids = EntityIds::new(&tables);
+        let mut out = String::from("erDiagram\n");
+        for table in tables.iter() {
+            let id = ids.id(&table.name);
+            // the names which are not valid identifiers are shown with an alias
+            if id == ids.label(&table.name).replace('.', "_") {
+                writeln!(out, "    {} {{", id).unwrap();
+            } else {
+                let label = escape_mermaid(&ids.label(&table.name));
+                writeln!(out, "    {}[\"{}\"] {{", id, label).unwrap();
+            }
+            for column in table.columns.iter() {
+                let markers = column_markers(table, column).join(", ");
+                let data_type = mermaid_name(&type_label(column).replace(',', "-"));
+                let name = mermaid_name(&column.name.name);
+                write!(out, "        {} {}", data_type, name).unwrap();
+                if !markers.is_empty() {
+                    write!(out, " {}", markers).unwrap();
+                }
+                if name != column.name.name {
+                    write!(out, " \"{}\"", escape_mermaid(&column.name.name)).unwrap();
+                }
+                out.push('\n');
+            }
+            out.push_str("    }\n");
+        }
+        for (table, foreign_key) in relations(&tables) {
+            let parent = if is_optional(table, foreign_key) {
+                "o|"
+            } else {
+                "||"
+            };
+            writeln!(
+                out,
+                "    {} }}o--{} {} : \"{}\"",
+                ids.id(&table.name),
+                parent,
+                ids.id(&foreign_key.foreign_table),
+                escape_mermaid(foreign_key.name.as_deref().unwrap_or(""))
+            )
+            .unwrap();
+        }
+        out
+    }
+
+    /// render as a Graphviz DOT digraph, the tables are grouped in a cluster per schema
+    pub fn to_dot(&self) -> String {
+        let tables = self.included_tables();
+        let ids = EntityIds::new(&tables);
+        let mut out = String::from("digraph schema {\n");
+        out.push_str("    rankdir=LR;\n");
+        out.push_str("    node [shape=plaintext];\n");
+        for (schema, tables) in group_by_schema(&tables) {
+            let schema = escape_dot(&schema);
+            writeln!(out, "    subgraph \"cluster_{}\" {{", schema).unwrap();
+            writeln!(out, "        label=\"{}\";", schema).unwrap();
+            for table in tables {
+                writeln!(
+                    out,
+                    "        \"{}\" [label=<<table border=\"0\" cellborder=\"1\" cellspacing=\"0\">",
+                    ids.id(&table.name)
+                )
+                .unwrap();
+                writeln!(
+                    out,
+                    "            <tr><td bgcolor=\"lightgrey\"><b>{}</b></td></tr>",
+                    escape_html(&ids.label(&table.name))
+                )
+                .unwrap();
+                for column in table.columns.iter() {
+                    let markers = column_markers(table, column);
+                    let markers = if markers.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" {}", markers.join(", "))
+                    };
+                    writeln!(
+                        out,
+                        "            <tr><td align=\"left\">{} : {}{}</td></tr>",
+                        escape_html(&column.name.name),
+                        escape_html(&type_label(column)),
+                        markers
+                    )
+                    .unwrap();
+                }
+                out.push_str("        </table>>];\n");
+            }
+            out.push_str("    }\n");
+        }
+        for (table, foreign_key) in relations(&tables) {
+            writeln!(
+                out,
+                "    \"{}\" -> \"{}\" [label=\"{}\"];",
+                ids.id(&table.name),
+                ids.id(&foreign_key.foreign_table),
+                escape_dot(foreign_key.name.as_deref().unwrap_or(""))
+            )
+            .unwrap();
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// render as a PlantUML entity diagram, the tables are grouped in a package per schema.
+    /// The mandatory columns are marked with `*` and the primary columns are listed first.
+    pub fn to_plantuml(&self) -> String {
+        let tables = self.included_tables();
+        let ids = EntityIds::new(&tables);
+        let mut out = String::from("@startuml\n");
+        for (schema, tables) in group_by_schema(&tables) {
+            if schema.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                writeln!(out, "package {} {{", schema).unwrap();
+            } else {
+                writeln!(out, "package \"{}\" {{", escape_plantuml(&schema)).unwrap();
+            }
+            for table in tables {
+                writeln!(
+                    out,
+                    "    entity \"{}\" as {} {{",
+                    escape_plantuml(&ids.label(&table.name)),
+                    ids.id(&table.name)
+                )
+                .unwrap();
+                let primary = table.get_primary_columns();
+                let (key_columns, other_columns): (Vec<&ColumnDef>, Vec<&ColumnDef>) =
+                    table.columns.iter().partition(|c| primary.contains(c));
+                for column in key_columns.iter() {
+                    write_plantuml_column(&mut out, table, column);
+                }
+                if !key_columns.is_empty() {
+                    out.push_str("        --\n");
+                }
+                for column in other_columns.iter() {
+                    write_plantuml_column(&mut out, table, column);
+                }
+                out.push_str("    }\n");
+            }
+            out.push_str("}\n");
+        }
+        for (table, foreign_key) in relations(&tables) {
+            let parent = if is_optional(table, foreign_key) {
+                "o|"
+            } else {
+                "||"
+            };
+            writeln!(
+                out,
+                "{} }}o..{} {} : {}",
+                ids.id(&table.name),
+                parent,
+                ids.id(&foreign_key.foreign_table),
+                escape_plantuml(foreign_key.name.as_deref().unwrap_or(""))
+            )
+            .unwrap();
+        }
+        out.push_str("@enduml\n");
+        out
+    }
+
+    /// the tables which pass all the filters, in their original order
+    fn included_tables(&self) -> Vec<&'a TableDef> {
+        let neighborhood = self.around.as_ref().map(|(center, depth)| {
+            let graph = SchemaGraph::new(self.tables);
+            let mut found: HashSet<&TableName> = HashSet::new();
+            if graph.tables().contains(center) {
+                found.insert(center);
+            }
+            let mut current: Vec<&TableName> = found.iter().copied().collect();
+            for _ in 0..*depth {
+                let mut next = vec![];
+                for table_name in current {
+                    for neighbor in graph
+                        .referenced_tables(table_name)
+                        .into_iter()
+                        .chain(graph.referencing_tables(table_name))
+                    {
+                        if found.insert(neighbor) {
+                            next.push(neighbor);
+                        }
+                    }
+                }
+                current = next;
+            }
+            found
+                .into_iter()
+                .map(TableName::complete_name)
+                .collect::<HashSet<String>>()
+        });
+        self.tables
+            .iter()
+            .filter(|table| {
+                self.schema
+                    .as_ref()
+                    .map(|schema| table.name.schema.as_ref() == Some(schema))
+                    .unwrap_or(true)
+            })
+            .filter(|table| {
+                self.table_pattern
+                    .as_ref()
+                    .map(|pattern| {
+                        let name = if pattern.contains('.') {
+                            table.complete_name()
+                        } else {
+                            table.name.name.to_string()
+                        };
+                        matches_pattern(pattern, &name)
+                    })
+                    .unwrap_or(true)
+            })
+            .filter(|table| {
+                neighborhood
+                    .as_ref()
+                    .map(|names| names.contains(&table.complete_name()))
+                    .unwrap_or(true)
+            })
+            .collect()
+    }
+}
+
+/// The identifier of each table in the diagram, the schema is only prepended
+/// when the tables come from several schemas. The characters other than letters, digits and
+/// underscores are replaced, the identifiers made the same this way are numbered.
+struct EntityIds {
+    qualified: bool,
+    ids: HashMap<TableName, String>,
+}
+
+impl EntityIds {
+    fn new(tables: &[&TableDef]) -> Self {
+        let schemas: BTreeSet<&Option<String>> = tables.iter().map(|t| &t.name.schema).collect();
+        let qualified = schemas.len() > 1;
+        let mut used = HashSet::new();
+        let mut ids = HashMap::new();
+        for table in tables {
+            let name = match table.name.schema {
+                Some(ref schema) if qualified => format!("{}_{}", schema, table.name.name),
+                _ => table.name.name.to_string(),
+            };
+            let mut id: String = name
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect();
+            if !id.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                id.insert(0, '_');
+            }
+            let mut unique = id.clone();
+            let mut n = 1;
+            while !used.insert(unique.clone()) {
+                n += 1;
+                unique = format!("{}_{}", id, n);
+            }
+            ids.insert(table.name.clone(), unique);
+        }
+        EntityIds { qualified, ids }
+    }
+
+    fn id(&self, table_name: &TableName) -> &str {
+        self.ids.get(table_name).map(String::as_str).unwrap_or_default()
+    }
+
+    fn label(&self, table_name: &TableName) -> String {
+        if self.qualified {
+            table_name.complete_name()
+        } else {
+            table_name.name.to_string()
+        }
+    }
+}
+
+/// the foreign keys between the included tables
+fn relations<'t>(
+    tables: &[&'t TableDef],
+) -> Vec<(&'t TableDef, &'t ForeignKey)> {
+    let names: Vec<&TableName> = tables.iter().map(|t| &t.name).collect();
+    tables
+        .iter()
+        .flat_map(|table| {
+            table
+                .get_foreign_keys()
+                .into_iter()
+                .map(move |foreign_key| (*table, foreign_key))
+        })
+        .filter(|(_, foreign_key)| names.contains(&&foreign_key.foreign_table))
+        .collect()
+}
+
+/// a foreign key is optional when any of its columns can be null
+fn is_optional(table: &TableDef, foreign_key: &ForeignKey) -> bool {
+    foreign_key.columns.iter().any(|column_name| {
+        table
+            .get_column(column_name)
+            .map(|column| !column.is_not_null())
+            .unwrap_or(true)
+    })
+}
+
+fn group_by_schema<'t>(tables: &[&'t TableDef]) -> BTreeMap<String, Vec<&'t TableDef>> {
+    let mut groups: BTreeMap<String, Vec<&TableDef>> = BTreeMap::new();
+    for table in tables {
+        let schema = table.name.schema.clone().unwrap_or_else(|| "public".to_string());
+        groups.entry(schema).or_default().push(table);
+    }
+    groups
+}
+
+fn column_markers(table: &TableDef, column: &ColumnDef) -> Vec<&'static str> {
+    let mut markers = vec![];
+    if table.is_primary_column(column) {
+        markers.push("PK");
+    }
+    if table.get_foreign_column_names().contains(&&column.name) {
+        markers.push("FK");
+    }
+    if table.table_key.iter().any(|key| {
+        match key {
+            TableKey::UniqueKey(unique) => unique.columns.contains(&column.name),
+            _ => false,
+        }
+    }) {
+        markers.push("UK");
+    }
+    markers
+}
+
+fn write_plantuml_column(out: &mut String, table: &TableDef, column: &ColumnDef) {
+    let mandatory = if column.is_not_null() { "* " } else { "" };
+    let markers: String = column_markers(table, column)
+        .iter()
+        .map(|marker| format!(" <<{}>>", marker))
+        .collect();
+    writeln!(
+        out,
+        "        {}{} : {}{}",
+        mandatory,
+        escape_plantuml(&column.name.name),
+        escape_plantuml(&type_label(column)),
+        markers
+    )
+    .unwrap();
+}
+
+/// the type of the column as shown in the diagram, example: `varchar(45)`, `numeric(4,2)`
+fn type_label(column: &ColumnDef) -> String {
+    let name = sql_type_label(&column.specification.sql_type);
+    match column.specification.capacity {
+        Some(Capacity::Limit(limit)) => format!("{}({})", name, limit),
+        Some(Capacity::Range(whole, decimal)) => format!("{}({},{})", name, whole, decimal),
+        None => name,
+    }
+}
+
+fn sql_type_label(sql_type: &SqlType) -> String {
+    match sql_type {
+        SqlType::Enum(name, _) | SqlType::Custom(name) | SqlType::Composite(name) => {
+            name.to_string()
+        }
+        SqlType::Array(inner) => format!("{}[]", sql_type_label(inner)),
+        SqlType::Geometry(ty, srid) | SqlType::Geography(ty, srid) => {
+            let base = if let SqlType::Geometry(..) = sql_type { "geometry" } else { "geography" };
+            let ty = format!("{:?}", ty).to_lowercase();
+            match srid {
+                Some(srid) => format!("{}({},{})", base, ty, srid),
+                None => format!("{}({})", base, ty),
+            }
+        }
+        _ => format!("{:?}", sql_type).to_lowercase(),
+    }
+}
+
+/// the name of an attribute or a type in mermaid, where the other characters than letters,
+/// digits, `_`, `-`, `(`, `)`, `[` and `]` are replaced
+fn mermaid_name(s: &str) -> String {
+    let mut name: String = s
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || "_-()[]".contains(c) {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// the text inside double quotes in mermaid
+fn escape_mermaid(s: &str) -> String { s.replace('"', "#quot;") }
+
+/// the text inside double quotes in graphviz
+fn escape_dot(s: &str) -> String { s.replace('\\', "\\\\").replace('"', "\\\"") }
+
+/// the text in plantuml, the characters of its syntax are written as unicode code points
+fn escape_plantuml(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            match c {
+                '"' | '{' | '}' | ':' | '<' | '>' => format!("<U+{:04X}>", c as u32),
+                _ => c.to_string(),
+            }
+        })
+        .collect()
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// glob matching where `*` matches any characters and `?` matches a single character
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // the positions in the pattern and the name to resume from on a mismatch after a `*`
+    let (mut p, mut n) = (0, 0);
+    let mut resume: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            resume = Some((p, n));
+            p += 1;
+        } else if let Some((star, matched)) = resume {
+            p = star + 1;
+            n = matched + 1;
+            resume = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        column::{
+            ColumnConstraint,
+            ColumnSpecification,
+        },
+        table::Key,
+        ColumnName,
+    };
+
+    fn column(table: &str, name: &str, sql_type: SqlType, capacity: Option<Capacity>) -> ColumnDef {
+        ColumnDef {
+            table: TableName::from(table),
+            name: ColumnName::from(name),
+            comment: None,
+            specification: ColumnSpecification {
+                sql_type,
+                capacity,
+                constraints: vec![ColumnConstraint::NotNull],
+            },
+            stat: None,
+        }
+    }
+
+    fn primary_key(name: &str, columns: &[&str]) -> TableKey {
+        TableKey::PrimaryKey(Key {
+            name: Some(name.to_string()),
+            columns: columns.iter().map(|c| ColumnName::from(c)).collect(),
+        })
+    }
+
+    fn foreign_key(name: &str, column: &str, foreign_table: &str) -> TableKey {
+        TableKey::ForeignKey(ForeignKey {
+            name: Some(name.to_string()),
+            columns: vec![ColumnName::from(column)],
+            foreign_table: TableName::from(foreign_table),
+            referred_columns: vec![ColumnName::from(column)],
+        })
+    }
+
+    fn sakila() -> Vec<TableDef> {
+        vec![
+            TableDef {
+                name: TableName::from("public.language"),
+                comment: None,
+                columns: vec![
+                    column("public.language", "language_id", SqlType::Int, None),
+                    column("public.language", "name", SqlType::Char, Some(Capacity::Limit(20))),
+                ],
+                is_view: false,
+                table_key: vec![primary_key("language_pkey", &["language_id"])],
+            },
+            TableDef {
+                name: TableName::from("public.film"),
+                comment: None,
+                columns: vec![
+                    column("public.film", "film_id", SqlType::Int, None),
+                    column(
+                        "public.film",
+                        "rental_rate",
+                        SqlType::Numeric,
+                        Some(Capacity::Range(4, 2)),
+                    ),
+                    column("public.film", "language_id", SqlType::Smallint, None),
+                ],
+                is_view: false,
+                table_key: vec![
+                    primary_key("film_pkey", &["film_id"]),
+                    foreign_key("film_language_id_fkey", "language_id", "public.language"),
+                ],
+            },
+            TableDef {
+                name: TableName::from("public.inventory"),
+                comment: None,
+                columns: vec![
+                    column("public.inventory", "inventory_id", SqlType::Int, None),
+                    column("public.inventory", "film_id", SqlType::Int, None),
+                ],
+                is_view: false,
+                table_key: vec![
+                    primary_key("inventory_pkey", &["inventory_id"]),
+                    foreign_key("inventory_film_id_fkey", "film_id", "public.film"),
+                ],
+            },
+            TableDef {
+                name: TableName::from("audit.log"),
+                comment: None,
+                columns: vec![column("audit.log", "message", SqlType::Text, None)],
+                is_view: false,
+                table_key: vec![],
+            },
+        ]
+    }
+
+    #[test]
+    fn mermaid() {
+        let tables = sakila();
+        let mermaid = ErDiagram::new(&tables)
+            .schema("public")
+            .table_pattern("*l*")
+            .to_mermaid();
+        assert_eq!(
+            mermaid,
+            r#"erDiagram
+    language {
+        int language_id PK
+        char(20) name
+    }
+    film {
+        int film_id PK
+        numeric(4-2) rental_rate
+        smallint language_id FK
+    }
+    film }o--|| language : "film_language_id_fkey"
+"#
+        );
+    }
+
+    #[test]
+    fn dot_groups_the_schemas() {
+        let tables = sakila();
+        let dot = ErDiagram::new(&tables).to_dot();
+        assert!(dot.starts_with("digraph schema {\n"));
+        assert!(dot.contains("subgraph \"cluster_audit\""));
+        assert!(dot.contains("subgraph \"cluster_public\""));
+        assert!(dot.contains("<b>public.film</b>"));
+        assert!(dot.contains("<td align=\"left\">film_id : int PK</td>"));
+        assert!(dot.contains(
+            "\"public_inventory\" -> \"public_film\" [label=\"inventory_film_id_fkey\"];"
+        ));
+    }
+
+    #[test]
+    fn plantuml_around_a_table() {
+        let tables = sakila();
+        let plantuml = ErDiagram::new(&tables)
+            .around(&TableName::from("public.inventory"), 1)
+            .to_plantuml();
+        assert_eq!(
+            plantuml,
+            r#"@startuml
+package public {
+    entity "film" as film {
+        * film_id : int <<PK>>
+        --
+        * rental_rate : numeric(4,2)
+        * language_id : smallint <<FK>>
+    }
+    entity "inventory" as inventory {
+        * inventory_id : int <<PK>>
+        --
+        * film_id : int <<FK>>
+    }
+}
+inventory }o..|| film : inventory_film_id_fkey
+@enduml
+"#
+        );
+    }
+
+    #[test]
+    fn names_are_escaped() {
+        let name = TableName {
+            name: "order \"items\" {x}".to_string(),
+            schema: None,
+            alias: None,
+        };
+        let mut order = column("orders", "order id", SqlType::Int, None);
+        order.table = name.clone();
+        let tables = vec![TableDef {
+            name: name.clone(),
+            comment: None,
+            columns: vec![order],
+            is_view: false,
+            table_key: vec![primary_key("order pkey", &["order id"])],
+        }];
+        let diagram = ErDiagram::new(&tables);
+        let mermaid = diagram.to_mermaid();
+        assert!(mermaid.contains("    order__items___x_[\"order #quot;items#quot; {x}\"] {"));
+        assert!(mermaid.contains("        int order_id PK \"order id\""));
+        let dot = diagram.to_dot();
+        assert!(dot.contains("\"order__items___x_\" [label=<"));
+        let plantuml = diagram.to_plantuml();
+        assert!(plantuml.contains(
+            "entity \"order <U+0022>items<U+0022> <U+007B>x<U+007D>\" as order__items___x_ {"
+        ));
+        assert!(plantuml.contains("order id : int"));
+    }
+
+    #[test]
+    fn glob_pattern() {
+        assert!(matches_pattern("film*", "film_actor"));
+        assert!(matches_pattern("film*", "film"));
+        assert!(matches_pattern("*_actor", "film_actor"));
+        assert!(matches_pattern("f?lm", "film"));
+        assert!(matches_pattern("public.*", "public.film"));
+        assert!(!matches_pattern("film", "film_actor"));
+        assert!(!matches_pattern("*_actor", "actor"));
+        assert!(matches_pattern("*a*b*", "xaxxbx"));
+    }
+}
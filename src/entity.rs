@@ -4,18 +4,36 @@ use crate::db_auth::{
     User,
 };
 use crate::{
+    cache::DbMetadataCache,
+    explain::{
+        self,
+        ExplainOptions,
+        QueryPlan,
+    },
+    observer::{
+        QueryObserver,
+        QueryObservers,
+    },
+    statement_cache::StatementCacheStats,
     table::SchemaContent,
+    type_registry::TypeRegistry,
     DBPlatform,
     DataError,
     Database,
     DatabaseName,
     DbError,
     Rows,
+    SchemaGraph,
     TableDef,
     ToValue,
     Value,
 };
 
+use std::{
+    sync::Arc,
+    time::Duration,
+};
+
 use clia_rustorm_dao::{
     FromDao,
     TableName,
@@ -24,18 +42,37 @@ use clia_rustorm_dao::{
     ToTableName,
 };
 
-pub struct EntityManager(pub DBPlatform);
+pub struct EntityManager(
+    pub DBPlatform,
+    pub(crate) Option<DbMetadataCache>,
+    pub(crate) QueryObservers,
+    pub(crate) Option<Duration>,
+);
 
 impl EntityManager {
-    pub fn begin_transaction(&mut self) -> Result<(), DbError> { self.0.begin_transaction() }
+    /// an entity manager of this database, without the metadata cache, the query observers
+    /// and the timeout of a `Pool`
+    pub fn new(db: DBPlatform) -> Self { EntityManager(db, None, QueryObservers::default(), None) }
 
-    pub fn commit_transaction(&mut self) -> Result<(), DbError> { self.0.commit_transaction() }
+    pub fn begin_transaction(&mut self) -> Result<(), DbError> {
+        self.0.begin_transaction()?;
+        self.2.set_in_transaction(true);
+        Ok(())
+    }
 
-    pub fn rollback_transaction(&mut self) -> Result<(), DbError> { self.0.rollback_transaction() }
+    pub fn commit_transaction(&mut self) -> Result<(), DbError> {
+        self.2.set_in_transaction(false);
+        self.0.commit_transaction()
+    }
+
+    pub fn rollback_transaction(&mut self) -> Result<(), DbError> {
+        self.2.set_in_transaction(false);
+        self.0.rollback_transaction()
+    }
 
     pub fn set_session_user(&mut self, username: &str) -> Result<(), DbError> {
-        let sql = format!("SET SESSION ROLE '{}'", username);
-        self.0.execute_sql_with_return(&sql, &[])?;
+        let sql = format!("SET SESSION ROLE {}", self.0.dialect().quote_exact(username)?);
+        self.execute(&sql, &[])?;
         Ok(())
     }
 
@@ -63,24 +100,67 @@ impl EntityManager {
 
     pub fn db(&mut self) -> &mut dyn Database { &mut *self.0 }
 
+    /// notify this observer of the statements executed by this entity manager,
+    /// in addition to the observers of the pool
+    pub fn add_query_observer<O>(&mut self, observer: O)
+    where
+        O: QueryObserver + 'static,
+    {
+        self.2.add(Arc::new(observer));
+    }
+
+    /// the hits and misses of the prepared statement cache of the connection
+    pub fn statement_cache_stats(&self) -> StatementCacheStats { self.0.statement_cache_stats() }
+
+    /// decode and encode the values of the custom types with the conversions of this registry
+    /// instead of the registry of the pool
+    pub fn set_type_registry(&mut self, registry: TypeRegistry) {
+        self.0.set_type_registry(registry);
+    }
+
+    /// cancel the statements of this entity manager running longer than the timeout,
+    /// None to let them run until they complete
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) { self.3 = timeout; }
+
+    /// execute `f` with this timeout for each statement instead of the timeout of this
+    /// entity manager
+    pub fn with_timeout<T, F>(&mut self, timeout: Duration, f: F) -> T
+    where
+        F: FnOnce(&mut Self) -> T,
+    {
+        let previous = self.3.replace(timeout);
+        let result = f(self);
+        self.3 = previous;
+        result
+    }
+
+    /// execute the statement, notifying the query observers
+    fn execute(&mut self, sql: &str, params: &[&Value]) -> Result<Rows, DbError> {
+        let explain_prefix = self.0.explain_prefix();
+        let timeout = self.3;
+        let db = &mut self.0;
+        self.2.observe(sql, params, explain_prefix, |sql, params| {
+            match timeout {
+                Some(timeout) => db.execute_sql_with_timeout(sql, params, timeout),
+                None => db.execute_sql_with_return(sql, params),
+            }
+        })
+    }
+
     /// get all the records of this table
     pub fn get_all<T>(&mut self) -> Result<Vec<T>, DbError>
     where
         T: ToTableName + ToColumnNames + FromDao,
     {
+        let dialect = self.0.dialect();
         let table = T::to_table_name();
         let columns = T::to_column_names();
-        let enumerated_columns = columns
-            .iter()
-            .map(|c| c.name.to_owned())
-            .collect::<Vec<_>>()
-            .join(", ");
         let sql = format!(
             "SELECT {} FROM {}",
-            enumerated_columns,
-            table.complete_name()
+            dialect.column_list(&columns)?,
+            dialect.table_name(&table)?
         );
-        let rows = self.0.execute_sql_with_return(&sql, &[])?;
+        let rows = self.execute(&sql, &[])?;
         let mut entities = vec![];
         for dao in rows.iter() {
             let entity = T::from_dao(&dao);
@@ -91,7 +171,25 @@ impl EntityManager {
 
     /// get the table from database based on this column name
     pub fn get_table(&mut self, table_name: &TableName) -> Result<Option<TableDef>, DbError> {
-        self.0.get_table(table_name)
+        match self.1 {
+            Some(ref cache) => cache.get_table(&mut *self.0, table_name),
+            None => self.0.get_table(table_name),
+        }
+    }
+
+    /// remove this table from the metadata cache of the pool, this is needed
+    /// after altering the table when the cache is not set to auto invalidate.
+    pub fn invalidate_table(&self, table_name: &TableName) {
+        if let Some(ref cache) = self.1 {
+            cache.invalidate_table(table_name);
+        }
+    }
+
+    /// remove all the tables of this database from the metadata cache of the pool
+    pub fn invalidate_metadata(&self) {
+        if let Some(ref cache) = self.1 {
+            cache.invalidate();
+        }
     }
 
     /// set the autoincrement value of the primary column(if present) of this table.
@@ -111,10 +209,38 @@ impl EntityManager {
         self.0.get_autoincrement_last_value(table_name)
     }
 
+    /// set the autoincrement value of every table to the greatest value in its autoincrement
+    /// column. This is needed after a bulk data import where the primary keys are supplied.
+    /// Tables with no autoincrement column or with no records are left untouched.
+    /// Returns the tables that were adjusted together with their new value.
+    pub fn reset_sequences_to_max(&mut self) -> Result<Vec<(TableName, i64)>, DbError> {
+        let tablenames = self.get_tablenames()?;
+        let mut reset = vec![];
+        for table_name in tablenames {
+            if let Some(max_value) = self.0.get_autoincrement_max_value(&table_name)? {
+                if let Some(value) = self.set_autoincrement_value(&table_name, max_value)? {
+                    reset.push((table_name, value));
+                }
+            }
+        }
+        Ok(reset)
+    }
+
     /// get all the user table and views from the database
     pub fn get_all_tables(&mut self) -> Result<Vec<TableDef>, DbError> {
-        info!("EXPENSIVE DB OPERATION: get_all_tables");
-        self.0.get_all_tables()
+        match self.1 {
+            Some(ref cache) => cache.get_all_tables(&mut *self.0),
+            None => {
+                info!("EXPENSIVE DB OPERATION: get_all_tables");
+                self.0.get_all_tables()
+            }
+        }
+    }
+
+    /// get the dependency graph of all the tables in the database
+    pub fn get_schema_graph(&mut self) -> Result<SchemaGraph, DbError> {
+        let tables = self.get_all_tables()?;
+        Ok(SchemaGraph::new(&tables))
     }
 
     /// get all the tablenames
@@ -128,7 +254,7 @@ impl EntityManager {
         }
         let sql = format!(
             "SELECT COUNT(*) AS count FROM {}",
-            table_name.complete_name()
+            self.0.dialect().table_name(table_name)?
         );
         let count: Result<Count, DbError> = self.execute_sql_with_one_return(&sql, &[]);
         count.map(|c| c.count as usize)
@@ -169,9 +295,9 @@ impl EntityManager {
         R: FromDao + ToColumnNames,
     {
         let columns = T::to_column_names();
-        let mut sql = self.build_insert_clause(entities);
+        let mut sql = self.build_insert_clause(entities)?;
         let return_columns = R::to_column_names();
-        sql += &self.build_returning_clause(return_columns);
+        sql += &self.build_returning_clause(return_columns)?;
 
         let mut values: Vec<Value> = Vec::with_capacity(entities.len() * columns.len());
         for entity in entities {
@@ -185,7 +311,7 @@ impl EntityManager {
             }
         }
         let bvalues: Vec<&Value> = values.iter().collect();
-        let rows = self.0.execute_sql_with_return(&sql, &bvalues)?;
+        let rows = self.execute(&sql, &bvalues)?;
         let mut retrieved_entities = vec![];
         for dao in rows.iter() {
             let retrieved = R::from_dao(&dao);
@@ -201,7 +327,7 @@ impl EntityManager {
         T: ToTableName + ToColumnNames + ToDao,
     {
         let columns = T::to_column_names();
-        let sql = self.build_insert_clause(&[entity]);
+        let sql = self.build_insert_clause(&[entity])?;
         let dao = entity.to_dao();
         let mut values: Vec<Value> = Vec::with_capacity(columns.len());
         for col in columns.iter() {
@@ -212,7 +338,7 @@ impl EntityManager {
             }
         }
         let bvalues: Vec<&Value> = values.iter().collect();
-        self.0.execute_sql_with_return(&sql, &bvalues)?;
+        self.execute(&sql, &bvalues)?;
         Ok(())
     }
 
@@ -222,14 +348,9 @@ impl EntityManager {
         T: ToTableName + ToColumnNames + ToDao,
         R: FromDao + ToColumnNames,
     {
-        let return_columns = R::to_column_names();
-        let return_column_names = return_columns
-            .iter()
-            .map(|rc| rc.name.to_owned())
-            .collect::<Vec<_>>()
-            .join(", ");
-
-        let table = T::to_table_name();
+        let dialect = self.0.dialect();
+        let return_column_names = dialect.column_list(&R::to_column_names())?;
+        let table = dialect.table_name(&T::to_table_name())?;
         //TODO: move this specific query to sqlite
         let last_insert_sql = format!(
             "\
@@ -237,12 +358,9 @@ impl EntityManager {
              FROM {} \
              WHERE ROWID = (\
              SELECT LAST_INSERT_ROWID() FROM {})",
-            return_column_names,
-            table.complete_name(),
-            table.complete_name()
+            return_column_names, table, table
         );
         let mut retrieved_entities = vec![];
-        println!("sql: {}", last_insert_sql);
         for entity in entities {
             self.single_insert(*entity)?;
             let retrieved = self.execute_sql_with_return(&last_insert_sql, &[])?;
@@ -252,35 +370,28 @@ impl EntityManager {
     }
 
     /// build the returning clause
-    fn build_returning_clause(&self, return_columns: Vec<clia_rustorm_dao::ColumnName>) -> String {
-        format!(
+    fn build_returning_clause(
+        &self,
+        return_columns: Vec<clia_rustorm_dao::ColumnName>,
+    ) -> Result<String, DbError> {
+        Ok(format!(
             "\nRETURNING \n{}",
-            return_columns
-                .iter()
-                .map(|rc| rc.name.to_owned())
-                .collect::<Vec<_>>()
-                .join(", ")
-        )
+            self.0.dialect().column_list(&return_columns)?
+        ))
     }
 
     /// build an insert clause
-    fn build_insert_clause<T>(&self, entities: &[&T]) -> String
+    fn build_insert_clause<T>(&self, entities: &[&T]) -> Result<String, DbError>
     where
         T: ToTableName + ToColumnNames + ToDao,
     {
+        let dialect = self.0.dialect();
         let table = T::to_table_name();
         let columns = T::to_column_names();
         let columns_len = columns.len();
         let mut sql = String::new();
-        sql += &format!("INSERT INTO {} ", table.complete_name());
-        sql += &format!(
-            "({})\n",
-            columns
-                .iter()
-                .map(|c| c.name.to_owned())
-                .collect::<Vec<_>>()
-                .join(", ")
-        );
+        sql += &format!("INSERT INTO {} ", dialect.table_name(&table)?);
+        sql += &format!("({})\n", dialect.column_list(&columns)?);
         sql += "VALUES ";
         sql += &entities
             .iter()
@@ -309,7 +420,7 @@ impl EntityManager {
             })
             .collect::<Vec<_>>()
             .join(", ");
-        sql
+        Ok(sql)
     }
 
     #[allow(clippy::redundant_closure)]
@@ -323,16 +434,30 @@ impl EntityManager {
     {
         let values: Vec<Value> = params.iter().map(|p| p.to_value()).collect();
         let bvalues: Vec<&Value> = values.iter().collect();
-        let rows = self.0.execute_sql_with_return(sql, &bvalues)?;
+        let rows = self.execute(sql, &bvalues)?;
         Ok(rows.iter().map(|dao| R::from_dao(&dao)).collect::<Vec<R>>())
     }
 
+    /// the query plan of the statement, see `explain::ExplainOptions` for what can be included
+    pub fn explain<'a>(
+        &mut self,
+        sql: &str,
+        params: &[&'a dyn ToValue],
+        options: &ExplainOptions,
+    ) -> Result<QueryPlan, DbError> {
+        let explain_sql = explain::explain_sql(&self.0, sql, options)?;
+        let values: Vec<Value> = params.iter().map(|p| p.to_value()).collect();
+        let bvalues: Vec<&Value> = values.iter().collect();
+        let rows = self.execute(&explain_sql, &bvalues)?;
+        explain::parse_plan(&self.0, &rows)
+    }
+
     pub fn raw_execute_sql_with_return(
         &mut self,
         sql: &str,
         params: &[&Value],
     ) -> Result<Rows, DbError> {
-        self.0.execute_sql_with_return(sql, params)
+        self.execute(sql, params)
     }
 
     pub fn execute_sql_with_one_return<'a, R>(
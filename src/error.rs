@@ -1,5 +1,7 @@
 use cfg_if::cfg_if;
+use clia_rustorm_dao::DaoError;
 use r2d2;
+use std::time::Duration;
 use thiserror::Error;
 use url;
 
@@ -77,6 +79,14 @@ impl From<PlatformError> for DataOpError {
                                 datatype: db_err.datatype().map(String::from),
                                 sql: sql.to_owned(),
                             }
+                        } else if pg_err.is_closed()
+                            || matches!(std::error::Error::source(pg_err),
+                                Some(e) if e.is::<std::io::Error>())
+                        {
+                            DataOpError::ConnectionLost {
+                                message: pg_err.to_string(),
+                                sql: Some(sql.to_owned()),
+                            }
                         } else {
                             DataOpError::GenericError {
                                 message: postgres_err.to_string(),
@@ -94,17 +104,21 @@ impl From<PlatformError> for DataOpError {
             }
             #[cfg(feature = "with-sqlite")]
             PlatformError::SqliteError(e) => {
-                DataOpError::GenericError {
-                    message: e.to_string(),
-                    sql: None,
-                }
+                e.to_data_op_error().unwrap_or_else(|| {
+                    DataOpError::GenericError {
+                        message: e.to_string(),
+                        sql: None,
+                    }
+                })
             }
             #[cfg(feature = "with-mysql")]
             PlatformError::MysqlError(e) => {
-                DataOpError::GenericError {
-                    message: e.to_string(),
-                    sql: None,
-                }
+                e.to_data_op_error().unwrap_or_else(|| {
+                    DataOpError::GenericError {
+                        message: e.to_string(),
+                        sql: None,
+                    }
+                })
             }
         }
     }
@@ -145,9 +159,81 @@ pub enum DbError {
     #[error("{0}")]
     ConvertError(#[from] ConvertError),
     #[error("{0}")]
+    DaoError(#[from] DaoError),
+    #[error("{0}")]
     ConnectError(#[from] ConnectError), //agnostic connection error
     #[error("Unsupported operation: {0}")]
     UnsupportedOperation(String),
+    /// the statement is cancelled because it ran longer than the timeout
+    #[error("Query timed out after {timeout:?}: {sql}")]
+    Timeout { sql: String, timeout: Duration },
+}
+
+/// The error codes of each class of errors, these are the SQLSTATE codes of postgresql,
+/// the extended result code names of sqlite and the error numbers of mysql
+const UNIQUE_VIOLATION: &[&str] = &[
+    "23505",
+    "SQLITE_CONSTRAINT_UNIQUE",
+    "SQLITE_CONSTRAINT_PRIMARYKEY",
+    "1062",
+    "1586",
+];
+const FOREIGN_KEY_VIOLATION: &[&str] =
+    &["23503", "SQLITE_CONSTRAINT_FOREIGNKEY", "1216", "1217", "1451", "1452"];
+const NOT_NULL_VIOLATION: &[&str] = &["23502", "SQLITE_CONSTRAINT_NOTNULL", "1048", "1364"];
+const DEADLOCK: &[&str] = &["40P01", "1213"];
+const SERIALIZATION_FAILURE: &[&str] = &["40001", "SQLITE_BUSY_SNAPSHOT"];
+const CONNECTION_LOST: &[&str] = &[
+    "08000", "08003", "08006", "08001", "08004", "57P01", "57P02", "57P03",
+];
+/// a lock could not be acquired in time, retrying may succeed
+const LOCK_NOT_AVAILABLE: &[&str] = &["55P03", "SQLITE_BUSY", "SQLITE_LOCKED", "1205"];
+const QUERY_CANCELED: &[&str] = &["57014", "SQLITE_INTERRUPT", "3024", "1317"];
+
+impl DbError {
+    /// a unique key or primary key constraint is violated
+    pub fn is_unique_violation(&self) -> bool { self.has_code(UNIQUE_VIOLATION) }
+
+    /// the referred record does not exist or the record is still referred to
+    pub fn is_foreign_key_violation(&self) -> bool { self.has_code(FOREIGN_KEY_VIOLATION) }
+
+    /// a null value is used for a column which does not allow null
+    pub fn is_not_null_violation(&self) -> bool { self.has_code(NOT_NULL_VIOLATION) }
+
+    /// the transaction is aborted to break a deadlock with another transaction
+    pub fn is_deadlock(&self) -> bool { self.has_code(DEADLOCK) }
+
+    /// the transaction could not be serialized with the concurrent transactions
+    pub fn is_serialization_failure(&self) -> bool { self.has_code(SERIALIZATION_FAILURE) }
+
+    /// the connection to the database server is closed or broken
+    pub fn is_connection_lost(&self) -> bool {
+        matches!(self, DbError::DataOpError(DataOpError::ConnectionLost { .. }))
+            || self.has_code(CONNECTION_LOST)
+    }
+
+    /// the statement ran longer than the timeout of the entity manager, or it was cancelled by
+    /// the server such as with the `statement_timeout` of postgresql
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, DbError::Timeout { .. }) || self.has_code(QUERY_CANCELED)
+    }
+
+    /// executing the statement or the transaction again may succeed
+    pub fn is_retryable(&self) -> bool {
+        self.is_deadlock()
+            || self.is_serialization_failure()
+            || self.is_connection_lost()
+            || self.has_code(LOCK_NOT_AVAILABLE)
+    }
+
+    fn has_code(&self, codes: &[&str]) -> bool {
+        match self {
+            DbError::DataOpError(DataOpError::ConstraintError { code, .. }) => {
+                codes.contains(&code.as_str())
+            }
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, Error)]
@@ -170,14 +256,22 @@ pub enum DataOpError {
         message: String,
         sql: Option<String>,
     },
+    /// The connection to the database server was closed while executing the statement
+    #[error("Connection lost: {message}")]
+    ConnectionLost {
+        message: String,
+        sql: Option<String>,
+    },
 }
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum ConvertError {
     #[error("Unknown data type")]
     UnknownDataType,
     #[error("Unsupported data type {0}")]
     UnsupportedDataType(String),
+    #[error("Unsupported conversion from {0} to {1}")]
+    UnsupportedConversion(String, String),
 }
 
 #[derive(Debug, Error)]
@@ -189,3 +283,69 @@ pub enum DataError {
     #[error("Table {0} not found")]
     TableNameNotFound(String),
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn db_error(code: &str) -> DbError {
+        DbError::DataOpError(DataOpError::ConstraintError {
+            severity: "ERROR".into(),
+            code: code.into(),
+            message: "".into(),
+            detail: None,
+            cause_table: None,
+            constraint: None,
+            column: None,
+            datatype: None,
+            sql: "".into(),
+        })
+    }
+
+    #[test]
+    fn classify_across_platforms() {
+        for code in &["23505", "SQLITE_CONSTRAINT_UNIQUE", "1062"] {
+            assert!(db_error(code).is_unique_violation());
+            assert!(!db_error(code).is_foreign_key_violation());
+            assert!(!db_error(code).is_retryable());
+        }
+        for code in &["23503", "SQLITE_CONSTRAINT_FOREIGNKEY", "1451", "1452"] {
+            assert!(db_error(code).is_foreign_key_violation());
+        }
+        for code in &["23502", "SQLITE_CONSTRAINT_NOTNULL", "1048"] {
+            assert!(db_error(code).is_not_null_violation());
+        }
+        assert!(db_error("40P01").is_deadlock());
+        assert!(db_error("1213").is_retryable());
+        assert!(db_error("40001").is_serialization_failure());
+        assert!(db_error("SQLITE_BUSY").is_retryable());
+        assert!(db_error("57P01").is_connection_lost());
+    }
+
+    #[test]
+    fn connection_lost_is_retryable() {
+        let error = DbError::DataOpError(DataOpError::ConnectionLost {
+            message: "connection closed".into(),
+            sql: None,
+        });
+        assert!(error.is_connection_lost());
+        assert!(error.is_retryable());
+        assert!(!error.is_unique_violation());
+        assert!(!DbError::DataError(DataError::ZeroRecordReturned).is_retryable());
+    }
+
+    #[test]
+    fn timeouts() {
+        let error = DbError::Timeout {
+            sql: "SELECT pg_sleep(10)".into(),
+            timeout: Duration::from_millis(1500),
+        };
+        assert!(error.is_timeout());
+        assert!(!error.is_retryable());
+        assert_eq!(error.to_string(), "Query timed out after 1.5s: SELECT pg_sleep(10)");
+        for code in &["57014", "SQLITE_INTERRUPT", "3024"] {
+            assert!(db_error(code).is_timeout());
+        }
+        assert!(!db_error("55P03").is_timeout());
+    }
+}
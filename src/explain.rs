@@ -0,0 +1,430 @@
+//! The query plan of a statement, as reported by `EXPLAIN` and parsed into a tree common to
+//! the supported database platforms.
+//!
+//! - postgresql: `EXPLAIN (FORMAT JSON, ANALYZE, BUFFERS)`
+//! - mysql: `EXPLAIN FORMAT=JSON`
+//! - sqlite: `EXPLAIN QUERY PLAN`
+use crate::{
+    error::ConvertError,
+    DBPlatform,
+    DbError,
+    Rows,
+    Value,
+};
+use serde_json::Value as Json;
+
+/// The options of `EntityManager::explain`
+#[derive(Debug, Clone, Default)]
+pub struct ExplainOptions {
+    analyze: bool,
+    buffers: bool,
+}
+
+impl ExplainOptions {
+    pub fn new() -> Self { Default::default() }
+
+    /// execute the statement to get the actual number of rows, postgresql only.
+    ///
+    /// Note: the statement is really executed, wrap data modifying statements in a transaction
+    /// which is rolled back.
+    pub fn analyze(mut self, analyze: bool) -> Self {
+        self.analyze = analyze;
+        self
+    }
+
+    /// include the buffer usage, postgresql only
+    pub fn buffers(mut self, buffers: bool) -> Self {
+        self.buffers = buffers;
+        self
+    }
+}
+
+/// A node of the query plan
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PlanNode {
+    /// the operation as named by the database, ie: `Seq Scan`, `ALL`, `SEARCH`
+    pub node_type: String,
+    /// the table read by this node
+    pub relation: Option<String>,
+    /// the index used by this node
+    pub index: Option<String>,
+    pub estimated_rows: Option<f64>,
+    /// the actual number of rows, only available with `analyze`
+    pub actual_rows: Option<f64>,
+    pub cost: Option<f64>,
+    /// the whole table is read without using an index
+    pub sequential_scan: bool,
+    pub children: Vec<PlanNode>,
+}
+
+impl PlanNode {
+    /// this node and all its descendants, depth first
+    pub fn nodes(&self) -> Vec<&PlanNode> {
+        let mut nodes = vec![self];
+        for child in self.children.iter() {
+            nodes.extend(child.nodes());
+        }
+        nodes
+    }
+}
+
+/// The query plan of a statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryPlan {
+    pub root: PlanNode,
+    /// the output of the database
+    pub raw: String,
+}
+
+impl QueryPlan {
+    /// the sequential scans reading at least `min_rows` rows.
+    /// The scans without row estimate, which is always the case in sqlite, are all included.
+    pub fn sequential_scans(&self, min_rows: f64) -> Vec<&PlanNode> {
+        self.root
+            .nodes()
+            .into_iter()
+            .filter(|node| node.sequential_scan)
+            .filter(|node| {
+                !matches!(node.actual_rows.or(node.estimated_rows), Some(rows) if rows < min_rows)
+            })
+            .collect()
+    }
+
+    /// whether the plan has a sequential scan reading at least `min_rows` rows
+    pub fn has_sequential_scan(&self, min_rows: f64) -> bool {
+        !self.sequential_scans(min_rows).is_empty()
+    }
+}
+
+/// the statement showing the query plan of `sql`
+pub(crate) fn explain_sql(
+    platform: &DBPlatform,
+    sql: &str,
+    options: &ExplainOptions,
+) -> Result<String, DbError> {
+    match *platform {
+        #[cfg(feature = "with-postgres")]
+        DBPlatform::Postgres(_) => {
+            let mut explain_options = vec!["FORMAT JSON"];
+            if options.analyze {
+                explain_options.push("ANALYZE");
+            }
+            if options.buffers {
+                explain_options.push("BUFFERS");
+            }
+            Ok(format!("EXPLAIN ({}) {}", explain_options.join(", "), sql))
+        }
+        #[cfg(feature = "with-sqlite")]
+        DBPlatform::Sqlite(_) => {
+            unsupported_options(options, "sqlite")?;
+            Ok(format!("EXPLAIN QUERY PLAN {}", sql))
+        }
+        #[cfg(feature = "with-mysql")]
+        DBPlatform::Mysql(_) => {
+            unsupported_options(options, "mysql")?;
+            Ok(format!("EXPLAIN FORMAT=JSON {}", sql))
+        }
+    }
+}
+
+#[cfg(any(feature = "with-sqlite", feature = "with-mysql"))]
+fn unsupported_options(options: &ExplainOptions, platform: &str) -> Result<(), DbError> {
+    if options.analyze || options.buffers {
+        Err(DbError::UnsupportedOperation(format!(
+            "explain with analyze or buffers in {}",
+            platform
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// parse the result of the statement built by `explain_sql`
+pub(crate) fn parse_plan(platform: &DBPlatform, rows: &Rows) -> Result<QueryPlan, DbError> {
+    match *platform {
+        #[cfg(feature = "with-postgres")]
+        DBPlatform::Postgres(_) => parse_postgres(&first_text(rows)?),
+        #[cfg(feature = "with-sqlite")]
+        DBPlatform::Sqlite(_) => Ok(parse_sqlite(rows)),
+        #[cfg(feature = "with-mysql")]
+        DBPlatform::Mysql(_) => parse_mysql(&first_text(rows)?),
+    }
+}
+
+/// the json document of the query plan
+#[cfg(any(feature = "with-postgres", feature = "with-mysql"))]
+fn first_text(rows: &Rows) -> Result<String, DbError> {
+    match rows.data.first().and_then(|row| row.first()) {
+        Some(Value::Json(json)) => Ok(json.to_string()),
+        Some(Value::Text(text)) => Ok(text.to_owned()),
+        other => Err(invalid_plan(&format!("{:?}", other))),
+    }
+}
+
+fn invalid_plan(raw: &str) -> DbError {
+    ConvertError::UnsupportedConversion(raw.to_string(), "query plan".to_string()).into()
+}
+
+fn parse_json(raw: &str) -> Result<Json, DbError> {
+    serde_json::from_str(raw).map_err(|_| invalid_plan(raw))
+}
+
+/// the number in either a json number or a json string
+fn number(json: &Json) -> Option<f64> {
+    match json {
+        Json::Number(n) => n.as_f64(),
+        Json::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
+
+fn string(json: &Json) -> Option<String> { json.as_str().map(String::from) }
+
+fn parse_postgres(raw: &str) -> Result<QueryPlan, DbError> {
+    fn node(plan: &Json) -> PlanNode {
+        let node_type = plan["Node Type"].as_str().unwrap_or_default().to_string();
+        let relation = match (string(&plan["Schema"]), string(&plan["Relation Name"])) {
+            (Some(schema), Some(relation)) => Some(format!("{}.{}", schema, relation)),
+            (None, relation) => relation,
+            (Some(_), None) => None,
+        };
+        PlanNode {
+            // `Parallel Seq Scan` as well
+            sequential_scan: node_type.ends_with("Seq Scan"),
+            node_type,
+            relation,
+            index: string(&plan["Index Name"]),
+            estimated_rows: number(&plan["Plan Rows"]),
+            actual_rows: number(&plan["Actual Rows"]),
+            cost: number(&plan["Total Cost"]),
+            children: plan["Plans"]
+                .as_array()
+                .map(|plans| plans.iter().map(node).collect())
+                .unwrap_or_default(),
+        }
+    }
+    let json = parse_json(raw)?;
+    match json[0].get("Plan") {
+        Some(plan) => {
+            Ok(QueryPlan {
+                root: node(plan),
+                raw: raw.to_string(),
+            })
+        }
+        None => Err(invalid_plan(raw)),
+    }
+}
+
+fn parse_mysql(raw: &str) -> Result<QueryPlan, DbError> {
+    /// the tables found in this part of the plan, not descending into the tables
+    fn tables(json: &Json) -> Vec<PlanNode> {
+        match json {
+            Json::Object(object) => {
+                object
+                    .iter()
+                    .flat_map(|(key, value)| {
+                        if key == "table" {
+                            vec![table(value)]
+                        } else {
+                            tables(value)
+                        }
+                    })
+                    .collect()
+            }
+            Json::Array(array) => array.iter().flat_map(tables).collect(),
+            _ => vec![],
+        }
+    }
+    fn table(json: &Json) -> PlanNode {
+        let access_type = json["access_type"].as_str().unwrap_or_default().to_string();
+        let subqueries = json
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter(|(key, _)| key.ends_with("subqueries"))
+            .flat_map(|(_, value)| tables(value))
+            .collect();
+        PlanNode {
+            sequential_scan: access_type == "ALL",
+            node_type: access_type,
+            relation: string(&json["table_name"]),
+            index: string(&json["key"]),
+            estimated_rows: number(&json["rows_examined_per_scan"]),
+            actual_rows: None,
+            cost: number(&json["cost_info"]["read_cost"]),
+            children: subqueries,
+        }
+    }
+    let json = parse_json(raw)?;
+    let query_block = &json["query_block"];
+    if query_block.is_null() {
+        return Err(invalid_plan(raw));
+    }
+    Ok(QueryPlan {
+        root: PlanNode {
+            node_type: "query_block".to_string(),
+            cost: number(&query_block["cost_info"]["query_cost"]),
+            children: tables(query_block),
+            ..Default::default()
+        },
+        raw: raw.to_string(),
+    })
+}
+
+/// the rows of `EXPLAIN QUERY PLAN` are (id, parent, notused, detail), where the detail
+/// is in the form of `SEARCH film USING INDEX idx_title (title=?)`
+fn parse_sqlite(rows: &Rows) -> QueryPlan {
+    let steps: Vec<(i64, i64, String)> = rows
+        .iter()
+        .filter_map(|row| {
+            let id: i64 = row.get("id").ok()?;
+            let parent: i64 = row.get("parent").ok()?;
+            let detail: String = row.get("detail").ok()?;
+            Some((id, parent, detail))
+        })
+        .collect();
+
+    fn node(detail: &str) -> PlanNode {
+        let mut words = detail.split_whitespace();
+        let node_type = words.next().unwrap_or_default().to_string();
+        let is_table_access = node_type == "SCAN" || node_type == "SEARCH";
+        // older versions of sqlite write `SCAN TABLE film`, the constant rows and the subqueries
+        // are scanned as `SCAN CONSTANT ROW`, `SCAN 3 CONSTANT ROWS` and `SCAN (subquery-1)` or
+        // `SCAN SUBQUERY 1` which are not tables
+        let relation = match words.next() {
+            Some("TABLE") => words.next(),
+            relation => relation,
+        }
+        .filter(|relation| {
+            is_table_access
+                && !matches!(*relation, "CONSTANT" | "SUBQUERY")
+                && !relation.starts_with(|c: char| c == '(' || c.is_ascii_digit())
+        })
+        .map(String::from);
+        let index = if detail.contains("USING INTEGER PRIMARY KEY") {
+            Some("rowid".to_string())
+        } else {
+            detail
+                .split_once(" INDEX ")
+                .and_then(|(_, rest)| rest.split_whitespace().next())
+                .map(String::from)
+        };
+        PlanNode {
+            sequential_scan: node_type == "SCAN" && relation.is_some() && index.is_none(),
+            node_type: if is_table_access {
+                node_type
+            } else {
+                detail.to_string()
+            },
+            relation,
+            index,
+            ..Default::default()
+        }
+    }
+    fn children(steps: &[(i64, i64, String)], parent: i64) -> Vec<PlanNode> {
+        steps
+            .iter()
+            .filter(|(_, step_parent, _)| *step_parent == parent)
+            .map(|(id, _, detail)| {
+                let mut node = node(detail);
+                node.children = children(steps, *id);
+                node
+            })
+            .collect()
+    }
+    QueryPlan {
+        root: PlanNode {
+            node_type: "QUERY PLAN".to_string(),
+            children: children(&steps, 0),
+            ..Default::default()
+        },
+        raw: steps
+            .iter()
+            .map(|(_, _, detail)| detail.as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn postgres_plan() {
+        let raw = r#"[{"Plan": {"Node Type": "Hash Join", "Total Cost": 11.32, "Plan Rows": 5462,
+            "Plans": [
+                {"Node Type": "Seq Scan", "Relation Name": "film_actor", "Plan Rows": 5462,
+                 "Actual Rows": 5462, "Total Cost": 84.62},
+                {"Node Type": "Index Scan", "Relation Name": "actor", "Index Name": "actor_pkey",
+                 "Plan Rows": 1, "Total Cost": 0.15},
+                {"Node Type": "Parallel Seq Scan", "Relation Name": "rental", "Plan Rows": 6681,
+                 "Total Cost": 150.0}
+            ]}}]"#;
+        let plan = parse_postgres(raw).unwrap();
+        assert_eq!(plan.root.node_type, "Hash Join");
+        assert_eq!(plan.root.nodes().len(), 4);
+        let index_scan = &plan.root.children[1];
+        assert_eq!(index_scan.index.as_deref(), Some("actor_pkey"));
+        assert!(!index_scan.sequential_scan);
+        let scans = plan.sequential_scans(1000.0);
+        assert_eq!(scans.len(), 2);
+        assert_eq!(scans[0].relation.as_deref(), Some("film_actor"));
+        assert_eq!(scans[0].actual_rows, Some(5462.0));
+        assert_eq!(scans[1].relation.as_deref(), Some("rental"));
+        assert!(!plan.has_sequential_scan(10_000.0));
+    }
+
+    #[test]
+    fn mysql_plan() {
+        let raw = r#"{"query_block": {"select_id": 1, "cost_info": {"query_cost": "1165.10"},
+            "nested_loop": [
+                {"table": {"table_name": "a", "access_type": "ALL",
+                    "rows_examined_per_scan": 200, "cost_info": {"read_cost": "1.25"}}},
+                {"table": {"table_name": "fa", "access_type": "ref", "key": "PRIMARY",
+                    "rows_examined_per_scan": 27, "cost_info": {"read_cost": "1.25"}}}
+            ]}}"#;
+        let plan = parse_mysql(raw).unwrap();
+        assert_eq!(plan.root.cost, Some(1165.10));
+        assert_eq!(plan.root.children.len(), 2);
+        assert_eq!(plan.root.children[1].index.as_deref(), Some("PRIMARY"));
+        let scans = plan.sequential_scans(100.0);
+        assert_eq!(scans.len(), 1);
+        assert_eq!(scans[0].relation.as_deref(), Some("a"));
+        assert_eq!(scans[0].estimated_rows, Some(200.0));
+    }
+
+    #[test]
+    fn sqlite_plan() {
+        let mut rows = Rows::new(vec![
+            "id".into(),
+            "parent".into(),
+            "notused".into(),
+            "detail".into(),
+        ]);
+        for (id, parent, detail) in &[
+            (2, 0, "SCAN a"),
+            (4, 0, "SEARCH fa USING COVERING INDEX idx_fa (actor_id=?)"),
+            (7, 0, "USE TEMP B-TREE FOR ORDER BY"),
+            (9, 0, "SCAN CONSTANT ROW"),
+            (11, 0, "SCAN (subquery-1)"),
+            (13, 0, "SCAN 3 CONSTANT ROWS"),
+        ] {
+            rows.push(vec![
+                Value::Bigint(*id),
+                Value::Bigint(*parent),
+                Value::Bigint(0),
+                Value::Text(detail.to_string()),
+            ]);
+        }
+        let plan = parse_sqlite(&rows);
+        assert_eq!(plan.root.children.len(), 6);
+        let search = &plan.root.children[1];
+        assert_eq!(search.node_type, "SEARCH");
+        assert_eq!(search.relation.as_deref(), Some("fa"));
+        assert_eq!(search.index.as_deref(), Some("idx_fa"));
+        let scans = plan.sequential_scans(1_000_000.0);
+        assert_eq!(scans.len(), 1);
+        assert_eq!(scans[0].relation.as_deref(), Some("a"));
+    }
+}
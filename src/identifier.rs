@@ -0,0 +1,242 @@
+//! Quoting of the table and column names in the generated sql statements.
+//!
+//! An identifier is quoted when it is a reserved word of the dialect or when it has other
+//! characters than letters, digits and underscores, the quote character inside the identifier
+//! is escaped by doubling it. The plain identifiers are left as is so that the case folding of
+//! the unquoted names keeps working as before.
+use crate::{
+    ColumnName,
+    DbError,
+    TableName,
+};
+
+/// The sql dialect of a database platform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dialect {
+    /// double quotes
+    Postgres,
+    /// double quotes
+    Sqlite,
+    /// backticks
+    Mysql,
+}
+
+impl Dialect {
+    fn quote_char(&self) -> char {
+        match self {
+            Dialect::Postgres | Dialect::Sqlite => '"',
+            Dialect::Mysql => '`',
+        }
+    }
+
+    fn reserved_words(&self) -> &'static [&'static str] {
+        match self {
+            Dialect::Postgres => POSTGRES_RESERVED,
+            Dialect::Sqlite => SQLITE_RESERVED,
+            Dialect::Mysql => MYSQL_RESERVED,
+        }
+    }
+
+    /// whether this word can not be used as an identifier without quoting it
+    pub fn is_reserved(&self, word: &str) -> bool {
+        self.reserved_words()
+            .binary_search(&word.to_uppercase().as_str())
+            .is_ok()
+    }
+
+    /// the identifier, quoted if needed.
+    /// Returns `DbError::SqlInjectionAttempt` if the identifier is empty or has a control
+    /// character such as a nul byte.
+    pub fn quote(&self, ident: &str) -> Result<String, DbError> {
+        validate(ident)?;
+        let is_plain = ident.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && ident.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if is_plain && !self.is_reserved(ident) {
+            Ok(ident.to_string())
+        } else {
+            self.quote_exact(ident)
+        }
+    }
+
+    /// the identifier always quoted, which keeps its case as is
+    pub fn quote_exact(&self, ident: &str) -> Result<String, DbError> {
+        validate(ident)?;
+        let quote = self.quote_char();
+        let escaped = ident.replace(quote, &format!("{}{}", quote, quote));
+        Ok(format!("{}{}{}", quote, escaped, quote))
+    }
+
+    /// the table name qualified with its schema, ie: `public.actor`
+    pub fn table_name(&self, table_name: &TableName) -> Result<String, DbError> {
+        match table_name.schema {
+            Some(ref schema) => {
+                Ok(format!(
+                    "{}.{}",
+                    self.quote(schema)?,
+                    self.quote(&table_name.name)?
+                ))
+            }
+            None => self.quote(&table_name.name),
+        }
+    }
+
+    /// the column name qualified with its table, ie: `actor.actor_id`
+    pub fn column_name(&self, column_name: &ColumnName) -> Result<String, DbError> {
+        match column_name.table {
+            Some(ref table) => {
+                Ok(format!(
+                    "{}.{}",
+                    self.quote(table)?,
+                    self.quote(&column_name.name)?
+                ))
+            }
+            None => self.quote(&column_name.name),
+        }
+    }
+
+    /// the comma separated names of these columns, without their table
+    pub fn column_list(&self, columns: &[ColumnName]) -> Result<String, DbError> {
+        Ok(columns
+            .iter()
+            .map(|column| self.quote(&column.name))
+            .collect::<Result<Vec<_>, _>>()?
+            .join(", "))
+    }
+}
+
+/// an identifier can not be empty nor have control characters
+fn validate(ident: &str) -> Result<(), DbError> {
+    if ident.is_empty() || ident.chars().any(char::is_control) {
+        Err(DbError::SqlInjectionAttempt(format!(
+            "invalid identifier: {:?}",
+            ident
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// the reserved key words of postgresql 16
+const POSTGRES_RESERVED: &[&str] = &[
+    "ALL", "ANALYSE", "ANALYZE", "AND", "ANY", "ARRAY", "AS", "ASC", "ASYMMETRIC", "AUTHORIZATION",
+    "BINARY", "BOTH", "CASE", "CAST", "CHECK", "COLLATE", "COLLATION", "COLUMN", "CONCURRENTLY",
+    "CONSTRAINT", "CREATE", "CROSS", "CURRENT_CATALOG", "CURRENT_DATE", "CURRENT_ROLE",
+    "CURRENT_SCHEMA", "CURRENT_TIME", "CURRENT_TIMESTAMP", "CURRENT_USER", "DEFAULT", "DEFERRABLE",
+    "DESC", "DISTINCT", "DO", "ELSE", "END", "EXCEPT", "FALSE", "FETCH", "FOR", "FOREIGN",
+    "FREEZE", "FROM", "FULL", "GRANT", "GROUP", "HAVING", "ILIKE", "IN", "INITIALLY", "INNER",
+    "INTERSECT", "INTO", "IS", "ISNULL", "JOIN", "LATERAL", "LEADING", "LEFT", "LIKE", "LIMIT",
+    "LOCALTIME", "LOCALTIMESTAMP", "NATURAL", "NOT", "NOTNULL", "NULL", "OFFSET", "ON", "ONLY",
+    "OR", "ORDER", "OUTER", "OVERLAPS", "PLACING", "PRIMARY", "REFERENCES", "RETURNING", "RIGHT",
+    "SELECT", "SESSION_USER", "SIMILAR", "SOME", "SYMMETRIC", "SYSTEM_USER", "TABLE",
+    "TABLESAMPLE", "THEN", "TO", "TRAILING", "TRUE", "UNION", "UNIQUE", "USER", "USING",
+    "VARIADIC", "VERBOSE", "WHEN", "WHERE", "WINDOW", "WITH"
+];
+
+/// the key words of sqlite
+const SQLITE_RESERVED: &[&str] = &[
+    "ABORT", "ACTION", "ADD", "AFTER", "ALL", "ALTER", "ALWAYS", "ANALYZE", "AND", "AS", "ASC",
+    "ATTACH", "AUTOINCREMENT", "BEFORE", "BEGIN", "BETWEEN", "BY", "CASCADE", "CASE", "CAST",
+    "CHECK", "COLLATE", "COLUMN", "COMMIT", "CONFLICT", "CONSTRAINT", "CREATE", "CROSS", "CURRENT",
+    "CURRENT_DATE", "CURRENT_TIME", "CURRENT_TIMESTAMP", "DATABASE", "DEFAULT", "DEFERRABLE",
+    "DEFERRED", "DELETE", "DESC", "DETACH", "DISTINCT", "DO", "DROP", "EACH", "ELSE", "END",
+    "ESCAPE", "EXCEPT", "EXCLUDE", "EXCLUSIVE", "EXISTS", "EXPLAIN", "FAIL", "FILTER", "FIRST",
+    "FOLLOWING", "FOR", "FOREIGN", "FROM", "FULL", "GENERATED", "GLOB", "GROUP", "GROUPS",
+    "HAVING", "IF", "IGNORE", "IMMEDIATE", "IN", "INDEX", "INDEXED", "INITIALLY", "INNER",
+    "INSERT", "INSTEAD", "INTERSECT", "INTO", "IS", "ISNULL", "JOIN", "KEY", "LAST", "LEFT",
+    "LIKE", "LIMIT", "MATCH", "MATERIALIZED", "NATURAL", "NO", "NOT", "NOTHING", "NOTNULL", "NULL",
+    "NULLS", "OF", "OFFSET", "ON", "OR", "ORDER", "OTHERS", "OUTER", "OVER", "PARTITION", "PLAN",
+    "PRAGMA", "PRECEDING", "PRIMARY", "QUERY", "RAISE", "RANGE", "RECURSIVE", "REFERENCES",
+    "REGEXP", "REINDEX", "RELEASE", "RENAME", "REPLACE", "RESTRICT", "RETURNING", "RIGHT",
+    "ROLLBACK", "ROW", "ROWS", "SAVEPOINT", "SELECT", "SET", "TABLE", "TEMP", "TEMPORARY", "THEN",
+    "TIES", "TO", "TRANSACTION", "TRIGGER", "UNBOUNDED", "UNION", "UNIQUE", "UPDATE", "USING",
+    "VACUUM", "VALUES", "VIEW", "VIRTUAL", "WHEN", "WHERE", "WINDOW", "WITH", "WITHOUT"
+];
+
+/// the reserved words of mysql 8.0
+const MYSQL_RESERVED: &[&str] = &[
+    "ACCESSIBLE", "ADD", "ALL", "ALTER", "ANALYZE", "AND", "AS", "ASC", "ASENSITIVE", "BEFORE",
+    "BETWEEN", "BIGINT", "BINARY", "BLOB", "BOTH", "BY", "CALL", "CASCADE", "CASE", "CHANGE",
+    "CHAR", "CHARACTER", "CHECK", "COLLATE", "COLUMN", "CONDITION", "CONSTRAINT", "CONTINUE",
+    "CONVERT", "CREATE", "CROSS", "CUBE", "CUME_DIST", "CURRENT_DATE", "CURRENT_TIME",
+    "CURRENT_TIMESTAMP", "CURRENT_USER", "CURSOR", "DATABASE", "DATABASES", "DAY_HOUR",
+    "DAY_MICROSECOND", "DAY_MINUTE", "DAY_SECOND", "DEC", "DECIMAL", "DECLARE", "DEFAULT",
+    "DELAYED", "DELETE", "DENSE_RANK", "DESC", "DESCRIBE", "DETERMINISTIC", "DISTINCT",
+    "DISTINCTROW", "DIV", "DOUBLE", "DROP", "DUAL", "EACH", "ELSE", "ELSEIF", "EMPTY", "ENCLOSED",
+    "ESCAPED", "EXCEPT", "EXISTS", "EXIT", "EXPLAIN", "FALSE", "FETCH", "FIRST_VALUE", "FLOAT",
+    "FLOAT4", "FLOAT8", "FOR", "FORCE", "FOREIGN", "FROM", "FULLTEXT", "FUNCTION", "GENERATED",
+    "GET", "GRANT", "GROUP", "GROUPING", "GROUPS", "HAVING", "HIGH_PRIORITY", "HOUR_MICROSECOND",
+    "HOUR_MINUTE", "HOUR_SECOND", "IF", "IGNORE", "IN", "INDEX", "INFILE", "INNER", "INOUT",
+    "INSENSITIVE", "INSERT", "INT", "INT1", "INT2", "INT3", "INT4", "INT8", "INTEGER", "INTERSECT",
+    "INTERVAL", "INTO", "IO_AFTER_GTIDS", "IO_BEFORE_GTIDS", "IS", "ITERATE", "JOIN", "JSON_TABLE",
+    "KEY", "KEYS", "KILL", "LAG", "LAST_VALUE", "LATERAL", "LEAD", "LEADING", "LEAVE", "LEFT",
+    "LIKE", "LIMIT", "LINEAR", "LINES", "LOAD", "LOCALTIME", "LOCALTIMESTAMP", "LOCK", "LONG",
+    "LONGBLOB", "LONGTEXT", "LOOP", "LOW_PRIORITY", "MASTER_BIND", "MASTER_SSL_VERIFY_SERVER_CERT",
+    "MATCH", "MAXVALUE", "MEDIUMBLOB", "MEDIUMINT", "MEDIUMTEXT", "MIDDLEINT",
+    "MINUTE_MICROSECOND", "MINUTE_SECOND", "MOD", "MODIFIES", "NATURAL", "NOT",
+    "NO_WRITE_TO_BINLOG", "NTH_VALUE", "NTILE", "NULL", "NUMERIC", "OF", "ON", "OPTIMIZE",
+    "OPTIMIZER_COSTS", "OPTION", "OPTIONALLY", "OR", "ORDER", "OUT", "OUTER", "OUTFILE", "OVER",
+    "PARTITION", "PERCENT_RANK", "PRECISION", "PRIMARY", "PROCEDURE", "PURGE", "RANGE", "RANK",
+    "READ", "READS", "READ_WRITE", "REAL", "RECURSIVE", "REFERENCES", "REGEXP", "RELEASE",
+    "RENAME", "REPEAT", "REPLACE", "REQUIRE", "RESIGNAL", "RESTRICT", "RETURN", "REVOKE", "RIGHT",
+    "RLIKE", "ROW", "ROWS", "ROW_NUMBER", "SCHEMA", "SCHEMAS", "SECOND_MICROSECOND", "SELECT",
+    "SENSITIVE", "SEPARATOR", "SET", "SHOW", "SIGNAL", "SMALLINT", "SPATIAL", "SPECIFIC", "SQL",
+    "SQLEXCEPTION", "SQLSTATE", "SQLWARNING", "SQL_BIG_RESULT", "SQL_CALC_FOUND_ROWS",
+    "SQL_SMALL_RESULT", "SSL", "STARTING", "STORED", "STRAIGHT_JOIN", "SYSTEM", "TABLE",
+    "TERMINATED", "THEN", "TINYBLOB", "TINYINT", "TINYTEXT", "TO", "TRAILING", "TRIGGER", "TRUE",
+    "UNDO", "UNION", "UNIQUE", "UNLOCK", "UNSIGNED", "UPDATE", "USAGE", "USE", "USING", "UTC_DATE",
+    "UTC_TIME", "UTC_TIMESTAMP", "VALUES", "VARBINARY", "VARCHAR", "VARCHARACTER", "VARYING",
+    "VIRTUAL", "WHEN", "WHERE", "WHILE", "WINDOW", "WITH", "WRITE", "XOR", "YEAR_MONTH", "ZEROFILL"
+];
+
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reserved_words_are_sorted() {
+        for dialect in &[Dialect::Postgres, Dialect::Sqlite, Dialect::Mysql] {
+            let words = dialect.reserved_words();
+            assert!(words.windows(2).all(|w| w[0] < w[1]), "{:?}", dialect);
+        }
+    }
+
+    #[test]
+    fn quote_when_needed() {
+        let pg = Dialect::Postgres;
+        assert_eq!(pg.quote("actor_id").unwrap(), "actor_id");
+        assert_eq!(pg.quote("user").unwrap(), r#""user""#);
+        assert_eq!(pg.quote("Order").unwrap(), r#""Order""#);
+        assert_eq!(pg.quote("first name").unwrap(), r#""first name""#);
+        assert_eq!(pg.quote("1st").unwrap(), r#""1st""#);
+        assert_eq!(
+            pg.quote(r#"x"; DROP TABLE actor; --"#).unwrap(),
+            r#""x""; DROP TABLE actor; --""#
+        );
+        let my = Dialect::Mysql;
+        assert_eq!(my.quote("key").unwrap(), "`key`");
+        assert_eq!(my.quote("user").unwrap(), "user");
+        assert_eq!(my.quote("a`b").unwrap(), "`a``b`");
+        assert_eq!(Dialect::Sqlite.quote("pragma").unwrap(), r#""pragma""#);
+        assert_eq!(pg.quote_exact("Admin's").unwrap(), r#""Admin's""#);
+    }
+
+    #[test]
+    fn invalid_identifiers() {
+        for ident in &["", "actor\0", "actor\n"] {
+            assert!(matches!(
+                Dialect::Postgres.quote(ident),
+                Err(DbError::SqlInjectionAttempt(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn qualified_names() {
+        let pg = Dialect::Postgres;
+        assert_eq!(pg.table_name(&TableName::from("public.user")).unwrap(), r#"public."user""#);
+        assert_eq!(pg.column_name(&ColumnName::from("order.id")).unwrap(), r#""order".id"#);
+        let columns = vec![ColumnName::from("id"), ColumnName::from("group")];
+        assert_eq!(Dialect::Mysql.column_list(&columns).unwrap(), "id, `group`");
+    }
+}
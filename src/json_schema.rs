@@ -0,0 +1,309 @@
+//! Generate JSON Schema documents and OpenAPI component schemas from the table definitions.
+//!
+//! The generated schemas follows JSON Schema draft 2020-12, which is also the schema
+//! dialect of OpenAPI 3.1. A nullable column is described as `"type": [<type>, "null"]`.
+use crate::{
+    column::Capacity,
+    types::SqlType,
+    ColumnDef,
+    TableDef,
+};
+use serde_json::{
+    json,
+    Map,
+    Value,
+};
+
+const JSON_SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+impl ColumnDef {
+    /// the JSON schema of the values of this column
+    pub fn to_json_schema(&self) -> Value {
+        let mut schema = sql_type_schema(&self.specification.sql_type);
+        match self.specification.capacity {
+            Some(Capacity::Limit(limit)) if is_string_type(&self.specification.sql_type) => {
+                schema.insert("maxLength".into(), json!(limit));
+            }
+            Some(Capacity::Range(whole, decimal)) => {
+                // numeric(4,2) holds values from -99.99 up to 99.99
+                let bound = 10f64.powi(whole - decimal);
+                schema.insert("multipleOf".into(), json!(10f64.powi(-decimal)));
+                schema.insert("exclusiveMinimum".into(), json!(-bound));
+                schema.insert("exclusiveMaximum".into(), json!(bound));
+            }
+            _ => (),
+        }
+        if !self.is_not_null() {
+            make_nullable(&mut schema);
+        }
+        if let Some(ref comment) = self.comment {
+            schema.insert("description".into(), json!(comment));
+        }
+        Value::Object(schema)
+    }
+}
+
+impl TableDef {
+    /// a JSON schema document describing a record of this table
+    pub fn to_json_schema(&self) -> Value {
+        let mut schema = self.object_schema();
+        schema.insert("$schema".into(), json!(JSON_SCHEMA_DIALECT));
+        Value::Object(schema)
+    }
+
+    /// the schema of a record of this table as used in the `components/schemas` of an
+    /// OpenAPI 3.1 document
+    pub fn to_openapi_schema(&self) -> Value { Value::Object(self.object_schema()) }
+
+    fn object_schema(&self) -> Map<String, Value> {
+        let mut schema = Map::new();
+        schema.insert("title".into(), json!(self.name.name));
+        if let Some(ref comment) = self.comment {
+            schema.insert("description".into(), json!(comment));
+        }
+        schema.insert("type".into(), json!("object"));
+        let properties: Map<String, Value> = self
+            .columns
+            .iter()
+            .map(|column| (column.name.name.to_string(), column.to_json_schema()))
+            .collect();
+        schema.insert("properties".into(), Value::Object(properties));
+        let required: Vec<&str> = self
+            .columns
+            .iter()
+            .filter(|column| column.is_not_null())
+            .map(|column| column.name.name.as_str())
+            .collect();
+        if !required.is_empty() {
+            schema.insert("required".into(), json!(required));
+        }
+        schema
+    }
+}
+
+/// the `components` object of an OpenAPI 3.1 document containing the schema of these tables,
+/// keyed by the complete name of the table
+pub fn to_openapi_components(tables: &[TableDef]) -> Value {
+    let schemas: Map<String, Value> = tables
+        .iter()
+        .map(|table| (table.complete_name(), table.to_openapi_schema()))
+        .collect();
+    json!({ "schemas": schemas })
+}
+
+fn is_string_type(sql_type: &SqlType) -> bool {
+    matches!(
+        *sql_type,
+        SqlType::Char
+            | SqlType::Varchar
+            | SqlType::Tinytext
+            | SqlType::Mediumtext
+            | SqlType::Text
+    )
+}
+
+fn sql_type_schema(sql_type: &SqlType) -> Map<String, Value> {
+    let schema = match *sql_type {
+        SqlType::Bool => json!({"type": "boolean"}),
+        SqlType::Tinyint | SqlType::Smallint | SqlType::Int => {
+            json!({"type": "integer", "format": "int32"})
+        }
+        SqlType::Bigint => json!({"type": "integer", "format": "int64"}),
+        SqlType::Real | SqlType::Float => json!({"type": "number", "format": "float"}),
+        SqlType::Double => json!({"type": "number", "format": "double"}),
+        SqlType::Numeric => json!({"type": "number"}),
+        SqlType::Tinyblob
+        | SqlType::Mediumblob
+        | SqlType::Blob
+        | SqlType::Longblob
+        | SqlType::Varbinary => json!({"type": "string", "contentEncoding": "base64"}),
+        SqlType::Char
+        | SqlType::Varchar
+        | SqlType::Tinytext
+        | SqlType::Mediumtext
+        | SqlType::Text
+        | SqlType::TsVector => json!({"type": "string"}),
+        // any json value
+        SqlType::Json => json!({}),
+        SqlType::Uuid => json!({"type": "string", "format": "uuid"}),
+        SqlType::Date => json!({"type": "string", "format": "date"}),
+        SqlType::Timestamp | SqlType::TimestampTz => {
+            json!({"type": "string", "format": "date-time"})
+        }
+        SqlType::Time | SqlType::TimeTz => json!({"type": "string", "format": "time"}),
+        SqlType::Interval => json!({"type": "string", "format": "duration"}),
+        SqlType::IpAddress | SqlType::Cidr | SqlType::MacAddr => json!({"type": "string"}),
+        // the amount in the smallest unit of the currency
+        SqlType::Money => json!({"type": "integer", "format": "int64"}),
+        SqlType::Bit | SqlType::VarBit => json!({"type": "string", "pattern": "^[01]*$"}),
+        // the text format of the range, ie: [1,10)
+        SqlType::Range(_) => json!({"type": "string"}),
+        // the geometries as serialized by geo_types
+        SqlType::Lseg
+        | SqlType::Box
+        | SqlType::Path
+        | SqlType::Polygon
+        | SqlType::Geometry(..)
+        | SqlType::Geography(..) => json!({"type": "object"}),
+        SqlType::Hstore => {
+            json!({"type": "object", "additionalProperties": {"type": ["string", "null"]}})
+        }
+        SqlType::Point => {
+            json!({
+                "type": "object",
+                "properties": {
+                    "x": {"type": "number", "format": "double"},
+                    "y": {"type": "number", "format": "double"},
+                },
+                "required": ["x", "y"],
+            })
+        }
+        SqlType::Enum(ref name, ref choices) => {
+            json!({"title": name, "type": "string", "enum": choices})
+        }
+        SqlType::Array(ref item) => {
+            json!({"type": "array", "items": Value::Object(sql_type_schema(item))})
+        }
+        // any value, the representation depends on the registered conversions
+        SqlType::Custom(ref name) => json!({"title": name}),
+        // the fields of the composite type
+        SqlType::Composite(ref name) => json!({"type": "object", "title": name}),
+    };
+    match schema {
+        Value::Object(schema) => schema,
+        _ => Map::new(),
+    }
+}
+
+/// allow null in addition to the type of the schema
+fn make_nullable(schema: &mut Map<String, Value>) {
+    if let Some(Value::String(data_type)) = schema.get("type").cloned() {
+        schema.insert("type".into(), json!([data_type, "null"]));
+    }
+    if let Some(Value::Array(choices)) = schema.get_mut("enum") {
+        choices.push(Value::Null);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        column::{
+            ColumnConstraint,
+            ColumnSpecification,
+        },
+        table::{
+            Key,
+            TableKey,
+        },
+        ColumnName,
+        TableName,
+    };
+
+    fn column(
+        name: &str,
+        sql_type: SqlType,
+        capacity: Option<Capacity>,
+        not_null: bool,
+    ) -> ColumnDef {
+        ColumnDef {
+            table: TableName::from("film"),
+            name: ColumnName::from(name),
+            comment: None,
+            specification: ColumnSpecification {
+                sql_type,
+                capacity,
+                constraints: if not_null {
+                    vec![ColumnConstraint::NotNull]
+                } else {
+                    vec![]
+                },
+            },
+            stat: None,
+        }
+    }
+
+    fn film() -> TableDef {
+        let mut title = column("title", SqlType::Varchar, Some(Capacity::Limit(255)), true);
+        title.comment = Some("the title of the film".into());
+        TableDef {
+            name: TableName::from("public.film"),
+            comment: Some("films for rent".into()),
+            columns: vec![
+                column("film_id", SqlType::Int, None, true),
+                title,
+                column("rental_rate", SqlType::Numeric, Some(Capacity::Range(4, 2)), true),
+                column(
+                    "rating",
+                    SqlType::Enum("mpaa_rating".into(), vec!["G".into(), "PG".into()]),
+                    None,
+                    false,
+                ),
+                column("last_update", SqlType::Timestamp, None, true),
+                column(
+                    "special_features",
+                    SqlType::Array(Box::new(SqlType::Text)),
+                    None,
+                    false,
+                ),
+            ],
+            is_view: false,
+            table_key: vec![TableKey::PrimaryKey(Key {
+                name: Some("film_pkey".into()),
+                columns: vec![ColumnName::from("film_id")],
+            })],
+        }
+    }
+
+    #[test]
+    fn table_json_schema() {
+        let schema = film().to_json_schema();
+        assert_eq!(
+            schema,
+            json!({
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "title": "film",
+                "description": "films for rent",
+                "type": "object",
+                "properties": {
+                    "film_id": {"type": "integer", "format": "int32"},
+                    "title": {
+                        "type": "string",
+                        "maxLength": 255,
+                        "description": "the title of the film",
+                    },
+                    "rental_rate": {
+                        "type": "number",
+                        "multipleOf": 0.01,
+                        "exclusiveMinimum": -100.0,
+                        "exclusiveMaximum": 100.0,
+                    },
+                    "rating": {
+                        "title": "mpaa_rating",
+                        "type": ["string", "null"],
+                        "enum": ["G", "PG", null],
+                    },
+                    "last_update": {"type": "string", "format": "date-time"},
+                    "special_features": {
+                        "type": ["array", "null"],
+                        "items": {"type": "string"},
+                    },
+                },
+                "required": ["film_id", "title", "rental_rate", "last_update"],
+            })
+        );
+    }
+
+    #[test]
+    fn openapi_components() {
+        let components = to_openapi_components(&[film()]);
+        let film_schema = &components["schemas"]["public.film"];
+        assert_eq!(film_schema["title"], json!("film"));
+        assert!(film_schema.get("$schema").is_none());
+        assert_eq!(
+            film_schema["properties"]["film_id"],
+            json!({"type": "integer", "format": "int32"})
+        );
+    }
+}
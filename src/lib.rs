@@ -24,26 +24,42 @@ cfg_if! {if #[cfg(feature = "with-sqlite")]{
     extern crate r2d2_sqlite;
     extern crate rusqlite;
     mod sqlite;
+    pub use sqlite::{
+        Decoding,
+        DecimalMode,
+    };
 }}
 cfg_if! {if #[cfg(feature = "with-mysql")]{
     mod my;
 }}
 
+pub mod cache;
 pub mod column;
 pub mod common;
 mod dao_manager;
 mod database;
 #[cfg(feature = "db-auth")]
 mod db_auth;
+pub mod diagram;
 mod entity;
 pub mod error;
+pub mod explain;
+pub mod identifier;
+pub mod json_schema;
+pub mod metrics;
+pub mod observer;
 mod platform;
 pub mod pool;
+pub mod schema_graph;
+pub mod statement_cache;
 pub mod table;
+mod timeout;
+pub mod type_registry;
 pub mod types;
 
 pub mod util;
 
+pub use cache::MetadataCache;
 pub use chrono;
 pub use column::ColumnDef;
 pub use dao_manager::DaoManager;
@@ -56,9 +72,22 @@ pub use error::{
     DataError,
     DbError,
 };
+pub use explain::{
+    ExplainOptions,
+    QueryPlan,
+};
 pub use platform::DBPlatform;
+pub use metrics::QueryStats;
+pub use observer::{
+    LogObserver,
+    QueryObserver,
+    SlowQueryLog,
+};
 pub use pool::Pool;
+pub use schema_graph::SchemaGraph;
+pub use statement_cache::StatementCacheStats;
 pub use table::TableDef;
+pub use type_registry::TypeRegistry;
 pub use uuid::{
     self,
     Uuid,
@@ -76,10 +105,19 @@ pub use codegen::{
 pub use clia_rustorm_dao::{
     self,
     Array,
+    BitString,
     ColumnName,
+    Composite,
     ConvertError,
+    Custom,
     Dao,
     FromValue,
+    Geometry,
+    IpNetwork,
+    Json,
+    MacAddress,
+    Range,
+    RangeBound,
     Rows,
     TableName,
     ToValue,
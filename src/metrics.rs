@@ -0,0 +1,293 @@
+//! Execution counters of the sql statements, grouped by normalized statement.
+//!
+//! The literal values in a statement are replaced with `?` and the whitespaces are collapsed,
+//! so `SELECT * FROM actor WHERE actor_id = 1` and `SELECT * FROM actor WHERE actor_id = 2`
+//! are counted as the same statement.
+use crate::observer::{
+    QueryEvent,
+    QueryObserver,
+};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    sync::{
+        Arc,
+        Mutex,
+        MutexGuard,
+    },
+    time::Duration,
+};
+
+/// the statements beyond this number are counted together as `<other>`
+const DEFAULT_MAX_STATEMENTS: usize = 1000;
+const OTHER_STATEMENTS: &str = "<other>";
+
+/// the name, type, help and value of a prometheus metric
+type Metric = (&'static str, &'static str, &'static str, fn(&StatementStats) -> String);
+
+/// A query observer counting the executions of each normalized statement.
+///
+/// The clones share the same counters, so a clone can be added to the pool while the
+/// original is kept to take snapshots.
+#[derive(Clone)]
+pub struct QueryStats {
+    stats: Arc<Mutex<HashMap<String, StatementStats>>>,
+    max_statements: usize,
+}
+
+/// The counters of a normalized statement
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatementStats {
+    pub statement: String,
+    pub calls: u64,
+    pub errors: u64,
+    pub rows: u64,
+    pub total_time: Duration,
+    pub max_time: Duration,
+}
+
+impl StatementStats {
+    fn new(statement: String) -> Self {
+        StatementStats {
+            statement,
+            calls: 0,
+            errors: 0,
+            rows: 0,
+            total_time: Duration::default(),
+            max_time: Duration::default(),
+        }
+    }
+
+    pub fn mean_time(&self) -> Duration {
+        if self.calls == 0 {
+            Duration::default()
+        } else {
+            self.total_time.div_f64(self.calls as f64)
+        }
+    }
+}
+
+impl QueryStats {
+    pub fn new() -> Self { Self::with_max_statements(DEFAULT_MAX_STATEMENTS) }
+
+    /// keep separate counters for at most this number of distinct statements,
+    /// the other statements are counted together as `<other>`
+    pub fn with_max_statements(max_statements: usize) -> Self {
+        QueryStats {
+            stats: Default::default(),
+            max_statements,
+        }
+    }
+
+    /// the counters of every statement, the statements taking the most time first
+    pub fn snapshot(&self) -> Vec<StatementStats> {
+        let mut snapshot: Vec<StatementStats> = self.lock().values().cloned().collect();
+        snapshot.sort_by(|a, b| {
+            b.total_time
+                .cmp(&a.total_time)
+                .then_with(|| a.statement.cmp(&b.statement))
+        });
+        snapshot
+    }
+
+    /// reset all the counters
+    pub fn reset(&self) { self.lock().clear() }
+
+    /// the counters in the prometheus text exposition format
+    pub fn to_prometheus(&self) -> String {
+        let snapshot = self.snapshot();
+        let mut out = String::new();
+        let metrics: [Metric; 5] = [
+            (
+                "rustorm_query_calls_total",
+                "counter",
+                "Number of executions of the statement",
+                |s| s.calls.to_string(),
+            ),
+            (
+                "rustorm_query_errors_total",
+                "counter",
+                "Number of failed executions of the statement",
+                |s| s.errors.to_string(),
+            ),
+            (
+                "rustorm_query_rows_total",
+                "counter",
+                "Number of rows returned by the statement",
+                |s| s.rows.to_string(),
+            ),
+            (
+                "rustorm_query_duration_seconds_total",
+                "counter",
+                "Total execution time of the statement",
+                |s| s.total_time.as_secs_f64().to_string(),
+            ),
+            (
+                "rustorm_query_duration_seconds_max",
+                "gauge",
+                "Longest execution time of the statement",
+                |s| s.max_time.as_secs_f64().to_string(),
+            ),
+        ];
+        for (name, kind, help, value) in metrics.iter() {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} {}", name, kind);
+            for stats in snapshot.iter() {
+                let _ = writeln!(
+                    out,
+                    "{}{{statement=\"{}\"}} {}",
+                    name,
+                    escape_label(&stats.statement),
+                    value(stats)
+                );
+            }
+        }
+        out
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, StatementStats>> {
+        self.stats.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for QueryStats {
+    fn default() -> Self { Self::new() }
+}
+
+impl QueryObserver for QueryStats {
+    fn on_query(&self, event: &QueryEvent<'_>) {
+        let mut statement = normalize(event.sql);
+        let mut stats = self.lock();
+        if !stats.contains_key(&statement) && stats.len() >= self.max_statements {
+            statement = OTHER_STATEMENTS.to_string();
+        }
+        let entry = stats
+            .entry(statement)
+            .or_insert_with_key(|statement| StatementStats::new(statement.clone()));
+        entry.calls += 1;
+        entry.total_time += event.duration;
+        entry.max_time = entry.max_time.max(event.duration);
+        match event.row_count {
+            Some(rows) => entry.rows += rows as u64,
+            None => entry.errors += 1,
+        }
+    }
+}
+
+/// replace the string and numeric literals with `?` and collapse the whitespaces
+pub fn normalize(sql: &str) -> String {
+    let mut normalized = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    // whether the previous char is part of an identifier, ie: the 1 in `film1`
+    let mut in_word = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                // a quote inside the literal is escaped by doubling it
+                while let Some(c) = chars.next() {
+                    if c == '\'' {
+                        if chars.peek() == Some(&'\'') {
+                            chars.next();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                normalized.push('?');
+                in_word = false;
+            }
+            c if c.is_ascii_digit() && !in_word => {
+                while let Some(c) = chars.peek() {
+                    if c.is_ascii_digit() || *c == '.' {
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                normalized.push('?');
+            }
+            c if c.is_whitespace() => {
+                while matches!(chars.peek(), Some(c) if c.is_whitespace()) {
+                    chars.next();
+                }
+                if !normalized.is_empty() {
+                    normalized.push(' ');
+                }
+                in_word = false;
+            }
+            c => {
+                normalized.push(c);
+                in_word = c.is_alphanumeric() || c == '_' || c == '$';
+            }
+        }
+    }
+    normalized.trim_end().to_string()
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        observer::QueryObservers,
+        Rows,
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn normalize_literals() {
+        assert_eq!(
+            normalize("SELECT *\n  FROM film1 WHERE title = 'it''s' AND rate > 4.99 AND id = $1 "),
+            "SELECT * FROM film1 WHERE title = ? AND rate > ? AND id = $1"
+        );
+    }
+
+    #[test]
+    fn count_per_statement() {
+        let stats = QueryStats::with_max_statements(1);
+        let mut observers = QueryObservers::default();
+        observers.add(Arc::new(stats.clone()));
+        for id in 1..=3 {
+            let sql = format!("SELECT * FROM actor WHERE actor_id = {}", id);
+            let _ = observers.observe(&sql, &[], "EXPLAIN", |_, _| {
+                let mut rows = Rows::new(vec!["actor_id".into()]);
+                rows.push(vec![id.into()]);
+                Ok(rows)
+            });
+        }
+        let _ = observers.observe("SELECT 1 FROM film", &[], "EXPLAIN", |_, _| {
+            Ok(Rows::empty())
+        });
+        let _ = observers.observe("DELETE FROM film", &[], "EXPLAIN", |_, _| {
+            Ok(Rows::empty())
+        });
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.len(), 2);
+        let actor = snapshot
+            .iter()
+            .find(|s| s.statement == "SELECT * FROM actor WHERE actor_id = ?")
+            .unwrap();
+        assert_eq!((actor.calls, actor.rows, actor.errors), (3, 3, 0));
+        assert!(actor.max_time <= actor.total_time);
+        let other = snapshot
+            .iter()
+            .find(|s| s.statement == OTHER_STATEMENTS)
+            .unwrap();
+        assert_eq!(other.calls, 2);
+
+        let text = stats.to_prometheus();
+        assert!(text.contains("# TYPE rustorm_query_calls_total counter\n"));
+        assert!(text.contains(
+            "rustorm_query_calls_total{statement=\"SELECT * FROM actor WHERE actor_id = ?\"} 3\n"
+        ));
+        stats.reset();
+        assert!(stats.snapshot().is_empty());
+    }
+}
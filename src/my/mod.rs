@@ -6,8 +6,21 @@ use crate::db_auth::{
 use crate::{
     column,
     common,
+    error::{
+        ConvertError,
+        DataOpError,
+    },
+    identifier::Dialect,
+    statement_cache::{
+        self,
+        StatementCacheStats,
+    },
     table::SchemaContent,
-    types::SqlType,
+    type_registry::TypeRegistry,
+    types::{
+        GeometryType,
+        SqlType,
+    },
     ColumnDef,
     ColumnName,
     DataError,
@@ -29,9 +42,13 @@ use r2d2_mysql::{
     },
 };
 use clia_rustorm_dao::{
+    DaoError,
     FromDao,
+    Geometry,
+    Interval,
     Rows,
 };
+use std::time::Duration;
 use thiserror::Error;
 
 pub fn init_pool(
@@ -39,7 +56,11 @@ pub fn init_pool(
 ) -> Result<r2d2::Pool<r2d2_mysql::MysqlConnectionManager>, MysqlError> {
     test_connection(db_url)?;
     let opts = mysql::Opts::from_url(db_url)?;
-    let builder = mysql::OptsBuilder::from_opts(opts);
+    // the statements are cached in the statement cache of the pooled connection instead
+    // the TIMESTAMP values are read and written in utc
+    let builder = mysql::OptsBuilder::from_opts(opts)
+        .stmt_cache_size(0)
+        .init(vec!["SET time_zone = '+00:00'"]);
     let manager = r2d2_mysql::MysqlConnectionManager::new(builder);
     let pool = r2d2::Pool::new(manager)?;
     Ok(pool)
@@ -54,7 +75,36 @@ pub fn test_connection(db_url: &str) -> Result<(), MysqlError> {
     Ok(())
 }
 
-pub struct MysqlDB(pub r2d2::PooledConnection<r2d2_mysql::MysqlConnectionManager>);
+pub struct MysqlDB(
+    pub r2d2::PooledConnection<r2d2_mysql::MysqlConnectionManager>,
+    pub(crate) TypeRegistry,
+);
+
+/// the error code of a prepared statement which has to be prepared again
+const ER_NEED_REPREPARE: u16 = 1615;
+
+impl MysqlDB {
+    /// prepare the statement and cache it, closing the statements evicted from the cache
+    fn prepare(&mut self, sql: &str) -> Result<mysql::Statement, MysqlError> {
+        let stmt = self
+            .0
+            .prep(sql)
+            .map_err(|e| MysqlError::Sql(e, sql.to_string()))?;
+        let evicted = statement_cache::of(&mut self.0).insert(sql, stmt.clone());
+        self.close(evicted);
+        Ok(stmt)
+    }
+
+    /// close the statements on the server, a failure only leaves them open until the
+    /// connection is closed
+    fn close(&mut self, statements: Vec<mysql::Statement>) {
+        for stmt in statements {
+            if let Err(e) = self.0.close(stmt) {
+                log::warn!("unable to close a statement: {}", e);
+            }
+        }
+    }
+}
 
 impl Database for MysqlDB {
     fn begin_transaction(&mut self) -> Result<(), DbError> {
@@ -97,27 +147,57 @@ impl Database for MysqlDB {
 
             collect(rows)
         } else {
-            let stmt = self
-                .0
-                .prep(&sql)
-                .map_err(|e| MysqlError::Sql(e, sql.to_string()))?;
-
-            let params: mysql::Params = param
-                .iter()
-                .map(|v| MyValue(v))
-                .map(|v| mysql::prelude::ToValue::to_value(&v))
-                .collect::<Vec<_>>()
-                .into();
-
-            let rows = self
-                .0
-                .exec(stmt, &params)
-                .map_err(|e| MysqlError::Sql(e, sql.to_string()))?;
+            let params: mysql::Params = to_my_values(param, &self.1)?.into();
+
+            let cached = statement_cache::of::<_, mysql::Statement>(&mut self.0).get(sql);
+            let stmt = match cached {
+                Some(ref stmt) => stmt.clone(),
+                None => self.prepare(sql)?,
+            };
+
+            let rows = match self.0.exec(stmt, &params) {
+                // the cached statement was invalidated by an altered table
+                Err(mysql::Error::MySqlError(ref e))
+                    if cached.is_some() && e.code == ER_NEED_REPREPARE =>
+                {
+                    statement_cache::of::<_, mysql::Statement>(&mut self.0).remove(sql);
+                    let stmt = self.prepare(sql)?;
+                    self.0.exec(stmt, &params)
+                }
+                result => result,
+            }
+            .map_err(|e| MysqlError::Sql(e, sql.to_string()))?;
 
             collect(rows)
         }
     }
 
+    /// only the `SELECT` statements can be cancelled, using `max_execution_time`
+    fn execute_sql_with_timeout(
+        &mut self,
+        sql: &str,
+        param: &[&Value],
+        timeout: Duration,
+    ) -> Result<Rows, DbError> {
+        let set_max_execution_time = format!(
+            "SET SESSION max_execution_time = {}",
+            timeout.as_millis().max(1)
+        );
+        self.execute_sql_with_return(&set_max_execution_time, &[])?;
+        let result = self.execute_sql_with_return(sql, param);
+        let reset = self.execute_sql_with_return("SET SESSION max_execution_time = DEFAULT", &[]);
+        match result {
+            Err(e) if e.is_timeout() => {
+                Err(DbError::Timeout {
+                    sql: sql.to_string(),
+                    timeout,
+                })
+            }
+            Err(e) => Err(e),
+            Ok(rows) => reset.map(|_| rows),
+        }
+    }
+
     fn get_table(&mut self, table_name: &TableName) -> Result<Option<TableDef>, DbError> {
         #[derive(Debug, FromDao)]
         struct TableSpec {
@@ -183,7 +263,6 @@ impl Database for MysqlDB {
                         let end = spec.type_.find(')');
                         if let (Some(start), Some(end)) = (start, end) {
                             let dtype = &spec.type_[0..start];
-                            println!("dtype: {:?}", dtype);
                             let range = &spec.type_[start + 1..end];
                             let choices = range
                                 .split(',')
@@ -222,7 +301,13 @@ impl Database for MysqlDB {
                             "date" => SqlType::Date,
                             "datetime" | "timestamp" => SqlType::Timestamp,
                             "time" => SqlType::Time,
-                            _ => panic!("not yet handled: {}", dtype),
+                            // the srid of the column is in SRS_ID since mysql 8
+                            _ => {
+                                match GeometryType::from_name(&dtype) {
+                                    Some(ty) => SqlType::Geometry(ty, None),
+                                    None => panic!("not yet handled: {}", dtype),
+                                }
+                            }
                         };
 
                         (sql_type, capacity)
@@ -326,6 +411,23 @@ impl Database for MysqlDB {
         }
     }
 
+    /// the information_schema of mysql doesn't reliably reflect schema changes
+    fn get_schema_version(&mut self) -> Result<Option<String>, DbError> { Ok(None) }
+
+    fn set_statement_cache_capacity(&mut self, capacity: usize) {
+        let evicted =
+            statement_cache::of::<_, mysql::Statement>(&mut self.0).set_capacity(capacity);
+        self.close(evicted);
+    }
+
+    fn statement_cache_stats(&self) -> StatementCacheStats {
+        statement_cache::stats_of::<_, mysql::Statement>(&self.0)
+    }
+
+    /// mysql has no user defined types, only the encoders of the custom parameters apply and
+    /// the decoders are ignored
+    fn set_type_registry(&mut self, registry: TypeRegistry) { self.1 = registry; }
+
     #[cfg(feature = "db-auth")]
     fn get_users(&mut self) -> Result<Vec<User>, DbError> {
         let sql = "SELECT USER as usernameFROM information_schema.user_attributes";
@@ -360,19 +462,97 @@ impl Database for MysqlDB {
     #[cfg(feature = "db-auth")]
     fn get_roles(&mut self, _username: &str) -> Result<Vec<Role>, DbError> { todo!() }
 
+    /// mysql stores the next value to be used, this is set to `sequence_value + 1`
+    /// to match the semantic of `setval` in postgresql. InnoDB raises a value lower than the
+    /// largest one used, the value applied is read back from information_schema.TABLES which
+    /// mysql 8 caches unless `information_schema_stats_expiry` is 0
     fn set_autoincrement_value(
         &mut self,
-        _table_name: &TableName,
-        _sequence_value: i64,
+        table_name: &TableName,
+        sequence_value: i64,
     ) -> Result<Option<i64>, DbError> {
-        todo!()
+        if self.get_autoincrement_column(table_name)?.is_none() {
+            return Ok(None);
+        }
+        let sql = format!(
+            "ALTER TABLE {} AUTO_INCREMENT = {}",
+            Dialect::Mysql.table_name(table_name)?,
+            sequence_value + 1
+        );
+        self.execute_sql_with_return(&sql, &[])?;
+        self.get_autoincrement_last_value(table_name)
     }
 
+    /// Note: information_schema.TABLES is cached in mysql 8 according to
+    /// `information_schema_stats_expiry`
     fn get_autoincrement_last_value(
         &mut self,
-        _table_name: &TableName,
+        table_name: &TableName,
     ) -> Result<Option<i64>, DbError> {
-        todo!()
+        let sql = r#"
+                SELECT AUTO_INCREMENT AS next_value
+                  FROM INFORMATION_SCHEMA.TABLES
+                 WHERE TABLE_SCHEMA = COALESCE(?, DATABASE()) AND TABLE_NAME = ?"#;
+        let rows = self.execute_sql_with_return(sql, &[
+            &table_name.schema.to_value(),
+            &table_name.name.to_value(),
+        ])?;
+        match rows.iter().next() {
+            Some(row) => {
+                let next_value: Option<i64> = row.get_opt("next_value")?;
+                Ok(next_value.map(|next_value| next_value - 1))
+            }
+            None => {
+                Err(DbError::DataError(DataError::TableNameNotFound(
+                    table_name.complete_name(),
+                )))
+            }
+        }
+    }
+
+    fn get_autoincrement_max_value(
+        &mut self,
+        table_name: &TableName,
+    ) -> Result<Option<i64>, DbError> {
+        if let Some(column) = self.get_autoincrement_column(table_name)? {
+            let sql = format!(
+                "SELECT MAX({}) AS value FROM {}",
+                Dialect::Mysql.quote(&column.name)?,
+                Dialect::Mysql.table_name(table_name)?
+            );
+            let rows = self.execute_sql_with_return(&sql, &[])?;
+            match rows.iter().next() {
+                Some(row) => Ok(row.get_opt("value")?),
+                None => Ok(None),
+            }
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl MysqlDB {
+    /// get the column of this table which has the `auto_increment` attribute
+    fn get_autoincrement_column(
+        &mut self,
+        table_name: &TableName,
+    ) -> Result<Option<ColumnName>, DbError> {
+        let sql = r#"
+                SELECT COLUMN_NAME AS name
+                  FROM INFORMATION_SCHEMA.COLUMNS
+                 WHERE TABLE_SCHEMA = COALESCE(?, DATABASE()) AND TABLE_NAME = ?
+                   AND EXTRA LIKE '%auto_increment%'"#;
+        let rows = self.execute_sql_with_return(sql, &[
+            &table_name.schema.to_value(),
+            &table_name.name.to_value(),
+        ])?;
+        match rows.iter().next() {
+            Some(row) => {
+                let name: String = row.get("name")?;
+                Ok(Some(ColumnName::from(&name)))
+            }
+            None => Ok(None),
+        }
     }
 }
 
@@ -399,12 +579,35 @@ fn get_table_names(db: &mut dyn Database, kind: &str) -> Result<Vec<TableName>,
     Ok(table_names)
 }
 
+/// the parameters, the custom values are encoded with the encoder registered for their type
+/// and sent as bytes, or as their inner value when there is none
+fn to_my_values(
+    params: &[&Value],
+    registry: &TypeRegistry,
+) -> Result<Vec<mysql::Value>, ConvertError> {
+    params
+        .iter()
+        .map(|param| {
+            match param {
+                Value::Custom(custom) => {
+                    match registry.encoder(&custom.type_name, None) {
+                        Some(encode) => Ok(mysql::Value::Bytes(encode(param)?)),
+                        None => MyValue(param).to_my_value(),
+                    }
+                }
+                _ => MyValue(param).to_my_value(),
+            }
+        })
+        .collect()
+}
+
 #[derive(Debug)]
 pub struct MyValue<'a>(&'a Value);
 
-impl mysql::prelude::ToValue for MyValue<'_> {
-    fn to_value(&self) -> mysql::Value {
-        match self.0 {
+impl MyValue<'_> {
+    /// convert into the value used in the statement parameters
+    fn to_my_value(&self) -> Result<mysql::Value, ConvertError> {
+        let value = match self.0 {
             Value::Bool(ref v) => v.into(),
             Value::Tinyint(ref v) => v.into(),
             Value::Smallint(ref v) => v.into(),
@@ -412,27 +615,109 @@ impl mysql::prelude::ToValue for MyValue<'_> {
             Value::Bigint(ref v) => v.into(),
             Value::Float(ref v) => v.into(),
             Value::Double(ref v) => v.into(),
+            // mysql parses the decimal from its text representation without losing precision
+            Value::BigDecimal(ref v) => v.to_string().into(),
             Value::Blob(ref v) => v.into(),
             Value::Char(ref v) => v.to_string().into(),
             Value::Text(ref v) => v.into(),
             Value::Uuid(ref v) => v.as_bytes().into(),
             Value::Date(ref v) => v.into(),
+            // the connections use the utc time zone
             Value::Timestamp(ref v) => v.naive_utc().into(),
+            Value::TimestampTz(ref v) => v.naive_utc().into(),
             Value::DateTime(ref v) => v.into(),
             Value::Time(ref v) => v.into(),
-            Value::Interval(ref _v) => panic!("storing interval in DB is not supported"),
-            Value::Json(ref v) => v.into(),
+            Value::Json(ref v) => v.to_string().into(),
+            // mysql has no arrays, they are stored as json
+            Value::Array(ref v) => v.to_json().into(),
             Value::Nil => mysql::Value::NULL,
-            Value::BigDecimal(_) => unimplemented!("we need to upgrade bigdecimal crate"),
-            Value::Point(_) | Value::Array(_) => unimplemented!("unsupported type"),
+            Value::Interval(ref v) => interval_to_my_value(v),
+            Value::Money(v) => v.into(),
+            // the bits of a BIT column as an integer
+            Value::Bit(ref v) if v.0.len() <= 64 => {
+                v.0.iter().fold(0u64, |n, bit| n << 1 | u64::from(*bit)).into()
+            }
+            // the addresses and ranges in their text format, the hstores as a json object
+            Value::Inet(_)
+            | Value::Cidr(_)
+            | Value::MacAddr(_)
+            | Value::Bit(_)
+            | Value::Range(_)
+            | Value::Hstore(_) => self.0.to_string().into(),
+            Value::Point(ref v) => to_my_geometry(&Geometry::new(*v)),
+            Value::Geometry(ref v) => to_my_geometry(v),
+            Value::Custom(ref v) => return MyValue(&v.value).to_my_value(),
+            // mysql has no composite types, the fields are stored as json
+            Value::Composite(ref v) => {
+                serde_json::to_string(v)
+                    .map_err(|e| ConvertError::UnsupportedConversion(e.to_string(), "json".into()))?
+                    .into()
+            }
+            Value::TimeTz(..) => {
+                return Err(ConvertError::UnsupportedDataType(format!("{:?}", self.0)));
+            }
+        };
+        Ok(value)
+    }
+}
+
+/// the internal format of the spatial values of mysql, the srid in 4 little endian bytes
+/// followed by the WKB
+fn to_my_geometry(geometry: &Geometry) -> mysql::Value {
+    let mut bytes = geometry.srid.unwrap_or(0).to_le_bytes().to_vec();
+    bytes.extend(geometry.to_wkb());
+    mysql::Value::Bytes(bytes)
+}
+
+fn from_my_geometry(bytes: Vec<u8>) -> Result<Value, ConvertError> {
+    if bytes.len() < 4 {
+        return Err(ConvertError::UnsupportedConversion(
+            format!("{:?}", bytes),
+            "Geometry".to_string(),
+        ));
+    }
+    let (srid, wkb) = bytes.split_at(4);
+    let srid = i32::from_le_bytes([srid[0], srid[1], srid[2], srid[3]]);
+    let geometry = Geometry::from_wkb(wkb).map_err(|e| {
+        ConvertError::UnsupportedConversion(format!("{:?}", bytes), e.to_string())
+    })?;
+    let geometry = if srid == 0 {
+        geometry
+    } else {
+        geometry.with_srid(srid)
+    };
+    Ok(Value::Geometry(Box::new(geometry)))
+}
+
+/// the interval as a TIME when it has no months and is in the range of TIME,
+/// otherwise as an ISO 8601 duration
+fn interval_to_my_value(interval: &Interval) -> mysql::Value {
+    /// TIME is at most 838:59:59
+    const MAX_TIME_MICROSECONDS: i64 = (839 * 3600 - 1) * 1_000_000;
+    let micros = i64::from(interval.days)
+        .checked_mul(86_400_000_000)
+        .and_then(|days| days.checked_add(interval.microseconds));
+    match micros {
+        Some(micros) if interval.months == 0 && micros.abs() <= MAX_TIME_MICROSECONDS => {
+            let (is_negative, micros) = (micros < 0, micros.abs());
+            let seconds = micros / 1_000_000;
+            mysql::Value::Time(
+                is_negative,
+                (seconds / 86_400) as u32,
+                (seconds % 86_400 / 3600) as u8,
+                (seconds % 3600 / 60) as u8,
+                (seconds % 60) as u8,
+                (micros % 1_000_000) as u32,
+            )
         }
+        _ => interval.to_string().into(),
     }
 }
 
 fn into_record(
     mut row: mysql::Row,
     column_types: &[mysql::consts::ColumnType],
-) -> Result<Vec<Value>, MysqlError> {
+) -> Result<Vec<Value>, DbError> {
     use mysql::{
         consts::ColumnType,
         from_value_opt as fvo,
@@ -444,14 +729,14 @@ fn into_record(
         .map(|(i, column_type)| {
             let cell: mysql::Value = row
                 .take_opt(i)
-                .unwrap_or_else(|| unreachable!("column length does not enough"))
-                .unwrap_or_else(|_| unreachable!("could not convert as `mysql::Value`"));
+                .ok_or_else(|| DaoError::NoSuchValueError(format!("column {}", i)))?
+                .map_err(MysqlError::from)?;
 
             if cell == mysql::Value::NULL {
                 return Ok(Value::Nil);
             }
 
-            match column_type {
+            let value = match column_type {
                 ColumnType::MYSQL_TYPE_DECIMAL | ColumnType::MYSQL_TYPE_NEWDECIMAL => {
                     fvo(cell)
                         .and_then(|v: Vec<u8>| {
@@ -473,7 +758,7 @@ fn into_record(
                 ColumnType::MYSQL_TYPE_NULL => fvo(cell).map(|_: mysql::Value| Value::Nil),
                 ColumnType::MYSQL_TYPE_TIMESTAMP => {
                     fvo(cell).map(|v: chrono::NaiveDateTime| {
-                        Value::Timestamp(chrono::DateTime::from_utc(v, chrono::Utc))
+                        Value::Timestamp(chrono::TimeZone::from_utc_datetime(&chrono::Utc, &v))
                     })
                 }
                 ColumnType::MYSQL_TYPE_DATE | ColumnType::MYSQL_TYPE_NEWDATE => {
@@ -483,27 +768,30 @@ fn into_record(
                 ColumnType::MYSQL_TYPE_DATETIME => fvo(cell).map(Value::DateTime),
                 ColumnType::MYSQL_TYPE_VARCHAR
                 | ColumnType::MYSQL_TYPE_VAR_STRING
-                | ColumnType::MYSQL_TYPE_STRING => fvo(cell).map(Value::Text),
+                | ColumnType::MYSQL_TYPE_STRING
+                | ColumnType::MYSQL_TYPE_ENUM
+                | ColumnType::MYSQL_TYPE_SET => fvo(cell).map(Value::Text),
                 ColumnType::MYSQL_TYPE_JSON => fvo(cell).map(Value::Json),
+                ColumnType::MYSQL_TYPE_GEOMETRY => {
+                    let bytes: Vec<u8> = fvo(cell).map_err(MysqlError::from)?;
+                    return from_my_geometry(bytes).map_err(DbError::from);
+                }
                 ColumnType::MYSQL_TYPE_TINY_BLOB
                 | ColumnType::MYSQL_TYPE_MEDIUM_BLOB
                 | ColumnType::MYSQL_TYPE_LONG_BLOB
                 | ColumnType::MYSQL_TYPE_BLOB => fvo(cell).map(Value::Blob),
+                // TIMESTAMP2, DATETIME2 and TIME2 are only used in the server side
                 ColumnType::MYSQL_TYPE_TIMESTAMP2
                 | ColumnType::MYSQL_TYPE_DATETIME2
-                | ColumnType::MYSQL_TYPE_TIME2 => {
-                    panic!("only used in server side: {:?}", column_type)
-                }
-                ColumnType::MYSQL_TYPE_BIT
-                | ColumnType::MYSQL_TYPE_ENUM
-                | ColumnType::MYSQL_TYPE_SET
-                | ColumnType::MYSQL_TYPE_GEOMETRY
+                | ColumnType::MYSQL_TYPE_TIME2
+                | ColumnType::MYSQL_TYPE_BIT
                 | ColumnType::MYSQL_TYPE_TYPED_ARRAY
                 | ColumnType::MYSQL_TYPE_UNKNOWN => {
-                    panic!("not yet handling this kind: {:?}", column_type)
+                    let data_type = format!("{:?}", column_type);
+                    return Err(ConvertError::UnsupportedDataType(data_type).into());
                 }
-            }
-            .map_err(MysqlError::from)
+            };
+            value.map_err(|e| MysqlError::from(e).into())
         })
         .collect()
 }
@@ -525,3 +813,181 @@ pub enum MysqlError {
 impl From<mysql::Error> for MysqlError {
     fn from(e: mysql::Error) -> Self { MysqlError::Sql(e, "Generic Error".into()) }
 }
+
+impl MysqlError {
+    /// the details of a constraint violation, a lock conflict or a lost connection, parsed from
+    /// the error number and the message such as `Duplicate entry 'x' for key 'actor.PRIMARY'`
+    pub(crate) fn to_data_op_error(&self) -> Option<DataOpError> {
+        let (error, sql) = match self {
+            MysqlError::Sql(mysql::Error::MySqlError(error), sql) => (error, sql),
+            MysqlError::Sql(mysql::Error::IoError(e), sql) => {
+                return Some(DataOpError::ConnectionLost {
+                    message: e.to_string(),
+                    sql: Some(sql.to_string()),
+                });
+            }
+            MysqlError::Sql(mysql::Error::CodecError(e), sql) => {
+                return Some(DataOpError::ConnectionLost {
+                    message: e.to_string(),
+                    sql: Some(sql.to_string()),
+                });
+            }
+            _ => return None,
+        };
+        let message = error.message.as_str();
+        let mut cause_table = None;
+        let mut column = None;
+        let mut constraint = None;
+        match error.code {
+            // ER_DUP_ENTRY, ER_DUP_ENTRY_WITH_KEY_NAME
+            1062 | 1586 => {
+                // since mysql 8.0 the key is prefixed with the table name
+                let key = message
+                    .rfind("for key '")
+                    .and_then(|start| between(&message[start..], "for key '", "'"));
+                match key.map(|key| key.rsplit_once('.').unwrap_or(("", key))) {
+                    Some(("", key)) => constraint = Some(key.to_string()),
+                    Some((table, key)) => {
+                        cause_table = Some(table.to_string());
+                        constraint = Some(key.to_string());
+                    }
+                    None => (),
+                }
+            }
+            // ER_ROW_IS_REFERENCED_2, ER_NO_REFERENCED_ROW_2
+            1451 | 1452 => {
+                // (`db`.`child`, CONSTRAINT `fk` FOREIGN KEY (`column`) REFERENCES ..)
+                cause_table = between(message, "(", ", CONSTRAINT").map(|t| t.replace('`', ""));
+                constraint = between(message, "CONSTRAINT `", "`").map(String::from);
+                column = between(message, "FOREIGN KEY (", ")")
+                    .filter(|columns| !columns.contains(','))
+                    .map(|c| c.replace('`', ""));
+            }
+            // ER_ROW_IS_REFERENCED, ER_NO_REFERENCED_ROW
+            1216 | 1217 => (),
+            // ER_BAD_NULL_ERROR
+            1048 => column = between(message, "Column '", "'").map(String::from),
+            // ER_NO_DEFAULT_FOR_FIELD
+            1364 => column = between(message, "Field '", "'").map(String::from),
+            // ER_CHECK_CONSTRAINT_VIOLATED
+            3819 => constraint = between(message, "Check constraint '", "'").map(String::from),
+            // ER_LOCK_WAIT_TIMEOUT, ER_LOCK_DEADLOCK
+            1205 | 1213 => (),
+            // ER_QUERY_TIMEOUT, ER_QUERY_INTERRUPTED
+            3024 | 1317 => (),
+            // CR_SERVER_GONE_ERROR, CR_SERVER_LOST
+            2006 | 2013 => {
+                return Some(DataOpError::ConnectionLost {
+                    message: error.message.clone(),
+                    sql: Some(sql.to_string()),
+                });
+            }
+            _ => return None,
+        }
+        Some(DataOpError::ConstraintError {
+            severity: "ERROR".to_string(),
+            code: error.code.to_string(),
+            message: error.message.clone(),
+            detail: None,
+            cause_table,
+            constraint,
+            column,
+            datatype: None,
+            sql: sql.to_string(),
+        })
+    }
+}
+
+/// the text between the first occurence of `start` and the `end` that follows it
+fn between<'a>(text: &'a str, start: &str, end: &str) -> Option<&'a str> {
+    let from = text.find(start)? + start.len();
+    let to = from + text[from..].find(end)?;
+    Some(&text[from..to])
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn constraint_error(code: u16, message: &str) -> Option<DataOpError> {
+        let error = mysql::Error::MySqlError(mysql::MySqlError {
+            state: "23000".to_string(),
+            message: message.to_string(),
+            code,
+        });
+        MysqlError::Sql(error, "INSERT INTO child ..".to_string()).to_data_op_error()
+    }
+
+    /// the code, table, column and constraint of the constraint error
+    type Parsed = (String, Option<String>, Option<String>, Option<String>);
+
+    fn parsed(error: Option<DataOpError>) -> Parsed {
+        match error {
+            Some(DataOpError::ConstraintError {
+                code,
+                cause_table,
+                column,
+                constraint,
+                ..
+            }) => (code, cause_table, column, constraint),
+            other => panic!("expecting a constraint error, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn duplicate_entry() {
+        assert_eq!(
+            parsed(constraint_error(1062, "Duplicate entry 'a.b' for key 'actor.name_idx'")),
+            ("1062".into(), Some("actor".into()), None, Some("name_idx".into()))
+        );
+        assert_eq!(
+            parsed(constraint_error(1062, "Duplicate entry '1' for key 'PRIMARY'")),
+            ("1062".into(), None, None, Some("PRIMARY".into()))
+        );
+    }
+
+    #[test]
+    fn foreign_key_violation() {
+        let message = "Cannot add or update a child row: a foreign key constraint fails \
+            (`sakila`.`child`, CONSTRAINT `fk_parent` FOREIGN KEY (`parent_id`) \
+            REFERENCES `parent` (`id`))";
+        assert_eq!(
+            parsed(constraint_error(1452, message)),
+            (
+                "1452".into(),
+                Some("sakila.child".into()),
+                Some("parent_id".into()),
+                Some("fk_parent".into())
+            )
+        );
+    }
+
+    #[test]
+    fn not_null_and_check_violation() {
+        assert_eq!(
+            parsed(constraint_error(1048, "Column 'name' cannot be null")).2,
+            Some("name".into())
+        );
+        assert_eq!(
+            parsed(constraint_error(3819, "Check constraint 'positive_age' is violated.")).3,
+            Some("positive_age".into())
+        );
+        assert!(constraint_error(1146, "Table 'sakila.nope' doesn't exist").is_none());
+    }
+
+    #[test]
+    fn custom_parameters_use_the_registered_encoder() {
+        use crate::Custom;
+
+        let mut registry = TypeRegistry::new();
+        registry.register_encoder("mood", |value| {
+            Ok(value.to_string().to_uppercase().into_bytes())
+        });
+        let happy = Custom::new("mood", "happy").to_value();
+        let other = Custom::new("color", "red").to_value();
+        assert_eq!(to_my_values(&[&happy, &other], &registry).unwrap(), vec![
+            mysql::Value::Bytes(b"HAPPY".to_vec()),
+            mysql::Value::Bytes(b"red".to_vec()),
+        ]);
+    }
+}
@@ -0,0 +1,420 @@
+//! Hooks to observe the sql statements executed by the entity managers and dao managers.
+//!
+//! An observer is notified after each statement with the sql, the parameters, the time it
+//! took, the number of rows returned and the error if any. The parameters are redacted by
+//! default so that the values don't end up in the application logs.
+//!
+//! The statements taking longer than the threshold of the `SlowQueryLog` are logged as
+//! warnings, together with their query plan unless they are executed inside a transaction.
+//!
+//! With the `with-tracing` feature, each statement is also executed in a `rustorm.query`
+//! tracing span.
+//!
+//! Only the statements executed through an `EntityManager` or a `DaoManager` are observed.
+//! The catalog queries of the introspection, such as `get_table` and `get_all_tables`, are
+//! executed on the database directly and are not seen by the observers, the slow query log
+//! or the `QueryStats` metrics.
+use crate::{
+    DbError,
+    Rows,
+    Value,
+};
+use log::Level;
+use std::{
+    fmt,
+    sync::Arc,
+    time::{
+        Duration,
+        Instant,
+    },
+};
+
+/// Receives the details of every statement executed by an entity manager or a dao manager,
+/// except the introspection queries
+pub trait QueryObserver: Send + Sync {
+    fn on_query(&self, event: &QueryEvent<'_>);
+}
+
+/// How the parameter values are exposed to the observers
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Redaction {
+    /// replace every value with `<redacted>`, only the null values are shown
+    #[default]
+    All,
+    /// show the values as is
+    None,
+}
+
+/// The details of an executed statement
+pub struct QueryEvent<'a> {
+    pub sql: &'a str,
+    params: &'a [&'a Value],
+    redaction: Redaction,
+    pub duration: Duration,
+    /// the number of rows returned, None when the statement failed
+    pub row_count: Option<usize>,
+    pub error: Option<&'a DbError>,
+}
+
+impl QueryEvent<'_> {
+    /// the parameter values, redacted according to the redaction of the pool
+    pub fn params(&self) -> Vec<String> {
+        self.params
+            .iter()
+            .map(|param| {
+                match (self.redaction, param) {
+                    (_, Value::Nil) => "NULL".to_string(),
+                    (Redaction::All, _) => "<redacted>".to_string(),
+                    (Redaction::None, param) => format!("{:?}", param),
+                }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for QueryEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("QueryEvent")
+            .field("sql", &self.sql)
+            .field("params", &self.params())
+            .field("duration", &self.duration)
+            .field("row_count", &self.row_count)
+            .field("error", &self.error)
+            .finish()
+    }
+}
+
+/// An observer writing the statements to the `log` facade
+#[derive(Debug, Clone)]
+pub struct LogObserver {
+    level: Level,
+}
+
+impl LogObserver {
+    /// log the statements at the debug level, the failed statements are logged as warnings
+    pub fn new() -> Self { LogObserver { level: Level::Debug } }
+
+    /// log the successful statements at this level
+    pub fn with_level(level: Level) -> Self { LogObserver { level } }
+}
+
+impl Default for LogObserver {
+    fn default() -> Self { Self::new() }
+}
+
+impl QueryObserver for LogObserver {
+    fn on_query(&self, event: &QueryEvent<'_>) {
+        match event.error {
+            Some(error) => {
+                log::warn!(
+                    target: "rustorm::query",
+                    "failed in {:?}: {} params: {:?} error: {}",
+                    event.duration,
+                    event.sql,
+                    event.params(),
+                    error
+                );
+            }
+            None => {
+                log::log!(
+                    target: "rustorm::query",
+                    self.level,
+                    "executed in {:?}: {} params: {:?} rows: {}",
+                    event.duration,
+                    event.sql,
+                    event.params(),
+                    event.row_count.unwrap_or(0)
+                );
+            }
+        }
+    }
+}
+
+/// Log the statements which take longer than a threshold
+#[derive(Debug, Clone)]
+pub struct SlowQueryLog {
+    threshold: Duration,
+    explain: bool,
+}
+
+impl SlowQueryLog {
+    /// log the statements taking longer than `threshold` together with their query plan
+    pub fn new(threshold: Duration) -> Self {
+        SlowQueryLog {
+            threshold,
+            explain: true,
+        }
+    }
+
+    /// whether to execute `EXPLAIN` on the slow statements, this does not execute the statement
+    /// again since `EXPLAIN ANALYZE` is not used. Enabled by default.
+    ///
+    /// The statements executed inside a transaction are not explained, since an error of the
+    /// `EXPLAIN` would abort the transaction in postgresql.
+    pub fn explain(mut self, explain: bool) -> Self {
+        self.explain = explain;
+        self
+    }
+
+    fn log(&self, sql: &str, duration: Duration, plan: Option<Result<Rows, DbError>>) {
+        let plan = match plan {
+            Some(Ok(plan)) => {
+                plan.data
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(" | ")
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            Some(Err(e)) => format!("unable to explain: {}", e),
+            None => String::new(),
+        };
+        log::warn!(
+            target: "rustorm::slow_query",
+            "slow query took {:?}: {}\n{}",
+            duration,
+            sql,
+            plan
+        );
+    }
+}
+
+/// only these statements can be explained
+fn is_explainable(sql: &str) -> bool {
+    let keyword: String = sql
+        .trim_start()
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect::<String>()
+        .to_lowercase();
+    matches!(&*keyword, "select" | "insert" | "update" | "delete" | "with")
+}
+
+/// whether the statement starts or ends a transaction, `None` for the other statements
+/// including `ROLLBACK TO SAVEPOINT`
+fn transaction_state(sql: &str) -> Option<bool> {
+    let words: Vec<String> = sql
+        .split(|c: char| c.is_whitespace() || c == ';')
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect();
+    match words.first().map(String::as_str) {
+        Some("begin") | Some("start") => Some(true),
+        Some("commit") | Some("end") | Some("abort") => Some(false),
+        Some("rollback") if !words.iter().any(|word| word == "to") => Some(false),
+        _ => None,
+    }
+}
+
+/// The observers of the statements executed by an entity manager or a dao manager
+#[derive(Clone, Default)]
+pub(crate) struct QueryObservers {
+    observers: Vec<Arc<dyn QueryObserver>>,
+    redaction: Redaction,
+    slow_query_log: Option<SlowQueryLog>,
+    /// whether the statements are executed inside a transaction
+    in_transaction: bool,
+}
+
+impl QueryObservers {
+    pub(crate) fn add(&mut self, observer: Arc<dyn QueryObserver>) {
+        self.observers.push(observer)
+    }
+
+    pub(crate) fn set_redaction(&mut self, redaction: Redaction) { self.redaction = redaction }
+
+    pub(crate) fn set_slow_query_log(&mut self, slow_query_log: Option<SlowQueryLog>) {
+        self.slow_query_log = slow_query_log;
+    }
+
+    /// set when a transaction is started or ended without executing a statement here
+    pub(crate) fn set_in_transaction(&mut self, in_transaction: bool) {
+        self.in_transaction = in_transaction;
+    }
+
+    /// execute the statement and notify the observers of the outcome, a slow statement is
+    /// explained by executing it again prefixed with `explain_prefix` when it is not executed
+    /// inside a transaction
+    pub(crate) fn observe<F>(
+        &mut self,
+        sql: &str,
+        params: &[&Value],
+        explain_prefix: &str,
+        mut execute: F,
+    ) -> Result<Rows, DbError>
+    where
+        F: FnMut(&str, &[&Value]) -> Result<Rows, DbError>,
+    {
+        #[cfg(feature = "with-tracing")]
+        let span = tracing::debug_span!(
+            "rustorm.query",
+            sql,
+            rows = tracing::field::Empty,
+            error = tracing::field::Empty
+        );
+        #[cfg(feature = "with-tracing")]
+        let _entered = span.enter();
+
+        let start = Instant::now();
+        let result = execute(sql, params);
+        let duration = start.elapsed();
+        let in_transaction = self.in_transaction;
+        if let Some(in_transaction) = transaction_state(sql) {
+            self.in_transaction = in_transaction;
+        }
+
+        #[cfg(feature = "with-tracing")]
+        match result {
+            Ok(ref rows) => span.record("rows", rows.data.len()),
+            Err(ref error) => span.record("error", tracing::field::display(error)),
+        };
+
+        if let Some(ref slow_query_log) = self.slow_query_log {
+            if duration >= slow_query_log.threshold {
+                let plan = if slow_query_log.explain
+                    && !in_transaction
+                    && result.is_ok()
+                    && is_explainable(sql)
+                {
+                    Some(execute(&format!("{} {}", explain_prefix, sql), params))
+                } else {
+                    None
+                };
+                slow_query_log.log(sql, duration, plan);
+            }
+        }
+
+        if !self.observers.is_empty() {
+            let event = QueryEvent {
+                sql,
+                params,
+                redaction: self.redaction,
+                duration,
+                row_count: result.as_ref().ok().map(|rows| rows.data.len()),
+                error: result.as_ref().err(),
+            };
+            for observer in self.observers.iter() {
+                observer.on_query(&event);
+            }
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::DataError;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl QueryObserver for Recorder {
+        fn on_query(&self, event: &QueryEvent<'_>) {
+            self.0.lock().unwrap().push(format!(
+                "{} {:?} {:?} {}",
+                event.sql,
+                event.params(),
+                event.row_count,
+                event.error.is_some()
+            ));
+        }
+    }
+
+    #[test]
+    fn observers_are_notified_with_redacted_params() {
+        let recorder = Arc::new(Recorder::default());
+        let mut observers = QueryObservers::default();
+        observers.add(recorder.clone());
+        let secret = Value::Text("hunter2".into());
+        let rows = observers.observe("SELECT $1, $2", &[&secret, &Value::Nil], "EXPLAIN", |_, _| {
+            let mut rows = Rows::new(vec!["password".into(), "nothing".into()]);
+            rows.push(vec![secret.clone(), Value::Nil]);
+            Ok(rows)
+        });
+        assert!(rows.is_ok());
+        let result = observers.observe("SELECT 1", &[], "EXPLAIN", |_, _| {
+            Err(DbError::DataError(DataError::ZeroRecordReturned))
+        });
+        assert!(result.is_err());
+        assert_eq!(*recorder.0.lock().unwrap(), vec![
+            r#"SELECT $1, $2 ["<redacted>", "NULL"] Some(1) false"#.to_string(),
+            "SELECT 1 [] None true".to_string(),
+        ]);
+    }
+
+    #[test]
+    fn params_are_shown_without_redaction() {
+        let recorder = Arc::new(Recorder::default());
+        let mut observers = QueryObservers::default();
+        observers.add(recorder.clone());
+        observers.set_redaction(Redaction::None);
+        let _ = observers.observe("SELECT $1", &[&Value::Int(42)], "EXPLAIN", |_, _| {
+            Ok(Rows::new(vec![]))
+        });
+        assert_eq!(*recorder.0.lock().unwrap(), vec![
+            r#"SELECT $1 ["Int(42)"] Some(0) false"#.to_string()
+        ]);
+    }
+
+    #[test]
+    fn explain_slow_queries() {
+        let mut observers = QueryObservers::default();
+        observers.set_slow_query_log(Some(SlowQueryLog::new(Duration::from_secs(0))));
+        let mut executed = vec![];
+        let id = Value::Int(1);
+        for sql in &["SELECT * FROM film WHERE film_id = $1", "BEGIN TRANSACTION"] {
+            let _ = observers.observe(sql, &[&id], "EXPLAIN", |sql, params| {
+                executed.push(format!("{} {:?}", sql, params));
+                Ok(Rows::empty())
+            });
+        }
+        assert_eq!(executed, vec![
+            "SELECT * FROM film WHERE film_id = $1 [Int(1)]",
+            "EXPLAIN SELECT * FROM film WHERE film_id = $1 [Int(1)]",
+            "BEGIN TRANSACTION [Int(1)]",
+        ]);
+    }
+
+    #[test]
+    fn slow_queries_are_not_explained_inside_a_transaction() {
+        let mut observers = QueryObservers::default();
+        observers.set_slow_query_log(Some(SlowQueryLog::new(Duration::from_secs(0))));
+        let mut executed = vec![];
+        for sql in &[
+            "BEGIN",
+            "SELECT 1",
+            "SAVEPOINT a",
+            "ROLLBACK TO SAVEPOINT a",
+            "SELECT 2",
+            "COMMIT",
+            "SELECT 3",
+        ] {
+            let _ = observers.observe(sql, &[], "EXPLAIN", |sql, _| {
+                executed.push(sql.to_string());
+                Ok(Rows::empty())
+            });
+        }
+        observers.set_in_transaction(true);
+        let _ = observers.observe("SELECT 4", &[], "EXPLAIN", |sql, _| {
+            executed.push(sql.to_string());
+            Ok(Rows::empty())
+        });
+        assert_eq!(executed, vec![
+            "BEGIN",
+            "SELECT 1",
+            "SAVEPOINT a",
+            "ROLLBACK TO SAVEPOINT a",
+            "SELECT 2",
+            "COMMIT",
+            "SELECT 3",
+            "EXPLAIN SELECT 3",
+            "SELECT 4",
+        ]);
+    }
+}
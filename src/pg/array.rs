@@ -0,0 +1,148 @@
+//! The binary format of the postgresql arrays, which supports the multi-dimensional arrays
+//! and the null elements that the `Vec<T>` of the postgres crate doesn't.
+//!
+//! The array starts with the number of dimensions, whether it has nulls and the oid of the
+//! element type, followed by the length and the lower bound of each dimension, then each
+//! element prefixed with its length, -1 for null.
+use super::{
+    OwnedPgValue,
+    PgValue,
+};
+use crate::{
+    error::ConvertError,
+    Value,
+};
+use byteorder::{
+    NetworkEndian,
+    ReadBytesExt,
+};
+use bytes::{
+    BufMut,
+    BytesMut,
+};
+use clia_rustorm_dao::value::Array;
+use postgres::types::{
+    FromSql,
+    IsNull,
+    ToSql,
+    Type,
+};
+use std::{
+    convert::TryFrom,
+    error::Error,
+};
+
+/// encode the array whose elements are of the `member` type
+pub(crate) fn to_sql(
+    array: &Array,
+    member: &Type,
+    out: &mut BytesMut,
+) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+    let dimensions = if array.is_empty() {
+        vec![]
+    } else {
+        array.dimensions()?
+    };
+    let elements: Vec<Value> = array
+        .flatten()
+        .into_iter()
+        .map(|element| coerce(element, member))
+        .collect();
+    out.put_i32(dimensions.len() as i32);
+    out.put_i32(elements.iter().any(Value::is_nil) as i32);
+    out.put_u32(member.oid());
+    for dimension in dimensions {
+        out.put_i32(dimension as i32);
+        // the arrays are 1-based
+        out.put_i32(1);
+    }
+    for element in elements.iter() {
+        let start = out.len();
+        out.put_i32(0);
+        let len = match PgValue(element).to_sql(member, out)? {
+            IsNull::Yes => -1,
+            IsNull::No => (out.len() - start - 4) as i32,
+        };
+        out[start..start + 4].copy_from_slice(&len.to_be_bytes());
+    }
+    Ok(IsNull::No)
+}
+
+/// decode the array whose elements are of the `member` type
+pub(crate) fn from_sql(member: &Type, raw: &[u8]) -> Result<Array, Box<dyn Error + Sync + Send>> {
+    let mut raw = raw;
+    let ndim = raw.read_i32::<NetworkEndian>()?;
+    let _has_nulls = raw.read_i32::<NetworkEndian>()?;
+    let _oid = raw.read_u32::<NetworkEndian>()?;
+    let mut dimensions = vec![];
+    for _ in 0..ndim {
+        dimensions.push(raw.read_i32::<NetworkEndian>()? as usize);
+        let _lower_bound = raw.read_i32::<NetworkEndian>()?;
+    }
+    let count = if dimensions.is_empty() {
+        0
+    } else {
+        dimensions.iter().product()
+    };
+    // each element takes at least the 4 bytes of its length
+    let mut elements = Vec::with_capacity(count.min(raw.len() / 4));
+    for _ in 0..count {
+        let len = raw.read_i32::<NetworkEndian>()?;
+        if len < 0 {
+            elements.push(Value::Nil);
+        } else {
+            let len = len as usize;
+            if raw.len() < len {
+                return Err(Box::new(ConvertError::UnsupportedConversion(
+                    format!("{:?}", raw),
+                    format!("{}[]", member.name()),
+                )));
+            }
+            let (element, rest) = raw.split_at(len);
+            let OwnedPgValue(element) = FromSql::from_sql(member, element)?;
+            elements.push(element);
+            raw = rest;
+        }
+    }
+    Ok(nest(&dimensions, elements))
+}
+
+/// split the elements into sub arrays for each dimension after the first
+fn nest(dimensions: &[usize], elements: Vec<Value>) -> Array {
+    match dimensions {
+        [len, sub_dimensions @ ..] if !sub_dimensions.is_empty() => {
+            let chunk: usize = sub_dimensions.iter().product();
+            Array::Nested(
+                (0..*len)
+                    .map(|i| nest(sub_dimensions, elements[i * chunk..(i + 1) * chunk].to_vec()))
+                    .collect(),
+            )
+        }
+        _ => Array::from_values(elements),
+    }
+}
+
+/// convert the numeric elements into the width of the element type,
+/// ie: `Vec<i32>` into a `bigint[]`
+pub(crate) fn coerce(element: Value, member: &Type) -> Value {
+    let integer = match element {
+        Value::Tinyint(v) => Some(i64::from(v)),
+        Value::Smallint(v) => Some(i64::from(v)),
+        Value::Int(v) => Some(i64::from(v)),
+        Value::Bigint(v) => Some(v),
+        _ => None,
+    };
+    match (integer, member) {
+        (Some(v), &Type::INT2) => i16::try_from(v).map(Value::Smallint).unwrap_or(element),
+        (Some(v), &Type::INT4) => i32::try_from(v).map(Value::Int).unwrap_or(element),
+        (Some(v), &Type::INT8) => Value::Bigint(v),
+        (Some(v), &Type::FLOAT8) => Value::Double(v as f64),
+        (None, &Type::FLOAT8) => {
+            match element {
+                Value::Float(v) => Value::Double(f64::from(v)),
+                element => element,
+            }
+        }
+        _ => element,
+    }
+}
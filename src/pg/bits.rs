@@ -0,0 +1,52 @@
+//! The binary format of `bit` and `bit varying`, the number of bits followed by the bits
+//! packed into bytes.
+use byteorder::{
+    NetworkEndian,
+    ReadBytesExt,
+};
+use bytes::{
+    BufMut,
+    BytesMut,
+};
+use clia_rustorm_dao::BitString;
+use postgres::types::{
+    to_sql_checked,
+    FromSql,
+    IsNull,
+    ToSql,
+    Type,
+};
+use std::error::Error;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgBitString(pub BitString);
+
+impl<'a> FromSql<'a> for PgBitString {
+    fn from_sql(_ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut bytes = raw;
+        let len = bytes.read_i32::<NetworkEndian>()? as usize;
+        if bytes.len() * 8 < len {
+            return Err(format!("invalid bit string: {:?}", raw).into());
+        }
+        Ok(PgBitString(BitString::from_bytes(bytes, len)))
+    }
+
+    fn accepts(ty: &Type) -> bool { matches!(*ty, Type::BIT | Type::VARBIT) }
+}
+
+impl ToSql for PgBitString {
+    to_sql_checked!();
+
+    fn to_sql(
+        &self,
+        _ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + 'static + Sync + Send>> {
+        let PgBitString(bits) = self;
+        out.put_i32(bits.0.len() as i32);
+        out.put_slice(&bits.to_bytes());
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool { matches!(*ty, Type::BIT | Type::VARBIT) }
+}
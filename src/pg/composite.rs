@@ -0,0 +1,73 @@
+//! The binary format of the postgresql composite types, the number of fields followed by the
+//! oid of each field and its value, prefixed with its length or -1 when it is null.
+use super::{
+    array::coerce,
+    OwnedPgValue,
+    PgValue,
+};
+use byteorder::{
+    NetworkEndian,
+    ReadBytesExt,
+};
+use bytes::{
+    BufMut,
+    BytesMut,
+};
+use clia_rustorm_dao::{
+    Dao,
+    Value,
+};
+use postgres::types::{
+    Field,
+    FromSql,
+    IsNull,
+    ToSql,
+};
+use std::error::Error;
+
+/// encode the dao into the composite type with these fields, the missing fields are null
+pub(crate) fn to_sql(
+    dao: &Dao,
+    fields: &[Field],
+    out: &mut BytesMut,
+) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+    out.put_i32(fields.len() as i32);
+    for field in fields {
+        out.put_u32(field.type_().oid());
+        let start = out.len();
+        out.put_i32(0);
+        let value = dao.get_value(field.name()).cloned().unwrap_or(Value::Nil);
+        let len = match PgValue(&coerce(value, field.type_())).to_sql(field.type_(), out)? {
+            IsNull::Yes => -1,
+            IsNull::No => (out.len() - start - 4) as i32,
+        };
+        out[start..start + 4].copy_from_slice(&len.to_be_bytes());
+    }
+    Ok(IsNull::No)
+}
+
+/// decode the composite type with these fields into a dao
+pub(crate) fn from_sql(fields: &[Field], raw: &[u8]) -> Result<Dao, Box<dyn Error + Sync + Send>> {
+    let mut raw = raw;
+    let count = raw.read_i32::<NetworkEndian>()?;
+    if count as usize != fields.len() {
+        return Err(format!("expecting {} fields, found {}", fields.len(), count).into());
+    }
+    let mut dao = Dao::new();
+    for field in fields {
+        let _oid = raw.read_u32::<NetworkEndian>()?;
+        let len = raw.read_i32::<NetworkEndian>()?;
+        let value = if len < 0 {
+            None
+        } else if raw.len() < len as usize {
+            return Err(format!("invalid field {}: {:?}", field.name(), raw).into());
+        } else {
+            let (value, rest) = raw.split_at(len as usize);
+            raw = rest;
+            Some(value)
+        };
+        let OwnedPgValue(value) = OwnedPgValue::from_sql_nullable(field.type_(), value)?;
+        dao.insert_value(field.name(), &value);
+    }
+    Ok(dao)
+}
@@ -0,0 +1,119 @@
+//! The geometric types of postgresql and the `geometry` and `geography` of PostGIS, whose
+//! binary format is the EWKB.
+use crate::{
+    error::ConvertError,
+    Value,
+};
+use byteorder::{
+    NetworkEndian,
+    ReadBytesExt,
+};
+use bytes::{
+    BufMut,
+    BytesMut,
+};
+use clia_rustorm_dao::Geometry;
+use geo_types::{
+    Coord,
+    Line,
+    LineString,
+    Point,
+    Polygon,
+    Rect,
+};
+use postgres::types::{
+    FromSql,
+    IsNull,
+    ToSql,
+    Type,
+};
+use std::{
+    convert::TryFrom,
+    error::Error,
+};
+
+/// the types of PostGIS, which are the types of an extension without a fixed oid
+pub(crate) fn is_postgis(ty: &Type) -> bool { matches!(ty.name(), "geometry" | "geography") }
+
+pub(crate) fn to_sql(
+    geometry: &Geometry,
+    ty: &Type,
+    out: &mut BytesMut,
+) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+    if is_postgis(ty) {
+        out.put_slice(&geometry.to_ewkb());
+        return Ok(IsNull::No);
+    }
+    let unsupported =
+        || ConvertError::UnsupportedConversion(geometry.to_string(), ty.name().to_string());
+    let shape = geometry.geometry.clone();
+    match *ty {
+        Type::POINT => Point::try_from(shape).map_err(|_| unsupported())?.to_sql(ty, out),
+        Type::BOX => Rect::try_from(shape).map_err(|_| unsupported())?.to_sql(ty, out),
+        Type::PATH => LineString::try_from(shape).map_err(|_| unsupported())?.to_sql(ty, out),
+        Type::LSEG => {
+            let line = Line::try_from(shape).map_err(|_| unsupported())?;
+            put_coords(out, &[line.start, line.end]);
+            Ok(IsNull::No)
+        }
+        // the polygons of postgresql have no interiors and their points are not repeated
+        Type::POLYGON => {
+            let polygon = Polygon::try_from(shape).map_err(|_| unsupported())?;
+            if !polygon.interiors().is_empty() {
+                return Err(Box::new(unsupported()));
+            }
+            let mut points = polygon.exterior().0.as_slice();
+            if points.len() > 1 && points.first() == points.last() {
+                points = &points[..points.len() - 1];
+            }
+            out.put_i32(points.len() as i32);
+            put_coords(out, points);
+            Ok(IsNull::No)
+        }
+        _ => Err(Box::new(unsupported())),
+    }
+}
+
+fn put_coords(out: &mut BytesMut, coords: &[Coord<f64>]) {
+    for coord in coords {
+        out.put_f64(coord.x);
+        out.put_f64(coord.y);
+    }
+}
+
+pub(crate) fn from_sql(ty: &Type, raw: &[u8]) -> Result<Value, Box<dyn Error + Sync + Send>> {
+    let shape: geo_types::Geometry<f64> = match *ty {
+        Type::BOX => Rect::<f64>::from_sql(ty, raw)?.into(),
+        Type::PATH => LineString::<f64>::from_sql(ty, raw)?.into(),
+        Type::LSEG => {
+            let mut raw = raw;
+            let coords = read_coords(&mut raw, 2)?;
+            Line::new(coords[0], coords[1]).into()
+        }
+        Type::POLYGON => {
+            let mut raw = raw;
+            let len = raw.read_i32::<NetworkEndian>()?;
+            if len < 0 || len as usize * 16 != raw.len() {
+                return Err(format!("invalid polygon: {:?}", raw).into());
+            }
+            let coords = read_coords(&mut raw, len as usize)?;
+            Polygon::new(LineString(coords), vec![]).into()
+        }
+        _ => return Ok(Value::Geometry(Box::new(Geometry::from_wkb(raw)?))),
+    };
+    Ok(Value::Geometry(Box::new(Geometry::new(shape))))
+}
+
+fn read_coords(
+    raw: &mut &[u8],
+    len: usize,
+) -> Result<Vec<Coord<f64>>, Box<dyn Error + Sync + Send>> {
+    (0..len)
+        .map(|_| {
+            Ok(Coord {
+                x: raw.read_f64::<NetworkEndian>()?,
+                y: raw.read_f64::<NetworkEndian>()?,
+            })
+        })
+        .collect()
+}
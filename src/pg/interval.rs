@@ -9,8 +9,15 @@ use byteorder::{
     BigEndian,
     ReadBytesExt,
 };
+use bytes::{
+    BufMut,
+    BytesMut,
+};
 use postgres::types::{
+    to_sql_checked,
     FromSql,
+    IsNull,
+    ToSql,
     Type,
 };
 use std::error::Error;
@@ -71,3 +78,20 @@ impl<'b> FromSql<'b> for PgInterval {
 
     fn accepts(ty: &Type) -> bool { matches!(*ty, Type::INTERVAL) }
 }
+
+impl ToSql for PgInterval {
+    to_sql_checked!();
+
+    fn to_sql(
+        &self,
+        _ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + 'static + Sync + Send>> {
+        out.put_i64(self.microseconds);
+        out.put_i32(self.days);
+        out.put_i32(self.months);
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool { matches!(*ty, Type::INTERVAL) }
+}
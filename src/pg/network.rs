@@ -0,0 +1,106 @@
+//! The binary format of `inet` and `cidr`, the address family, the length of the prefix,
+//! whether it is a cidr and the bytes of the address, and of `macaddr`, its 6 bytes.
+use bytes::{
+    BufMut,
+    BytesMut,
+};
+use clia_rustorm_dao::{
+    IpNetwork,
+    MacAddress,
+};
+use postgres::types::{
+    to_sql_checked,
+    FromSql,
+    IsNull,
+    ToSql,
+    Type,
+};
+use std::{
+    convert::TryFrom,
+    error::Error,
+    net::IpAddr,
+};
+
+/// the address families of postgresql, not the ones of the platform
+const PGSQL_AF_INET: u8 = 2;
+const PGSQL_AF_INET6: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PgIpNetwork(pub IpNetwork);
+
+impl<'a> FromSql<'a> for PgIpNetwork {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let invalid = || format!("invalid {}: {:?}", ty.name(), raw);
+        let (family, prefix, bytes) = match raw {
+            [family, prefix, _is_cidr, len, bytes @ ..] if usize::from(*len) == bytes.len() => {
+                (*family, *prefix, bytes)
+            }
+            _ => return Err(invalid().into()),
+        };
+        let addr = match family {
+            PGSQL_AF_INET => IpAddr::from(<[u8; 4]>::try_from(bytes)?),
+            PGSQL_AF_INET6 => IpAddr::from(<[u8; 16]>::try_from(bytes)?),
+            _ => return Err(invalid().into()),
+        };
+        Ok(PgIpNetwork(IpNetwork::new(addr, prefix)?))
+    }
+
+    fn accepts(ty: &Type) -> bool { matches!(*ty, Type::INET | Type::CIDR) }
+}
+
+impl ToSql for PgIpNetwork {
+    to_sql_checked!();
+
+    fn to_sql(
+        &self,
+        ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + 'static + Sync + Send>> {
+        let PgIpNetwork(network) = self;
+        let family = match network.addr {
+            IpAddr::V4(_) => PGSQL_AF_INET,
+            IpAddr::V6(_) => PGSQL_AF_INET6,
+        };
+        out.put_u8(family);
+        out.put_u8(network.prefix);
+        out.put_u8(u8::from(*ty == Type::CIDR));
+        match network.addr {
+            IpAddr::V4(addr) => put_addr(out, &addr.octets()),
+            IpAddr::V6(addr) => put_addr(out, &addr.octets()),
+        }
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool { matches!(*ty, Type::INET | Type::CIDR) }
+}
+
+fn put_addr(out: &mut BytesMut, bytes: &[u8]) {
+    out.put_u8(bytes.len() as u8);
+    out.put_slice(bytes);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PgMacAddress(pub MacAddress);
+
+impl<'a> FromSql<'a> for PgMacAddress {
+    fn from_sql(_ty: &Type, raw: &'a [u8]) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        Ok(PgMacAddress(MacAddress(<[u8; 6]>::try_from(raw)?)))
+    }
+
+    fn accepts(ty: &Type) -> bool { *ty == Type::MACADDR }
+}
+
+impl ToSql for PgMacAddress {
+    to_sql_checked!();
+
+    fn to_sql(
+        &self,
+        _ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + 'static + Sync + Send>> {
+        out.put_slice(&(self.0).0);
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool { *ty == Type::MACADDR }
+}
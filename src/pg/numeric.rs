@@ -9,7 +9,10 @@ use bytes::{
     BufMut,
     BytesMut,
 };
-use std::error::Error;
+use std::{
+    convert::TryFrom,
+    error::Error,
+};
 
 use postgres::types::{
     to_sql_checked,
@@ -33,6 +36,7 @@ use bigdecimal::{
     BigDecimal,
 };
 
+use crate::error::ConvertError;
 use num_integer::Integer;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -227,8 +231,11 @@ impl From<BigDecimal> for PgNumeric {
     fn from(bigdecimal: BigDecimal) -> Self { (&bigdecimal).into() }
 }
 
-impl From<PgNumeric> for BigDecimal {
-    fn from(numeric: PgNumeric) -> Self {
+/// NaN has no BigDecimal representation
+impl TryFrom<PgNumeric> for BigDecimal {
+    type Error = ConvertError;
+
+    fn try_from(numeric: PgNumeric) -> Result<Self, Self::Error> {
         let (sign, weight, _, digits) = match numeric {
             PgNumeric::Positive {
                 weight,
@@ -240,7 +247,12 @@ impl From<PgNumeric> for BigDecimal {
                 scale,
                 digits,
             } => (Sign::Minus, weight, scale, digits),
-            PgNumeric::NaN => panic!("NaN is not (yet) supported in BigDecimal"),
+            PgNumeric::NaN => {
+                return Err(ConvertError::UnsupportedConversion(
+                    "NaN numeric".into(),
+                    "BigDecimal".into(),
+                ));
+            }
         };
         let mut result = BigUint::default();
         let count = digits.len() as i64;
@@ -252,6 +264,6 @@ impl From<PgNumeric> for BigDecimal {
         let correction_exp = 4 * (i64::from(weight) - count + 1);
         // FIXME: `scale` allows to drop some insignificant figures, which is currently unimplemented.
         // This means that e.g. PostgreSQL 0.01 will be interpreted as 0.0100
-        BigDecimal::new(BigInt::from_biguint(sign, result), -correction_exp)
+        Ok(BigDecimal::new(BigInt::from_biguint(sign, result), -correction_exp))
     }
 }
@@ -0,0 +1,94 @@
+//! The binary format of the postgresql ranges, the flags of the bounds followed by each bound
+//! that is neither unbounded nor null, prefixed with its length.
+use super::{
+    array::coerce,
+    OwnedPgValue,
+    PgValue,
+};
+use bytes::{
+    BufMut,
+    BytesMut,
+};
+use byteorder::{
+    NetworkEndian,
+    ReadBytesExt,
+};
+use clia_rustorm_dao::{
+    Range,
+    RangeBound,
+};
+use postgres::types::{
+    FromSql,
+    IsNull,
+    ToSql,
+    Type,
+};
+use std::error::Error;
+
+const RANGE_EMPTY: u8 = 0x01;
+const RANGE_LB_INC: u8 = 0x02;
+const RANGE_UB_INC: u8 = 0x04;
+const RANGE_LB_INF: u8 = 0x08;
+const RANGE_UB_INF: u8 = 0x10;
+
+/// encode the range whose bounds are of the `member` type
+pub(crate) fn to_sql(
+    range: &Range,
+    member: &Type,
+    out: &mut BytesMut,
+) -> Result<IsNull, Box<dyn Error + Sync + Send>> {
+    let (lower, upper) = match range {
+        Range::Empty => {
+            out.put_u8(RANGE_EMPTY);
+            return Ok(IsNull::No);
+        }
+        Range::Bounds(lower, upper) => (lower, upper),
+    };
+    let flags = |bound: &RangeBound, inclusive: u8, infinite: u8| {
+        match bound {
+            RangeBound::Inclusive(_) => inclusive,
+            RangeBound::Exclusive(_) => 0,
+            RangeBound::Unbounded => infinite,
+        }
+    };
+    out.put_u8(
+        flags(lower, RANGE_LB_INC, RANGE_LB_INF) | flags(upper, RANGE_UB_INC, RANGE_UB_INF),
+    );
+    for value in lower.value().into_iter().chain(upper.value()) {
+        let start = out.len();
+        out.put_i32(0);
+        PgValue(&coerce(value.clone(), member)).to_sql(member, out)?;
+        let len = (out.len() - start - 4) as i32;
+        out[start..start + 4].copy_from_slice(&len.to_be_bytes());
+    }
+    Ok(IsNull::No)
+}
+
+/// decode the range whose bounds are of the `member` type
+pub(crate) fn from_sql(member: &Type, raw: &[u8]) -> Result<Range, Box<dyn Error + Sync + Send>> {
+    let mut raw = raw;
+    let flags = raw.read_u8()?;
+    if flags & RANGE_EMPTY != 0 {
+        return Ok(Range::Empty);
+    }
+    let mut bound = |inclusive: u8, infinite: u8| -> Result<_, Box<dyn Error + Sync + Send>> {
+        if flags & infinite != 0 {
+            return Ok(RangeBound::Unbounded);
+        }
+        let len = raw.read_i32::<NetworkEndian>()? as usize;
+        if raw.len() < len {
+            return Err(format!("invalid {} range: {:?}", member.name(), raw).into());
+        }
+        let (value, rest) = raw.split_at(len);
+        let OwnedPgValue(value) = FromSql::from_sql(member, value)?;
+        raw = rest;
+        if flags & inclusive != 0 {
+            Ok(RangeBound::Inclusive(value))
+        } else {
+            Ok(RangeBound::Exclusive(value))
+        }
+    };
+    let lower = bound(RANGE_LB_INC, RANGE_LB_INF)?;
+    let upper = bound(RANGE_UB_INC, RANGE_UB_INF)?;
+    Ok(Range::Bounds(lower, upper))
+}
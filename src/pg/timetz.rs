@@ -0,0 +1,62 @@
+//! The binary format of `time with time zone`, the microseconds since midnight followed by the
+//! offset in seconds west of utc.
+use byteorder::{
+    NetworkEndian,
+    ReadBytesExt,
+};
+use bytes::{
+    BufMut,
+    BytesMut,
+};
+use chrono::{
+    FixedOffset,
+    NaiveTime,
+    Timelike,
+};
+use postgres::types::{
+    to_sql_checked,
+    FromSql,
+    IsNull,
+    ToSql,
+    Type,
+};
+use std::error::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PgTimeTz(pub NaiveTime, pub FixedOffset);
+
+impl<'a> FromSql<'a> for PgTimeTz {
+    fn from_sql(_ty: &Type, bytes: &'a [u8]) -> Result<Self, Box<dyn Error + Send + Sync>> {
+        let mut bytes = bytes;
+        let micros = bytes.read_i64::<NetworkEndian>()?;
+        let west = bytes.read_i32::<NetworkEndian>()?;
+        let secs = (micros / 1_000_000) as u32;
+        let nanos = (micros % 1_000_000 * 1000) as u32;
+        let time = NaiveTime::from_num_seconds_from_midnight_opt(secs, nanos)
+            .ok_or_else(|| format!("invalid time: {} microseconds", micros))?;
+        let offset =
+            FixedOffset::west_opt(west).ok_or_else(|| format!("invalid offset: {}", west))?;
+        Ok(PgTimeTz(time, offset))
+    }
+
+    fn accepts(ty: &Type) -> bool { *ty == Type::TIMETZ }
+}
+
+impl ToSql for PgTimeTz {
+    to_sql_checked!();
+
+    fn to_sql(
+        &self,
+        _ty: &Type,
+        out: &mut BytesMut,
+    ) -> Result<IsNull, Box<dyn Error + 'static + Sync + Send>> {
+        let PgTimeTz(time, offset) = self;
+        let micros = i64::from(time.num_seconds_from_midnight()) * 1_000_000
+            + i64::from(time.nanosecond() / 1000);
+        out.put_i64(micros);
+        out.put_i32(-offset.local_minus_utc());
+        Ok(IsNull::No)
+    }
+
+    fn accepts(ty: &Type) -> bool { *ty == Type::TIMETZ }
+}
@@ -1,5 +1,6 @@
 use crate::{
     error::ParseError,
+    identifier::Dialect,
     Database,
 };
 use cfg_if::cfg_if;
@@ -58,6 +59,32 @@ impl std::ops::DerefMut for DBPlatform {
     }
 }
 
+impl DBPlatform {
+    /// the dialect used to quote the identifiers in the statements of this platform
+    pub fn dialect(&self) -> Dialect {
+        match *self {
+            #[cfg(feature = "with-postgres")]
+            DBPlatform::Postgres(_) => Dialect::Postgres,
+            #[cfg(feature = "with-sqlite")]
+            DBPlatform::Sqlite(_) => Dialect::Sqlite,
+            #[cfg(feature = "with-mysql")]
+            DBPlatform::Mysql(_) => Dialect::Mysql,
+        }
+    }
+
+    /// the prefix of a statement to show its query plan
+    pub(crate) fn explain_prefix(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "with-postgres")]
+            DBPlatform::Postgres(_) => "EXPLAIN",
+            #[cfg(feature = "with-sqlite")]
+            DBPlatform::Sqlite(_) => "EXPLAIN QUERY PLAN",
+            #[cfg(feature = "with-mysql")]
+            DBPlatform::Mysql(_) => "EXPLAIN",
+        }
+    }
+}
+
 pub(crate) enum Platform {
     #[cfg(feature = "with-postgres")]
     Postgres,
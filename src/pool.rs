@@ -9,7 +9,7 @@ cfg_if! {if #[cfg(feature = "with-postgres")]{
 }}
 cfg_if! {if #[cfg(feature = "with-sqlite")]{
     use r2d2_sqlite::SqliteConnectionManager;
-    use crate::sqlite::{self, SqliteDB};
+    use crate::sqlite::{self, Decoding, DecimalMode, SqliteDB};
 }}
 cfg_if! {if #[cfg(feature = "with-mysql")]{
     use r2d2_mysql::MysqlConnectionManager;
@@ -17,11 +17,19 @@ cfg_if! {if #[cfg(feature = "with-mysql")]{
 }}
 
 use crate::{
+    cache::MetadataCache,
+    observer::{
+        QueryObserver,
+        QueryObservers,
+        Redaction,
+        SlowQueryLog,
+    },
     error::{
         ConnectError,
         ParseError,
     },
     platform::Platform,
+    type_registry::TypeRegistry,
     DBPlatform,
     DaoManager,
     DbError,
@@ -32,10 +40,23 @@ use postgres::NoTls;
 use std::{
     collections::BTreeMap,
     convert::TryFrom,
+    sync::Arc,
+    time::Duration,
 };
 
 #[derive(Default)]
-pub struct Pool(BTreeMap<String, ConnPool>);
+pub struct Pool {
+    pools: BTreeMap<String, ConnPool>,
+    metadata_cache: Option<MetadataCache>,
+    query_observers: QueryObservers,
+    statement_cache_capacity: Option<usize>,
+    query_timeout: Option<Duration>,
+    type_registry: TypeRegistry,
+    #[cfg(feature = "with-sqlite")]
+    sqlite_decimal_mode: DecimalMode,
+    #[cfg(feature = "with-sqlite")]
+    sqlite_decoding: Decoding,
+}
 pub enum ConnPool {
     #[cfg(feature = "with-postgres")]
     PoolPg(r2d2::Pool<PostgresConnectionManager<NoTls>>),
@@ -57,6 +78,61 @@ pub enum PooledConn {
 impl Pool {
     pub fn new() -> Self { Default::default() }
 
+    /// cache the table definitions introspected by the entity managers of this pool.
+    /// The same cache can be set to several pools to share the cached entries.
+    pub fn set_metadata_cache(&mut self, cache: MetadataCache) {
+        self.metadata_cache = Some(cache);
+    }
+
+    /// the metadata cache of this pool, if enabled
+    pub fn metadata_cache(&self) -> Option<&MetadataCache> { self.metadata_cache.as_ref() }
+
+    /// notify this observer of the statements executed by the entity managers and
+    /// dao managers created from this pool
+    pub fn add_query_observer<O>(&mut self, observer: O)
+    where
+        O: QueryObserver + 'static,
+    {
+        self.query_observers.add(Arc::new(observer));
+    }
+
+    /// set how the parameter values are exposed to the query observers,
+    /// the values are redacted by default
+    pub fn set_param_redaction(&mut self, redaction: Redaction) {
+        self.query_observers.set_redaction(redaction);
+    }
+
+    /// log the statements taking longer than the threshold of the slow query log,
+    /// None to disable it
+    pub fn set_slow_query_log(&mut self, slow_query_log: Option<SlowQueryLog>) {
+        self.query_observers.set_slow_query_log(slow_query_log);
+    }
+
+    /// set the number of prepared statements cached by each connection, 0 to disable the cache.
+    /// Defaults to `statement_cache::DEFAULT_CAPACITY`
+    pub fn set_statement_cache_capacity(&mut self, capacity: usize) {
+        self.statement_cache_capacity = Some(capacity);
+    }
+
+    /// cancel the statements running longer than the timeout, this is the default timeout of
+    /// the entity managers and dao managers created from this pool
+    pub fn set_query_timeout(&mut self, timeout: Option<Duration>) { self.query_timeout = timeout; }
+
+    /// decode and encode the values of the custom types with the conversions of this registry,
+    /// mysql ignores the decoders
+    pub fn set_type_registry(&mut self, registry: TypeRegistry) { self.type_registry = registry; }
+
+    /// set how the decimals are stored in the sqlite databases, as a REAL by default
+    #[cfg(feature = "with-sqlite")]
+    pub fn set_sqlite_decimal_mode(&mut self, mode: DecimalMode) {
+        self.sqlite_decimal_mode = mode;
+    }
+
+    /// set whether the values of the sqlite databases which can not be decoded into the type
+    /// declared for their column are an error or kept as they are stored, the default
+    #[cfg(feature = "with-sqlite")]
+    pub fn set_sqlite_decoding(&mut self, decoding: Decoding) { self.sqlite_decoding = decoding; }
+
     /// ensure that a connection pool for this db_url exist
     ///
     /// Note: if that db_url already has an equivalent connection pool, this doesn't do anything
@@ -68,26 +144,26 @@ impl Pool {
                 match platform {
                     #[cfg(feature = "with-postgres")]
                     Platform::Postgres => {
-                        if self.0.get(db_url).is_none() {
+                        if self.pools.get(db_url).is_none() {
                             let pool_pg = pg::init_pool(db_url)?;
-                            self.0.insert(db_url.to_string(), ConnPool::PoolPg(pool_pg));
+                            self.pools.insert(db_url.to_string(), ConnPool::PoolPg(pool_pg));
                         }
                         Ok(())
                     }
                     #[cfg(feature = "with-sqlite")]
                     Platform::Sqlite(path) => {
                         info!("matched sqlite");
-                        if self.0.get(db_url).is_none() {
+                        if self.pools.get(db_url).is_none() {
                             let pool_sq = sqlite::init_pool(&path)?;
-                            self.0.insert(db_url.to_string(), ConnPool::PoolSq(pool_sq));
+                            self.pools.insert(db_url.to_string(), ConnPool::PoolSq(pool_sq));
                         }
                         Ok(())
                     }
                     #[cfg(feature = "with-mysql")]
                     Platform::Mysql => {
-                        if self.0.get(db_url).is_none() {
+                        if self.pools.get(db_url).is_none() {
                             let pool_my = my::init_pool(db_url)?;
-                            self.0.insert(db_url.to_string(), ConnPool::PoolMy(pool_my));
+                            self.pools.insert(db_url.to_string(), ConnPool::PoolMy(pool_my));
                         }
                         Ok(())
                     }
@@ -110,7 +186,7 @@ impl Pool {
                 match platform {
                     #[cfg(feature = "with-postgres")]
                     Platform::Postgres => {
-                        let conn: Option<&ConnPool> = self.0.get(db_url);
+                        let conn: Option<&ConnPool> = self.pools.get(db_url);
                         if let Some(conn) = conn {
                             Ok(conn)
                         } else {
@@ -120,7 +196,7 @@ impl Pool {
                     #[cfg(feature = "with-sqlite")]
                     Platform::Sqlite(_path) => {
                         info!("getting sqlite pool");
-                        let conn: Option<&ConnPool> = self.0.get(db_url);
+                        let conn: Option<&ConnPool> = self.pools.get(db_url);
                         if let Some(conn) = conn {
                             Ok(conn)
                         } else {
@@ -129,7 +205,7 @@ impl Pool {
                     }
                     #[cfg(feature = "with-mysql")]
                     Platform::Mysql => {
-                        let conn: Option<&ConnPool> = self.0.get(db_url);
+                        let conn: Option<&ConnPool> = self.pools.get(db_url);
                         if let Some(conn) = conn {
                             Ok(conn)
                         } else {
@@ -181,7 +257,7 @@ impl Pool {
     /// Dao, Rows and Value
     pub fn dm(&mut self, db_url: &str) -> Result<DaoManager, DbError> {
         let db = self.db(db_url)?;
-        Ok(DaoManager(db))
+        Ok(DaoManager(db, self.query_observers.clone(), self.query_timeout))
     }
 
     /// get the pool for this specific db_url, create one if it doesn't have yet.
@@ -193,7 +269,7 @@ impl Pool {
                 match platform {
                     #[cfg(feature = "with-postgres")]
                     Platform::Postgres => {
-                        let conn: Option<&ConnPool> = self.0.get(db_url);
+                        let conn: Option<&ConnPool> = self.pools.get(db_url);
                         if let Some(conn) = conn {
                             Ok(conn)
                         } else {
@@ -203,7 +279,7 @@ impl Pool {
                     #[cfg(feature = "with-sqlite")]
                     Platform::Sqlite(_path) => {
                         info!("getting sqlite pool");
-                        let conn: Option<&ConnPool> = self.0.get(db_url);
+                        let conn: Option<&ConnPool> = self.pools.get(db_url);
                         if let Some(conn) = conn {
                             Ok(conn)
                         } else {
@@ -212,7 +288,7 @@ impl Pool {
                     }
                     #[cfg(feature = "with-mysql")]
                     Platform::Mysql => {
-                        let conn: Option<&ConnPool> = self.0.get(db_url);
+                        let conn: Option<&ConnPool> = self.pools.get(db_url);
                         if let Some(conn) = conn {
                             Ok(conn)
                         } else {
@@ -264,24 +340,44 @@ impl Pool {
     pub fn db(&mut self, db_url: &str) -> Result<DBPlatform, DbError> {
         let pooled_conn = self.connect_mut(db_url)?;
 
-        match pooled_conn {
+        let mut db = match pooled_conn {
             #[cfg(feature = "with-postgres")]
             PooledConn::PooledPg(pooled_pg) => {
-                Ok(DBPlatform::Postgres(Box::new(PostgresDB(*pooled_pg))))
+                DBPlatform::Postgres(Box::new(PostgresDB(*pooled_pg, TypeRegistry::default())))
             }
             #[cfg(feature = "with-sqlite")]
             PooledConn::PooledSq(pooled_sq) => {
-                Ok(DBPlatform::Sqlite(Box::new(SqliteDB(*pooled_sq))))
+                DBPlatform::Sqlite(Box::new(SqliteDB(
+                    *pooled_sq,
+                    self.sqlite_decimal_mode,
+                    self.sqlite_decoding,
+                    TypeRegistry::default(),
+                )))
             }
             #[cfg(feature = "with-mysql")]
-            PooledConn::PooledMy(pooled_my) => Ok(DBPlatform::Mysql(Box::new(MysqlDB(*pooled_my)))),
+            PooledConn::PooledMy(pooled_my) => {
+                DBPlatform::Mysql(Box::new(MysqlDB(*pooled_my, TypeRegistry::default())))
+            }
+        };
+        if let Some(capacity) = self.statement_cache_capacity {
+            db.set_statement_cache_capacity(capacity);
+        }
+        if !self.type_registry.is_empty() {
+            db.set_type_registry(self.type_registry.clone());
         }
+        Ok(db)
     }
 
     /// return an entity manager which provides a higher level api
     pub fn em(&mut self, db_url: &str) -> Result<EntityManager, DbError> {
         let db = self.db(db_url)?;
-        Ok(EntityManager(db))
+        let cache = self.metadata_cache.as_ref().map(|cache| cache.for_db(db_url));
+        Ok(EntityManager(
+            db,
+            cache,
+            self.query_observers.clone(),
+            self.query_timeout,
+        ))
     }
 }
 
@@ -0,0 +1,491 @@
+//! The dependency graph of the tables, derived from their foreign keys.
+//!
+//! A table depends on the tables its foreign keys refer to, so those have to be
+//! populated first and emptied last.
+use crate::{
+    table::ForeignKey,
+    TableDef,
+    TableName,
+};
+use std::collections::{
+    BTreeSet,
+    HashMap,
+    VecDeque,
+};
+
+/// the foreign key of table `from` referring to table `to`
+#[derive(Debug)]
+struct Edge {
+    from: usize,
+    to: usize,
+    foreign_key: ForeignKey,
+}
+
+/// A group of tables which refer to each other through their foreign keys,
+/// a table with a foreign key to itself is a cycle of a single table.
+#[derive(Debug, PartialEq)]
+pub struct Cycle<'a> {
+    pub tables: Vec<&'a TableName>,
+    /// the foreign keys which, when declared `DEFERRABLE INITIALLY DEFERRED`, allows the
+    /// tables in this cycle to be populated in the order given by `insert_order`
+    pub deferrable: Vec<(&'a TableName, &'a ForeignKey)>,
+}
+
+#[derive(Debug)]
+pub struct SchemaGraph {
+    tables: Vec<TableName>,
+    index: HashMap<TableName, usize>,
+    edges: Vec<Edge>,
+    /// the position of the edges from each table
+    outgoing: Vec<Vec<usize>>,
+    /// the position of the edges to each table
+    incoming: Vec<Vec<usize>>,
+    /// the edges which closes a cycle in a depth first traversal of the graph,
+    /// removing them makes the graph acyclic
+    back_edges: BTreeSet<usize>,
+}
+
+impl SchemaGraph {
+    /// build the graph of these tables, foreign keys to tables which are not
+    /// in the list are ignored
+    pub fn new(tables: &[TableDef]) -> Self {
+        let table_names: Vec<TableName> = tables.iter().map(|t| t.name.clone()).collect();
+        let index: HashMap<TableName, usize> = table_names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (name.clone(), i))
+            .collect();
+        let mut edges = vec![];
+        for (from, table) in tables.iter().enumerate() {
+            for foreign_key in table.get_foreign_keys() {
+                if let Some(&to) = index.get(&foreign_key.foreign_table) {
+                    edges.push(Edge {
+                        from,
+                        to,
+                        foreign_key: foreign_key.clone(),
+                    });
+                }
+            }
+        }
+        let mut outgoing = vec![vec![]; table_names.len()];
+        let mut incoming = vec![vec![]; table_names.len()];
+        for (i, edge) in edges.iter().enumerate() {
+            outgoing[edge.from].push(i);
+            incoming[edge.to].push(i);
+        }
+        let mut graph = SchemaGraph {
+            tables: table_names,
+            index,
+            edges,
+            outgoing,
+            incoming,
+            back_edges: BTreeSet::new(),
+        };
+        graph.back_edges = graph.find_back_edges();
+        graph
+    }
+
+    /// the tables in this graph
+    pub fn tables(&self) -> &[TableName] { &self.tables }
+
+    /// the tables referred to by the foreign keys of this table
+    pub fn referenced_tables(&self, table_name: &TableName) -> Vec<&TableName> {
+        self.neighbors(table_name, &self.outgoing, |edge| edge.to)
+    }
+
+    /// the tables which have a foreign key referring to this table
+    pub fn referencing_tables(&self, table_name: &TableName) -> Vec<&TableName> {
+        self.neighbors(table_name, &self.incoming, |edge| edge.from)
+    }
+
+    /// all the tables this table depends on, directly or through other tables
+    pub fn all_referenced_tables(&self, table_name: &TableName) -> Vec<&TableName> {
+        self.reachable(table_name, &self.outgoing, |edge| edge.to)
+    }
+
+    /// all the tables which depend on this table, directly or through other tables
+    pub fn all_referencing_tables(&self, table_name: &TableName) -> Vec<&TableName> {
+        self.reachable(table_name, &self.incoming, |edge| edge.from)
+    }
+
+    /// the order the tables can be populated such that the referred tables
+    /// are inserted before the tables referring to them.
+    ///
+    /// If the graph has cycles, the `deferrable` foreign keys of the cycles are not
+    /// satisfied by this order.
+    pub fn insert_order(&self) -> Vec<&TableName> {
+        let mut dependencies = vec![0; self.tables.len()];
+        let mut dependents: Vec<Vec<usize>> = vec![vec![]; self.tables.len()];
+        for (i, edge) in self.edges.iter().enumerate() {
+            if edge.from != edge.to && !self.back_edges.contains(&i) {
+                dependencies[edge.from] += 1;
+                dependents[edge.to].push(edge.from);
+            }
+        }
+        // the tables are taken in their original order whenever possible
+        let mut ready: BTreeSet<usize> =
+            (0..self.tables.len()).filter(|&i| dependencies[i] == 0).collect();
+        let mut order = Vec::with_capacity(self.tables.len());
+        while let Some(&i) = ready.iter().next() {
+            ready.remove(&i);
+            order.push(&self.tables[i]);
+            for &dependent in &dependents[i] {
+                dependencies[dependent] -= 1;
+                if dependencies[dependent] == 0 {
+                    ready.insert(dependent);
+                }
+            }
+        }
+        order
+    }
+
+    /// the order the tables can be emptied such that the tables referring
+    /// to a table are deleted before it.
+    pub fn delete_order(&self) -> Vec<&TableName> {
+        let mut order = self.insert_order();
+        order.reverse();
+        order
+    }
+
+    /// the groups of tables which refer to each other
+    pub fn cycles(&self) -> Vec<Cycle<'_>> {
+        let components = self.strongly_connected_components();
+        let mut component_of = vec![0; self.tables.len()];
+        for (c, component) in components.iter().enumerate() {
+            for &i in component {
+                component_of[i] = c;
+            }
+        }
+        components
+            .iter()
+            .enumerate()
+            .filter(|(_, component)| {
+                component.len() > 1
+                    || self.outgoing[component[0]]
+                        .iter()
+                        .any(|&i| self.edges[i].to == component[0])
+            })
+            .map(|(c, component)| {
+                let mut tables: Vec<usize> = component.to_vec();
+                tables.sort_unstable();
+                let mut deferrable: Vec<usize> = tables
+                    .iter()
+                    .flat_map(|&table| self.outgoing[table].iter().copied())
+                    .filter(|i| {
+                        let edge = &self.edges[*i];
+                        component_of[edge.to] == c
+                            && (edge.from == edge.to || self.back_edges.contains(i))
+                    })
+                    .collect();
+                deferrable.sort_unstable();
+                let deferrable = deferrable
+                    .into_iter()
+                    .map(|i| (&self.tables[self.edges[i].from], &self.edges[i].foreign_key))
+                    .collect();
+                Cycle {
+                    tables: tables.into_iter().map(|i| &self.tables[i]).collect(),
+                    deferrable,
+                }
+            })
+            .collect()
+    }
+
+    /// returns true if some of the tables refer to each other
+    pub fn has_cycles(&self) -> bool {
+        !self.back_edges.is_empty() || self.edges.iter().any(|edge| edge.from == edge.to)
+    }
+
+    fn neighbors<F>(
+        &self,
+        table_name: &TableName,
+        adjacent: &[Vec<usize>],
+        target: F,
+    ) -> Vec<&TableName>
+    where
+        F: Fn(&Edge) -> usize,
+    {
+        let start = match self.index.get(table_name) {
+            Some(&start) => start,
+            None => return vec![],
+        };
+        let found: BTreeSet<usize> =
+            adjacent[start].iter().map(|&i| target(&self.edges[i])).collect();
+        found.into_iter().map(|i| &self.tables[i]).collect()
+    }
+
+    fn reachable<F>(
+        &self,
+        table_name: &TableName,
+        adjacent: &[Vec<usize>],
+        target: F,
+    ) -> Vec<&TableName>
+    where
+        F: Fn(&Edge) -> usize,
+    {
+        let start = match self.index.get(table_name) {
+            Some(&start) => start,
+            None => return vec![],
+        };
+        let mut visited = vec![false; self.tables.len()];
+        let mut found = BTreeSet::new();
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+        while let Some(current) = queue.pop_front() {
+            for &i in &adjacent[current] {
+                let to = target(&self.edges[i]);
+                if !visited[to] {
+                    visited[to] = true;
+                    found.insert(to);
+                    queue.push_back(to);
+                }
+            }
+        }
+        found.into_iter().map(|i| &self.tables[i]).collect()
+    }
+
+    /// depth first traversal, an edge to a table which is still being traversed
+    /// closes a cycle
+    fn find_back_edges(&self) -> BTreeSet<usize> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum State {
+            New,
+            Visiting,
+            Done,
+        }
+        let mut state = vec![State::New; self.tables.len()];
+        let mut back_edges = BTreeSet::new();
+        for root in 0..self.tables.len() {
+            if state[root] != State::New {
+                continue;
+            }
+            // the table and the position of the next edge to follow
+            let mut stack = vec![(root, 0)];
+            state[root] = State::Visiting;
+            while let Some((current, next)) = stack.pop() {
+                match self.outgoing[current].get(next) {
+                    Some(&i) => {
+                        stack.push((current, next + 1));
+                        let to = self.edges[i].to;
+                        match state[to] {
+                            State::New => {
+                                state[to] = State::Visiting;
+                                stack.push((to, 0));
+                            }
+                            State::Visiting if to != current => {
+                                back_edges.insert(i);
+                            }
+                            _ => (),
+                        }
+                    }
+                    None => state[current] = State::Done,
+                }
+            }
+        }
+        back_edges
+    }
+
+    /// group the tables which are reachable from each other, with the algorithm of Tarjan
+    fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+        const UNVISITED: usize = usize::MAX;
+        let mut order = vec![UNVISITED; self.tables.len()];
+        // the earliest visited table reachable from each table
+        let mut lowest = vec![UNVISITED; self.tables.len()];
+        let mut on_stack = vec![false; self.tables.len()];
+        let mut stack = vec![];
+        let mut visited = 0;
+        let mut components = vec![];
+        for root in 0..self.tables.len() {
+            if order[root] != UNVISITED {
+                continue;
+            }
+            // the table and the position of the next edge to follow
+            let mut calls = vec![(root, 0)];
+            while let Some((current, next)) = calls.pop() {
+                if next == 0 {
+                    order[current] = visited;
+                    lowest[current] = visited;
+                    visited += 1;
+                    stack.push(current);
+                    on_stack[current] = true;
+                }
+                match self.outgoing[current].get(next) {
+                    Some(&i) => {
+                        calls.push((current, next + 1));
+                        let to = self.edges[i].to;
+                        if order[to] == UNVISITED {
+                            calls.push((to, 0));
+                        } else if on_stack[to] {
+                            lowest[current] = lowest[current].min(order[to]);
+                        }
+                    }
+                    None => {
+                        if let Some(&(caller, _)) = calls.last() {
+                            lowest[caller] = lowest[caller].min(lowest[current]);
+                        }
+                        if lowest[current] == order[current] {
+                            let mut component = vec![];
+                            while let Some(table) = stack.pop() {
+                                on_stack[table] = false;
+                                component.push(table);
+                                if table == current {
+                                    break;
+                                }
+                            }
+                            component.sort_unstable();
+                            components.push(component);
+                        }
+                    }
+                }
+            }
+        }
+        components.sort_unstable();
+        components
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        table::TableKey,
+        ColumnName,
+    };
+
+    fn table(name: &str, references: &[&str]) -> TableDef {
+        TableDef {
+            name: TableName::from(name),
+            comment: None,
+            columns: vec![],
+            is_view: false,
+            table_key: references
+                .iter()
+                .map(|foreign| {
+                    TableKey::ForeignKey(ForeignKey {
+                        name: Some(format!("{}_{}_fkey", name, foreign)),
+                        columns: vec![ColumnName::from(&format!("{}_id", foreign))],
+                        foreign_table: TableName::from(foreign),
+                        referred_columns: vec![ColumnName::from(&format!("{}_id", foreign))],
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    fn names(tables: &[&TableName]) -> Vec<String> {
+        tables.iter().map(|t| t.complete_name()).collect()
+    }
+
+    fn sakila() -> SchemaGraph {
+        SchemaGraph::new(&[
+            table("film_actor", &["film", "actor"]),
+            table("film", &["language"]),
+            table("actor", &[]),
+            table("language", &[]),
+            table("inventory", &["film", "store"]),
+            table("store", &[]),
+        ])
+    }
+
+    #[test]
+    fn insert_and_delete_order() {
+        let graph = sakila();
+        assert!(!graph.has_cycles());
+        assert_eq!(names(&graph.insert_order()), vec![
+            "actor",
+            "language",
+            "film",
+            "film_actor",
+            "store",
+            "inventory"
+        ]);
+        assert_eq!(names(&graph.delete_order()), vec![
+            "inventory",
+            "store",
+            "film_actor",
+            "film",
+            "language",
+            "actor"
+        ]);
+    }
+
+    #[test]
+    fn reverse_lookup_and_closure() {
+        let graph = sakila();
+        let film = TableName::from("film");
+        let language = TableName::from("language");
+        assert_eq!(names(&graph.referenced_tables(&film)), vec!["language"]);
+        assert_eq!(names(&graph.referencing_tables(&film)), vec![
+            "film_actor",
+            "inventory"
+        ]);
+        assert_eq!(names(&graph.referencing_tables(&language)), vec!["film"]);
+        assert_eq!(names(&graph.all_referencing_tables(&language)), vec![
+            "film_actor",
+            "film",
+            "inventory"
+        ]);
+        assert_eq!(
+            names(&graph.all_referenced_tables(&TableName::from("film_actor"))),
+            vec!["film", "actor", "language"]
+        );
+        assert!(graph
+            .referenced_tables(&TableName::from("no_such_table"))
+            .is_empty());
+    }
+
+    #[test]
+    fn cycles_with_deferrable_suggestion() {
+        let graph = SchemaGraph::new(&[
+            table("store", &["staff", "address"]),
+            table("staff", &["store", "staff"]),
+            table("address", &[]),
+            table("payment", &["staff"]),
+        ]);
+        assert!(graph.has_cycles());
+        let cycles = graph.cycles();
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(names(&cycles[0].tables), vec!["store", "staff"]);
+        let deferrable: Vec<_> = cycles[0]
+            .deferrable
+            .iter()
+            .map(|(table, fk)| (table.name.as_str(), fk.foreign_table.name.as_str()))
+            .collect();
+        assert_eq!(deferrable, vec![("staff", "store"), ("staff", "staff")]);
+        // only the deferrable foreign keys are not satisfied
+        assert_eq!(names(&graph.insert_order()), vec![
+            "staff", "address", "store", "payment"
+        ]);
+    }
+
+    #[test]
+    fn self_reference_is_a_cycle() {
+        let graph = SchemaGraph::new(&[table("category", &["category"])]);
+        assert!(graph.has_cycles());
+        let cycles = graph.cycles();
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(names(&cycles[0].tables), vec!["category"]);
+        assert_eq!(cycles[0].deferrable.len(), 1);
+        assert_eq!(names(&graph.insert_order()), vec!["category"]);
+    }
+
+    #[test]
+    fn cycles_of_a_large_schema() {
+        let names: Vec<String> = (0..5000).map(|i| format!("t{}", i)).collect();
+        // t0 -> t1 -> .. -> t2499 -> t0 is a cycle, the other tables are a chain
+        let tables: Vec<TableDef> = (0..names.len())
+            .map(|i| {
+                match i {
+                    2499 => table(&names[i], &[&names[0]]),
+                    4999 => table(&names[i], &[]),
+                    _ => table(&names[i], &[&names[i + 1]]),
+                }
+            })
+            .collect();
+        let graph = SchemaGraph::new(&tables);
+        let cycles = graph.cycles();
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].tables.len(), 2500);
+        assert_eq!(cycles[0].deferrable.len(), 1);
+        assert_eq!(graph.insert_order().len(), 5000);
+    }
+}
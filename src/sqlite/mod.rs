@@ -13,22 +13,35 @@ use crate::{
     },
     common,
     error::{
+        ConvertError,
         DataOpError,
         PlatformError,
     },
+    identifier::Dialect,
+    statement_cache::{
+        self,
+        StatementCacheStats,
+    },
     table::{
         ForeignKey,
         Key,
         SchemaContent,
         TableKey,
     },
-    types::SqlType,
+    timeout,
+    type_registry::TypeRegistry,
+    types::{
+        GeometryType,
+        SqlType,
+    },
     util,
     ColumnName,
+    DataError,
     Database,
     DatabaseName,
     DbError,
     FromDao,
+    Geometry,
     Rows,
     TableDef,
     TableName,
@@ -41,6 +54,7 @@ use r2d2::{
     self,
     ManageConnection,
 };
+use std::time::Duration;
 use thiserror::Error;
 use uuid::Uuid;
 
@@ -48,7 +62,10 @@ pub fn init_pool(
     db_url: &str,
 ) -> Result<r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, SqliteError> {
     info!("initializing pool: {}", db_url);
-    let manager = r2d2_sqlite::SqliteConnectionManager::file(db_url);
+    let manager = r2d2_sqlite::SqliteConnectionManager::file(db_url).with_init(|conn| {
+        conn.set_prepared_statement_cache_capacity(statement_cache::DEFAULT_CAPACITY);
+        Ok(())
+    });
     let pool = r2d2::Pool::new(manager)?;
     Ok(pool)
 }
@@ -60,11 +77,35 @@ pub fn test_connection(db_url: &str) -> Result<(), SqliteError> {
     Ok(())
 }
 
-pub struct SqliteDB(pub r2d2::PooledConnection<r2d2_sqlite::SqliteConnectionManager>);
+pub struct SqliteDB(
+    pub r2d2::PooledConnection<r2d2_sqlite::SqliteConnectionManager>,
+    pub(crate) DecimalMode,
+    pub(crate) Decoding,
+    pub(crate) TypeRegistry,
+);
 
-fn to_sq_value(val: &Value) -> rusqlite::types::Value {
+/// How the decimals are stored, sqlite has no decimal type
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DecimalMode {
+    /// as a REAL, which keeps about 15 significant digits
+    #[default]
+    Real,
+    /// as the text of the decimal, which keeps every digit and the scale when the column is
+    /// declared as `TEXT`. Sqlite still converts the text stored in the columns declared as
+    /// `NUMERIC` or `DECIMAL` into a number. The text is compared and sorted as text, use
+    /// `CAST(amount AS REAL)` to compare the amounts as numbers.
+    Text,
+}
+
+/// the busy timeout set by rusqlite when opening a connection
+const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn to_sq_value(
+    val: &Value,
+    decimal_mode: DecimalMode,
+) -> Result<rusqlite::types::Value, ConvertError> {
     use bigdecimal::num_traits::ToPrimitive;
-    match *val {
+    let sq_value = match *val {
         Value::Text(ref v) => rusqlite::types::Value::Text(v.to_owned()),
         Value::Bool(v) => rusqlite::types::Value::Integer(if v { 1 } else { 0 }),
         Value::Tinyint(v) => rusqlite::types::Value::Integer(i64::from(v)),
@@ -74,30 +115,220 @@ fn to_sq_value(val: &Value) -> rusqlite::types::Value {
 
         Value::Float(v) => rusqlite::types::Value::Real(f64::from(v)),
         Value::Double(v) => rusqlite::types::Value::Real(v),
+        Value::BigDecimal(ref v) if decimal_mode == DecimalMode::Text => {
+            rusqlite::types::Value::Text(v.to_string())
+        }
         Value::BigDecimal(ref v) => {
             match v.to_f64() {
-                Some(v) => rusqlite::types::Value::Real(v as f64),
-                None => panic!("unable to convert bigdecimal"),
+                Some(v) => rusqlite::types::Value::Real(v),
+                None => {
+                    return Err(ConvertError::UnsupportedConversion(
+                        v.to_string(),
+                        "REAL".to_string(),
+                    ))
+                }
             }
         }
         Value::Blob(ref v) => rusqlite::types::Value::Blob(v.clone()),
         Value::Char(v) => rusqlite::types::Value::Text(format!("{}", v)),
-        Value::Json(ref v) => rusqlite::types::Value::Text(v.clone()),
+        Value::Json(ref v) => rusqlite::types::Value::Text(v.to_string()),
         Value::Uuid(ref v) => rusqlite::types::Value::Text(v.to_string()),
         Value::Date(ref v) => rusqlite::types::Value::Text(v.to_string()),
+        Value::Time(ref v) => rusqlite::types::Value::Text(v.to_string()),
+        // the intervals are stored as ISO 8601 durations
+        Value::Interval(ref v) => rusqlite::types::Value::Text(v.to_string()),
         Value::DateTime(ref v) => rusqlite::types::Value::Text(v.to_string()),
+        // the values with an offset are stored in RFC 3339
+        Value::Timestamp(ref v) => rusqlite::types::Value::Text(v.to_rfc3339()),
+        Value::TimeTz(..) | Value::TimestampTz(_) => rusqlite::types::Value::Text(val.to_string()),
+        // sqlite has no arrays, they are stored as json
+        Value::Array(ref v) => rusqlite::types::Value::Text(v.to_json()),
+        Value::Money(v) => rusqlite::types::Value::Integer(v),
+        // the addresses, bits and ranges in their text format, the hstores as a json object
+        Value::Inet(_)
+        | Value::Cidr(_)
+        | Value::MacAddr(_)
+        | Value::Bit(_)
+        | Value::Range(_)
+        | Value::Hstore(_) => rusqlite::types::Value::Text(val.to_string()),
+        // the geometries are stored as WKT, prefixed with their srid
+        Value::Point(v) => rusqlite::types::Value::Text(Geometry::new(v).to_string()),
+        Value::Geometry(ref v) => rusqlite::types::Value::Text(v.to_string()),
+        Value::Custom(ref v) => return to_sq_value(&v.value, decimal_mode),
+        // sqlite has no composite types, the fields are stored as json
+        Value::Composite(ref v) => {
+            let json = serde_json::to_string(v)
+                .map_err(|e| ConvertError::UnsupportedConversion(e.to_string(), "json".into()))?;
+            rusqlite::types::Value::Text(json)
+        }
         Value::Nil => rusqlite::types::Value::Null,
-        _ => panic!("not yet handled: {:?}", val),
+    };
+    Ok(sq_value)
+}
+
+/// the parameters, the custom values are encoded with the encoder registered for their type
+/// and stored as text when the encoded value is valid utf-8
+fn to_sq_values(
+    params: &[&Value],
+    decimal_mode: DecimalMode,
+    registry: &TypeRegistry,
+) -> Result<Vec<rusqlite::types::Value>, ConvertError> {
+    params
+        .iter()
+        .map(|param| {
+            match param {
+                Value::Custom(custom) => {
+                    match registry.encoder(&custom.type_name, None) {
+                        Some(encode) => {
+                            let encoded = encode(param)?;
+                            Ok(match String::from_utf8(encoded) {
+                                Ok(v) => rusqlite::types::Value::Text(v),
+                                Err(e) => rusqlite::types::Value::Blob(e.into_bytes()),
+                            })
+                        }
+                        None => to_sq_value(param, decimal_mode),
+                    }
+                }
+                _ => to_sq_value(param, decimal_mode),
+            }
+        })
+        .collect()
+}
+
+/// How the values are decoded into the variant of the type declared for their column
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Decoding {
+    /// keep the values which can not be decoded into the declared type as they are stored
+    #[default]
+    Lenient,
+    /// return an error for the values which can not be decoded into the declared type
+    Strict,
+}
+
+/// the value as it is stored by sqlite
+fn from_sq_value(raw: rusqlite::types::Value) -> Value {
+    match raw {
+        rusqlite::types::Value::Blob(v) => Value::Blob(v),
+        rusqlite::types::Value::Real(v) => Value::Double(v),
+        rusqlite::types::Value::Integer(v) => Value::Bigint(v),
+        rusqlite::types::Value::Text(v) => Value::Text(v),
+        rusqlite::types::Value::Null => Value::Nil,
     }
 }
 
-fn to_sq_values(params: &[&Value]) -> Vec<rusqlite::types::Value> {
-    let mut sql_values = Vec::with_capacity(params.len());
-    for param in params {
-        let sq_val = to_sq_value(param);
-        sql_values.push(sq_val);
+/// decode the value stored by sqlite into the variant of the declared type of its column,
+/// the columns of an expression have no declared type and their values are kept as they are
+fn decode(
+    raw: rusqlite::types::Value,
+    declared: Option<&SqlType>,
+    decoding: Decoding,
+) -> Result<Value, ConvertError> {
+    let value = from_sq_value(raw);
+    let sql_type = match declared {
+        Some(sql_type) if value != Value::Nil && !sql_type.same_type(&value) => sql_type,
+        _ => return Ok(value),
+    };
+    match decode_as(&value, sql_type) {
+        Some(decoded) => Ok(decoded),
+        None if decoding == Decoding::Lenient => Ok(value),
+        None => {
+            Err(ConvertError::UnsupportedConversion(
+                format!("{:?}", value),
+                format!("{:?}", sql_type),
+            ))
+        }
     }
-    sql_values
+}
+
+/// decode the text or blob stored in a column declared with a custom type, the numbers are
+/// decoded from their text
+fn decode_custom<D>(raw: rusqlite::types::Value, decode: &D) -> Result<Value, ConvertError>
+where
+    D: Fn(&[u8]) -> Result<Value, ConvertError> + ?Sized,
+{
+    match raw {
+        rusqlite::types::Value::Null => Ok(Value::Nil),
+        rusqlite::types::Value::Integer(v) => decode(v.to_string().as_bytes()),
+        rusqlite::types::Value::Real(v) => decode(v.to_string().as_bytes()),
+        rusqlite::types::Value::Text(v) => decode(v.as_bytes()),
+        rusqlite::types::Value::Blob(v) => decode(&v),
+    }
+}
+
+/// the values are parsed from the formats used when storing them in `to_sq_value`
+fn decode_as(value: &Value, sql_type: &SqlType) -> Option<Value> {
+    use bigdecimal::{
+        BigDecimal,
+        FromPrimitive,
+    };
+    use chrono::{
+        DateTime,
+        NaiveDate,
+        NaiveDateTime,
+        NaiveTime,
+        Utc,
+    };
+    use std::{
+        convert::TryFrom,
+        str::FromStr,
+    };
+
+    let decoded = match (value, sql_type) {
+        (Value::Bigint(v), SqlType::Bool) => Value::Bool(*v != 0),
+        (Value::Text(v), SqlType::Bool) => {
+            match &*v.to_lowercase() {
+                "true" | "t" | "1" => Value::Bool(true),
+                "false" | "f" | "0" => Value::Bool(false),
+                _ => return None,
+            }
+        }
+        (Value::Bigint(v), SqlType::Tinyint) => Value::Tinyint(i8::try_from(*v).ok()?),
+        (Value::Bigint(v), SqlType::Smallint) => Value::Smallint(i16::try_from(*v).ok()?),
+        (Value::Bigint(v), SqlType::Int) => Value::Int(i32::try_from(*v).ok()?),
+        (Value::Bigint(v), SqlType::Double) => Value::Double(*v as f64),
+        (Value::Bigint(v), SqlType::Numeric) => Value::BigDecimal(BigDecimal::from(*v)),
+        (Value::Double(v), SqlType::Numeric) => Value::BigDecimal(BigDecimal::from_f64(*v)?),
+        (Value::Text(v), SqlType::Numeric) => Value::BigDecimal(BigDecimal::from_str(v).ok()?),
+        (Value::Text(v), SqlType::Char) => {
+            let mut chars = v.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Value::Char(c),
+                _ => return None,
+            }
+        }
+        (Value::Text(v), SqlType::Uuid) => Value::Uuid(Uuid::parse_str(v).ok()?),
+        (Value::Blob(v), SqlType::Uuid) => Value::Uuid(Uuid::from_slice(v).ok()?),
+        (Value::Text(v), SqlType::Json) => Value::Json(serde_json::from_str(v).ok()?),
+        (Value::Text(v), SqlType::Date) => {
+            Value::Date(NaiveDate::parse_from_str(v, "%Y-%m-%d").ok()?)
+        }
+        (Value::Text(v), SqlType::Time) => {
+            Value::Time(NaiveTime::parse_from_str(v, "%H:%M:%S%.f").ok()?)
+        }
+        (Value::Text(v), SqlType::Timestamp) => {
+            match NaiveDateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S%.f")
+                .or_else(|_| NaiveDateTime::parse_from_str(v, "%Y-%m-%dT%H:%M:%S%.f"))
+            {
+                Ok(v) => Value::DateTime(v),
+                Err(_) => {
+                    let v = DateTime::parse_from_rfc3339(v).ok()?;
+                    Value::Timestamp(v.with_timezone(&Utc))
+                }
+            }
+        }
+        (Value::Text(v), SqlType::TimestampTz) => {
+            let v = DateTime::parse_from_rfc3339(v).ok()?;
+            Value::Timestamp(v.with_timezone(&Utc))
+        }
+        (Value::Text(v), SqlType::Geometry(..)) => {
+            Value::Geometry(Box::new(Geometry::from_str(v).ok()?))
+        }
+        (Value::Blob(v), SqlType::Geometry(..)) => {
+            Value::Geometry(Box::new(Geometry::from_wkb(v).ok()?))
+        }
+        _ => return None,
+    };
+    Some(decoded)
 }
 
 impl Database for SqliteDB {
@@ -117,50 +348,41 @@ impl Database for SqliteDB {
     }
 
     fn execute_sql_with_return(&mut self, sql: &str, params: &[&Value]) -> Result<Rows, DbError> {
-        info!("executing sql: {}", sql);
-        println!("executing sql: {}", sql);
-        info!("params: {:?}", params);
-        println!("params: {:?}", params);
-        let stmt = self.0.prepare(sql);
-
-        let column_names = if let Ok(ref stmt) = stmt {
-            stmt.column_names()
-        } else {
-            vec![]
-        };
-        let column_names: Vec<String> = column_names.iter().map(ToString::to_string).collect();
+        // the statements are cached by rusqlite, the cache here only counts the hits
+        let cached = statement_cache::of::<_, ()>(&mut self.0).get(sql).is_some();
+        let result = self.query(sql, params);
+        if !cached && result.is_ok() {
+            statement_cache::of::<_, ()>(&mut self.0).insert(sql, ());
+        }
+        result
+    }
 
-        match stmt {
-            Ok(mut stmt) => {
-                let sq_values = to_sq_values(params);
-                let column_count = stmt.column_count();
-                let mut records = Rows::new(column_names);
-                if let Ok(mut rows) = stmt.query(rusqlite::params_from_iter(&sq_values)) {
-                    while let Some(row) = rows.next()? {
-                        let mut record: Vec<Value> = vec![];
-                        for i in 0..column_count {
-                            let raw = row.get(i);
-                            if let Ok(raw) = raw {
-                                let value = match raw {
-                                    rusqlite::types::Value::Blob(v) => Value::Blob(v),
-                                    rusqlite::types::Value::Real(v) => Value::Double(v),
-                                    rusqlite::types::Value::Integer(v) => Value::Bigint(v),
-                                    rusqlite::types::Value::Text(v) => Value::Text(v),
-                                    rusqlite::types::Value::Null => Value::Nil,
-                                };
-                                record.push(value);
-                            }
-                        }
-                        records.push(record);
-                    }
-                }
-                Ok(records)
+    fn execute_sql_with_timeout(
+        &mut self,
+        sql: &str,
+        params: &[&Value],
+        timeout: Duration,
+    ) -> Result<Rows, DbError> {
+        let interrupt = self.0.get_interrupt_handle();
+        // waiting for a lock is also limited by the timeout
+        self.0
+            .busy_timeout(timeout.min(DEFAULT_BUSY_TIMEOUT))
+            .map_err(SqliteError::from)?;
+        let (result, interrupted) = timeout::execute(timeout, move || interrupt.interrupt(), || {
+            self.execute_sql_with_return(sql, params)
+        });
+        let reset = self.0.busy_timeout(DEFAULT_BUSY_TIMEOUT);
+        match result {
+            Err(_) if interrupted => {
+                Err(DbError::Timeout {
+                    sql: sql.to_string(),
+                    timeout,
+                })
             }
-            Err(e) => {
-                Err(
-                    Into::<DataOpError>::into(PlatformError::SqliteError(SqliteError::SqlError(e)))
-                        .into(),
-                )
+            Err(e) => Err(e),
+            Ok(rows) => {
+                reset.map_err(SqliteError::from)?;
+                Ok(rows)
             }
         }
     }
@@ -208,9 +430,13 @@ impl Database for SqliteDB {
                         ColumnConstraint::AutoIncrement(None)
                     } else {
                         let literal = match sql_type {
+                            // sqlite has no boolean literals before 3.23
                             SqlType::Bool => {
-                                let v: bool = default.parse().unwrap();
-                                Literal::Bool(v)
+                                match &*ic_default {
+                                    "1" | "true" => Literal::Bool(true),
+                                    "0" | "false" => Literal::Bool(false),
+                                    _ => panic!("error parsing to bool: {}", default),
+                                }
                             }
                             SqlType::Int
                             | SqlType::Smallint
@@ -293,7 +519,9 @@ impl Database for SqliteDB {
                             | SqlType::Tinytext
                             | SqlType::Mediumtext
                             | SqlType::Text => Literal::String(default.to_owned()),
-                            SqlType::Enum(_name, _choices) => Literal::String(default.to_owned()),
+                            SqlType::Enum(..) | SqlType::Custom(_) => {
+                                Literal::String(default.to_owned())
+                            }
                             _ => panic!("not convered: {:?}", sql_type),
                         };
                         ColumnConstraint::DefaultValue(literal)
@@ -304,33 +532,12 @@ impl Database for SqliteDB {
             }
 
             fn get_sql_type_capacity(&self) -> (SqlType, Option<Capacity>) {
-                let (dtype, capacity) = common::extract_datatype_with_capacity(&self.data_type);
-                let sql_type = match &*dtype {
-                    "int" | "integer" => SqlType::Int,
-                    "smallint" => SqlType::Smallint,
-                    "varchar" => SqlType::Text,
-                    "character varying" => SqlType::Text,
-                    "decimal" => SqlType::Double,
-                    "timestamp" => SqlType::Timestamp,
-                    "numeric" => SqlType::Numeric,
-                    "char" => {
-                        match capacity {
-                            None => SqlType::Char,
-                            Some(Capacity::Limit(1)) => SqlType::Char,
-                            Some(_) => SqlType::Varchar,
-                        }
-                    }
-                    "blob" => SqlType::Blob,
-                    "" => SqlType::Text,
-                    _ => {
-                        if dtype.contains("text") {
-                            SqlType::Text
-                        } else {
-                            panic!("not yet handled: {:?}", dtype)
-                        }
-                    }
-                };
-                (sql_type, capacity)
+                declared_sql_type(&self.data_type).unwrap_or_else(|| {
+                    // the types unknown to rustorm, converted with a `TypeRegistry`
+                    let (dtype, capacity) =
+                        common::extract_datatype_with_capacity(&self.data_type);
+                    (SqlType::Custom(dtype), capacity)
+                })
             }
         }
         macro_rules! unwrap_ok_some {
@@ -346,7 +553,10 @@ impl Database for SqliteDB {
                 }
             };
         }
-        let sql = format!("PRAGMA table_info({});", table_name.complete_name());
+        let sql = format!(
+            "PRAGMA table_info({});",
+            Dialect::Sqlite.table_name(table_name)?
+        );
         let result = self.execute_sql_with_return(&sql, &[])?;
         let mut primary_columns = vec![];
         let mut columns = vec![];
@@ -464,40 +674,252 @@ impl Database for SqliteDB {
     /// TODO: return the filename if possible
     fn get_database_name(&mut self) -> Result<Option<DatabaseName>, DbError> { Ok(None) }
 
+    /// sqlite increments the schema version every time the schema is modified
+    fn get_schema_version(&mut self) -> Result<Option<String>, DbError> {
+        let rows = self.execute_sql_with_return("PRAGMA schema_version", &[])?;
+        match rows.iter().next() {
+            Some(row) => Ok(Some(row.get::<i64>("schema_version")?.to_string())),
+            None => Ok(None),
+        }
+    }
+
+    fn set_statement_cache_capacity(&mut self, capacity: usize) {
+        self.0.set_prepared_statement_cache_capacity(capacity);
+        statement_cache::of::<_, ()>(&mut self.0).set_capacity(capacity);
+    }
+
+    fn statement_cache_stats(&self) -> StatementCacheStats {
+        statement_cache::stats_of::<_, ()>(&self.0)
+    }
+
+    fn set_type_registry(&mut self, registry: TypeRegistry) { self.3 = registry; }
+
+    /// sqlite keeps the last value of AUTOINCREMENT tables in `sqlite_sequence`,
+    /// the next rowid generated is the maximum of this value and the largest rowid plus 1.
     fn set_autoincrement_value(
         &mut self,
         table_name: &TableName,
         sequence_value: i64,
     ) -> Result<Option<i64>, DbError> {
-        println!("setting new sequnce value: {}", sequence_value);
-        let sql = "UPDATE sqlite_sequence SET seq = $2 WHERE name = $1";
+        if !self.is_autoincrement_table(table_name)? {
+            return Ok(None);
+        }
+        // the table will only have a row in sqlite_sequence once a record is inserted
+        let sql = if self.get_autoincrement_last_value(table_name)?.is_some() {
+            "UPDATE sqlite_sequence SET seq = ?2 WHERE name = ?1"
+        } else {
+            "INSERT INTO sqlite_sequence(name, seq) VALUES(?1, ?2)"
+        };
         self.execute_sql_with_return(sql, &[
-            &table_name.complete_name().into(),
-            &sequence_value.into(),
+            &table_name.name.to_value(),
+            &sequence_value.to_value(),
         ])?;
-
-        Ok(None)
+        Ok(Some(sequence_value))
     }
 
     fn get_autoincrement_last_value(
         &mut self,
         table_name: &TableName,
     ) -> Result<Option<i64>, DbError> {
-        let sql = "SELECT seq FROM sqlite_sequence where name = $1";
-        let result: Vec<Option<i64>> = self
-            .execute_sql_with_return(sql, &[&table_name.complete_name().into()])?
-            .iter()
-            .filter_map(|row| row.get("seq").ok())
-            .collect();
+        if !self.is_autoincrement_table(table_name)? {
+            return Ok(None);
+        }
+        let sql = "SELECT seq FROM sqlite_sequence WHERE name = ?1";
+        let rows = self.execute_sql_with_return(sql, &[&table_name.name.to_value()])?;
+        match rows.iter().next() {
+            Some(row) => Ok(row.get_opt("seq")?),
+            None => Ok(None),
+        }
+    }
 
-        if let Some(first) = result.get(0) {
-            Ok(*first)
+    fn get_autoincrement_max_value(
+        &mut self,
+        table_name: &TableName,
+    ) -> Result<Option<i64>, DbError> {
+        if !self.is_autoincrement_table(table_name)? {
+            return Ok(None);
+        }
+        let sql = format!(
+            "SELECT MAX(ROWID) AS value FROM {}",
+            Dialect::Sqlite.table_name(table_name)?
+        );
+        let rows = self.execute_sql_with_return(&sql, &[])?;
+        match rows.iter().next() {
+            Some(row) => Ok(row.get_opt("value")?),
+            None => Ok(None),
+        }
+    }
+}
+
+impl SqliteDB {
+    /// execute the statement, prepared with the statement cache of rusqlite
+    fn query(&self, sql: &str, params: &[&Value]) -> Result<Rows, DbError> {
+        let stmt = self.0.prepare_cached(sql);
+
+        let column_names = if let Ok(ref stmt) = stmt {
+            stmt.column_names()
         } else {
-            Ok(None)
+            vec![]
+        };
+        let column_names: Vec<String> = column_names.iter().map(ToString::to_string).collect();
+
+        match stmt {
+            Ok(mut stmt) => {
+                let sq_values = to_sq_values(params, self.1, &self.3)?;
+                let declared: Vec<Option<String>> = stmt
+                    .columns()
+                    .iter()
+                    .map(|column| column.decl_type().map(str::to_lowercase))
+                    .collect();
+                let sql_types: Vec<Option<SqlType>> = declared
+                    .iter()
+                    .map(|declared| {
+                        declared_sql_type(declared.as_ref()?).map(|(sql_type, _)| sql_type)
+                    })
+                    .collect();
+                let decoders: Vec<_> = declared
+                    .iter()
+                    .map(|declared| {
+                        let (name, _) = common::extract_datatype_with_capacity(declared.as_ref()?);
+                        self.3.decoder(&name, None)
+                    })
+                    .collect();
+                let mut records = Rows::new(column_names);
+                let mut rows = stmt
+                    .query(rusqlite::params_from_iter(&sq_values))
+                    .map_err(|e| SqliteError::Sql(e, sql.to_string()))?;
+                while let Some(row) = rows
+                    .next()
+                    .map_err(|e| SqliteError::Sql(e, sql.to_string()))?
+                {
+                    let mut record: Vec<Value> = vec![];
+                    for (i, (sql_type, decoder)) in sql_types.iter().zip(&decoders).enumerate() {
+                        let raw = row.get(i).map_err(|e| SqliteError::Sql(e, sql.to_string()))?;
+                        let value = match decoder {
+                            Some(decode) => decode_custom(raw, decode)?,
+                            None => decode(raw, sql_type.as_ref(), self.2)?,
+                        };
+                        record.push(value);
+                    }
+                    records.push(record);
+                }
+                Ok(records)
+            }
+            Err(e) => {
+                Err(
+                    Into::<DataOpError>::into(PlatformError::SqliteError(SqliteError::Sql(
+                        e,
+                        sql.to_string(),
+                    )))
+                    .into(),
+                )
+            }
+        }
+    }
+
+    /// only tables declared with AUTOINCREMENT are tracked in `sqlite_sequence`
+    fn is_autoincrement_table(&mut self, table_name: &TableName) -> Result<bool, DbError> {
+        let sql = "SELECT sql FROM sqlite_master WHERE type IN ('table', 'view') AND name = ?1";
+        let rows = self.execute_sql_with_return(sql, &[&table_name.name.to_value()])?;
+        match rows.iter().next() {
+            Some(row) => {
+                let create_sql: String = row.get("sql")?;
+                Ok(has_autoincrement_keyword(&create_sql))
+            }
+            None => {
+                Err(DbError::DataError(DataError::TableNameNotFound(
+                    table_name.complete_name(),
+                )))
+            }
         }
     }
 }
 
+/// whether the `AUTOINCREMENT` keyword is used in the create statement of a table, the quoted
+/// names, the string literals and the comments are skipped
+fn has_autoincrement_keyword(create_sql: &str) -> bool {
+    let mut chars = create_sql.chars().peekable();
+    let mut word = String::new();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            word.push(c);
+            continue;
+        }
+        if word.eq_ignore_ascii_case("AUTOINCREMENT") {
+            return true;
+        }
+        word.clear();
+        let end = match c {
+            '\'' | '"' | '`' => c,
+            '[' => ']',
+            '-' if chars.peek() == Some(&'-') => '\n',
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                while let Some(c) = chars.next() {
+                    if c == '*' && chars.peek() == Some(&'/') {
+                        chars.next();
+                        break;
+                    }
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        // a doubled quote inside a quoted name is read as two quoted names
+        for c in chars.by_ref() {
+            if c == end {
+                break;
+            }
+        }
+    }
+    word.eq_ignore_ascii_case("AUTOINCREMENT")
+}
+
+/// the sql type of the type declared for a column, which sqlite only uses for its affinity
+fn declared_sql_type(data_type: &str) -> Option<(SqlType, Option<Capacity>)> {
+    let (dtype, capacity) = common::extract_datatype_with_capacity(&data_type.to_lowercase());
+    let sql_type = match &*dtype {
+        "boolean" | "bool" => SqlType::Bool,
+        "tinyint" => SqlType::Tinyint,
+        // the integers of sqlite are 64 bits
+        "int" | "integer" => SqlType::Bigint,
+        "smallint" => SqlType::Smallint,
+        "bigint" => SqlType::Bigint,
+        // sqlite stores every floating point value in 8 bytes
+        "real" | "float" | "double" | "double precision" => SqlType::Double,
+        "varchar" => SqlType::Text,
+        "character varying" => SqlType::Text,
+        "decimal" => SqlType::Numeric,
+        "date" => SqlType::Date,
+        "time" => SqlType::Time,
+        "timestamp" | "datetime" => SqlType::Timestamp,
+        "timestamptz" => SqlType::TimestampTz,
+        "numeric" => SqlType::Numeric,
+        "uuid" => SqlType::Uuid,
+        "json" | "jsonb" => SqlType::Json,
+        "char" => {
+            match capacity {
+                None => SqlType::Char,
+                Some(Capacity::Limit(1)) => SqlType::Char,
+                Some(_) => SqlType::Varchar,
+            }
+        }
+        "blob" => SqlType::Blob,
+        "" => SqlType::Text,
+        _ => {
+            if dtype.contains("text") {
+                SqlType::Text
+            } else if let Some(ty) = GeometryType::from_name(&dtype) {
+                // the geometry columns declared like in spatialite
+                SqlType::Geometry(ty, None)
+            } else {
+                return None;
+            }
+        }
+    };
+    Some((sql_type, capacity))
+}
+
 fn get_table_names(db: &mut dyn Database, kind: &str) -> Result<Vec<TableName>, DbError> {
     #[derive(Debug, FromDao)]
     struct TableNameSimple {
@@ -523,7 +945,10 @@ fn get_table_names(db: &mut dyn Database, kind: &str) -> Result<Vec<TableName>,
 
 /// get the foreign keys of table
 fn get_foreign_keys(db: &mut dyn Database, table: &TableName) -> Result<Vec<ForeignKey>, DbError> {
-    let sql = format!("PRAGMA foreign_key_list({});", table.complete_name());
+    let sql = format!(
+        "PRAGMA foreign_key_list({});",
+        Dialect::Sqlite.table_name(table)?
+    );
     #[derive(Debug, FromDao)]
     struct ForeignSimple {
         id: i64,
@@ -566,14 +991,96 @@ fn get_foreign_keys(db: &mut dyn Database, table: &TableName) -> Result<Vec<Fore
     Ok(foreign_keys)
 }
 
+// the extended result codes
+const SQLITE_CONSTRAINT_CHECK: i32 = rusqlite::ffi::SQLITE_CONSTRAINT | (1 << 8);
+const SQLITE_CONSTRAINT_FOREIGNKEY: i32 = rusqlite::ffi::SQLITE_CONSTRAINT | (3 << 8);
+const SQLITE_CONSTRAINT_NOTNULL: i32 = rusqlite::ffi::SQLITE_CONSTRAINT | (5 << 8);
+const SQLITE_CONSTRAINT_PRIMARYKEY: i32 = rusqlite::ffi::SQLITE_CONSTRAINT | (6 << 8);
+const SQLITE_CONSTRAINT_TRIGGER: i32 = rusqlite::ffi::SQLITE_CONSTRAINT | (7 << 8);
+const SQLITE_CONSTRAINT_UNIQUE: i32 = rusqlite::ffi::SQLITE_CONSTRAINT | (8 << 8);
+// a write transaction on a stale snapshot of a database in wal mode
+const SQLITE_BUSY_SNAPSHOT: i32 = rusqlite::ffi::SQLITE_BUSY | (2 << 8);
+
 #[derive(Debug, Error)]
 pub enum SqliteError {
     #[error("Error executing {0}")]
     SqlError(#[from] rusqlite::Error),
+    #[error("Error executing {1}: {0}")]
+    Sql(rusqlite::Error, String),
     #[error("Pool initialization error: {0}")]
     PoolInitializationError(#[from] r2d2::Error),
 }
 
+impl SqliteError {
+    /// the details of a constraint violation or a busy database, parsed from the extended result
+    /// code and the error message such as `UNIQUE constraint failed: actor.first_name`
+    pub(crate) fn to_data_op_error(&self) -> Option<DataOpError> {
+        use rusqlite::ErrorCode;
+
+        let (error, sql) = match self {
+            SqliteError::Sql(error, sql) => (error, sql.as_str()),
+            SqliteError::SqlError(error) => (error, ""),
+            SqliteError::PoolInitializationError(_) => return None,
+        };
+        let (error_code, message) = match error {
+            rusqlite::Error::SqliteFailure(error_code, message) => {
+                let message = message.clone().unwrap_or_else(|| error_code.to_string());
+                (error_code, message)
+            }
+            _ => return None,
+        };
+        let code = error_code.extended_code;
+        let code_name = match (error_code.code, code) {
+            (_, SQLITE_CONSTRAINT_UNIQUE) => "SQLITE_CONSTRAINT_UNIQUE",
+            (_, SQLITE_CONSTRAINT_PRIMARYKEY) => "SQLITE_CONSTRAINT_PRIMARYKEY",
+            (_, SQLITE_CONSTRAINT_FOREIGNKEY) => "SQLITE_CONSTRAINT_FOREIGNKEY",
+            (_, SQLITE_CONSTRAINT_NOTNULL) => "SQLITE_CONSTRAINT_NOTNULL",
+            (_, SQLITE_CONSTRAINT_CHECK) => "SQLITE_CONSTRAINT_CHECK",
+            (_, SQLITE_CONSTRAINT_TRIGGER) => "SQLITE_CONSTRAINT_TRIGGER",
+            (_, SQLITE_BUSY_SNAPSHOT) => "SQLITE_BUSY_SNAPSHOT",
+            (ErrorCode::ConstraintViolation, _) => "SQLITE_CONSTRAINT",
+            (ErrorCode::DatabaseBusy, _) => "SQLITE_BUSY",
+            (ErrorCode::DatabaseLocked, _) => "SQLITE_LOCKED",
+            (ErrorCode::OperationInterrupted, _) => "SQLITE_INTERRUPT",
+            _ => return None,
+        };
+        // what comes after `constraint failed: `
+        let subject = message.split_once(": ").map(|(_, subject)| subject.trim());
+        let mut cause_table = None;
+        let mut column = None;
+        let mut constraint = None;
+        match code {
+            SQLITE_CONSTRAINT_UNIQUE
+            | SQLITE_CONSTRAINT_PRIMARYKEY
+            | SQLITE_CONSTRAINT_NOTNULL => {
+                // a comma separated list of `table.column`
+                let columns: Vec<(&str, &str)> = subject
+                    .into_iter()
+                    .flat_map(|subject| subject.split(", "))
+                    .filter_map(|table_column| table_column.split_once('.'))
+                    .collect();
+                cause_table = columns.first().map(|(table, _)| table.to_string());
+                if let [(_, single)] = columns.as_slice() {
+                    column = Some(single.to_string());
+                }
+            }
+            SQLITE_CONSTRAINT_CHECK => constraint = subject.map(String::from),
+            _ => (),
+        }
+        Some(DataOpError::ConstraintError {
+            severity: "ERROR".to_string(),
+            code: code_name.to_string(),
+            message,
+            detail: None,
+            cause_table,
+            constraint,
+            column,
+            datatype: None,
+            sql: sql.to_string(),
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -592,12 +1099,29 @@ mod test {
             Literal::Null,
         },
         types::SqlType::{
-            Int,
+            Bigint,
             Text,
             Timestamp,
         },
     };
 
+    #[test]
+    fn autoincrement_keyword() {
+        assert!(has_autoincrement_keyword(
+            "CREATE TABLE a (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT)"
+        ));
+        assert!(has_autoincrement_keyword(
+            "CREATE TABLE a (id INTEGER PRIMARY KEY autoincrement)"
+        ));
+        assert!(!has_autoincrement_keyword(
+            "CREATE TABLE a (id INTEGER PRIMARY KEY, \"autoincrement\" TEXT, note TEXT \
+             DEFAULT 'no AUTOINCREMENT here', [AUTOINCREMENT] INT) -- AUTOINCREMENT"
+        ));
+        assert!(!has_autoincrement_keyword(
+            "CREATE TABLE a (id INTEGER PRIMARY KEY /* AUTOINCREMENT */, autoincrement_id INT)"
+        ));
+    }
+
     #[test]
     fn test_advancing_autoincrement_primary_column() {
         let db_url = "sqlite://sakila.db";
@@ -621,6 +1145,35 @@ mod test {
         println!("last value: {}", last_value);
     }
 
+    #[test]
+    fn test_reset_sequences_to_max() {
+        let db_url = "sqlite://sakila.db";
+        let mut pool = Pool::new();
+        let mut em = pool.em(db_url).unwrap();
+        let actor_table = TableName::from("actor");
+        let max_value = em
+            .db()
+            .get_autoincrement_max_value(&actor_table)
+            .unwrap()
+            .unwrap();
+        em.set_autoincrement_value(&actor_table, max_value + 100)
+            .unwrap();
+        let reset = em.reset_sequences_to_max().unwrap();
+        assert!(reset.contains(&(actor_table.clone(), max_value)));
+        let last_value = em.get_autoincrement_last_value(&actor_table).unwrap();
+        assert_eq!(last_value, Some(max_value));
+    }
+
+    #[test]
+    fn test_autoincrement_on_table_without_one() {
+        let db_url = "sqlite://sakila.db";
+        let mut pool = Pool::new();
+        let mut em = pool.em(db_url).unwrap();
+        let film_actor = TableName::from("film_actor");
+        assert_eq!(em.get_autoincrement_last_value(&film_actor).unwrap(), None);
+        assert_eq!(em.set_autoincrement_value(&film_actor, 10).unwrap(), None);
+    }
+
     #[test]
     fn test_get_all_tables() {
         let db_url = "sqlite://sakila.db";
@@ -682,7 +1235,7 @@ mod test {
                     name: ColumnName::from("film_id"),
                     comment: None,
                     specification: ColumnSpecification {
-                        sql_type: SqlType::Int,
+                        sql_type: SqlType::Bigint,
                         capacity: None,
                         constraints: vec![
                             ColumnConstraint::NotNull,
@@ -771,7 +1324,7 @@ mod test {
                     name: ColumnName::from("rental_rate"),
                     comment: None,
                     specification: ColumnSpecification {
-                        sql_type: SqlType::Double,
+                        sql_type: SqlType::Numeric,
                         capacity: Some(Capacity::Range(4, 2)),
                         constraints: vec![
                             ColumnConstraint::NotNull,
@@ -796,7 +1349,7 @@ mod test {
                     name: ColumnName::from("replacement_cost"),
                     comment: None,
                     specification: ColumnSpecification {
-                        sql_type: SqlType::Double,
+                        sql_type: SqlType::Numeric,
                         capacity: Some(Capacity::Range(5, 2)),
                         constraints: vec![
                             ColumnConstraint::NotNull,
@@ -901,7 +1454,7 @@ mod test {
                     },
                     comment: None,
                     specification: ColumnSpecification {
-                        sql_type: Int,
+                        sql_type: Bigint,
                         capacity: None,
                         constraints: vec![NotNull, DefaultValue(Null)]
                     },
@@ -1012,7 +1565,7 @@ mod test {
                     },
                     comment: None,
                     specification: ColumnSpecification {
-                        sql_type: Int,
+                        sql_type: Bigint,
                         capacity: None,
                         constraints: vec![NotNull, DefaultValue(Null)]
                     },
@@ -1031,7 +1584,7 @@ mod test {
                     },
                     comment: None,
                     specification: ColumnSpecification {
-                        sql_type: Int,
+                        sql_type: Bigint,
                         capacity: None,
                         constraints: vec![NotNull, DefaultValue(Null)]
                     },
@@ -1167,4 +1720,455 @@ mod test {
             },
         ]);
     }
+
+    #[test]
+    fn geometries_are_stored_as_wkt() {
+        use clia_rustorm_dao::Geometry;
+        use geo_types::{
+            LineString,
+            Point,
+        };
+
+        let db_url = "sqlite://sakila.db";
+        let mut pool = Pool::new();
+        let mut dm = pool.dm(db_url).unwrap();
+        let line = LineString::from(vec![(1.0, 2.0), (3.0, 4.5)]);
+        let geometry = Geometry::new(line.clone()).with_srid(4326);
+        let rows = dm
+            .execute_sql_with_return("SELECT ?1 AS point, ?2 AS line, ?3 AS geometry", &[
+                &Point::new(1.0, 2.0).to_value(),
+                &line.to_value(),
+                &geometry.to_value(),
+            ])
+            .unwrap();
+        let row = rows.iter().next().unwrap();
+        assert_eq!(row.get::<String>("point").unwrap(), "POINT(1 2)");
+        assert_eq!(row.get::<Point<f64>>("point").unwrap(), Point::new(1.0, 2.0));
+        assert_eq!(row.get::<LineString<f64>>("line").unwrap(), line);
+        assert_eq!(row.get::<String>("geometry").unwrap(), "SRID=4326;LINESTRING(1 2,3 4.5)");
+        assert_eq!(row.get::<Geometry>("geometry").unwrap(), geometry);
+    }
+
+    #[test]
+    fn decimals_are_stored_as_real_or_text() {
+        use bigdecimal::BigDecimal;
+        use std::str::FromStr;
+
+        let amounts = [
+            BigDecimal::from_str("12345678901234567890.123").unwrap(),
+            BigDecimal::from_str("0.10").unwrap(),
+        ];
+        let pool = init_pool(":memory:").unwrap();
+        let mut db = SqliteDB(
+            pool.get().unwrap(),
+            DecimalMode::default(),
+            Decoding::Strict,
+            TypeRegistry::default(),
+        );
+        db.0.execute_batch("CREATE TABLE ledger (amount DECIMAL(30,3))").unwrap();
+        let insert = "INSERT INTO ledger VALUES (?1)";
+        let select = "SELECT amount, typeof(amount) AS storage FROM ledger";
+        db.execute_sql_with_return(insert, &[&amounts[0].to_value()]).unwrap();
+        let rows = db.execute_sql_with_return(select, &[]).unwrap();
+        let row = rows.iter().next().unwrap();
+        assert_eq!(row.get::<String>("storage").unwrap(), "real");
+        assert_ne!(row.get::<BigDecimal>("amount").unwrap(), amounts[0]);
+
+        db.0.execute_batch("DROP TABLE ledger; CREATE TABLE ledger (amount TEXT)").unwrap();
+        db.1 = DecimalMode::Text;
+        for amount in amounts.iter() {
+            db.execute_sql_with_return(insert, &[&amount.to_value()]).unwrap();
+        }
+        let rows = db.execute_sql_with_return(select, &[]).unwrap();
+        for (row, amount) in rows.iter().zip(amounts.iter()) {
+            assert_eq!(row.get::<String>("storage").unwrap(), "text");
+            let stored = row.get::<BigDecimal>("amount").unwrap();
+            assert_eq!(stored, *amount);
+            assert_eq!(stored.to_string(), amount.to_string());
+        }
+    }
+
+    #[test]
+    fn declared_types_are_decoded() {
+        use bigdecimal::BigDecimal;
+        use chrono::NaiveDate;
+        use std::str::FromStr;
+
+        let pool = init_pool(":memory:").unwrap();
+        let mut db = SqliteDB(
+            pool.get().unwrap(),
+            DecimalMode::default(),
+            Decoding::Lenient,
+            TypeRegistry::default(),
+        );
+        db.0.execute_batch(
+            "CREATE TABLE item (active BOOLEAN, day DATE, seen TIMESTAMP, id UUID, doc JSON,
+                price DECIMAL(10,2), amount SMALLINT, name TEXT);
+             INSERT INTO item VALUES (1, '2021-02-03', '2021-02-03 04:05:06',
+                '67e55044-10b1-426f-9247-bb680e5fe0c8', '{\"a\": 1}', '12.50', 7, 'x');
+             INSERT INTO item (day) VALUES ('yesterday');",
+        )
+        .unwrap();
+        let sql = "SELECT active, day, seen, id, doc, price, amount, name FROM item";
+        let rows = db.execute_sql_with_return(sql, &[]).unwrap();
+        assert_eq!(rows.data[0], vec![
+            Value::Bool(true),
+            Value::Date(NaiveDate::from_ymd_opt(2021, 2, 3).unwrap()),
+            Value::DateTime(
+                NaiveDate::from_ymd_opt(2021, 2, 3).unwrap().and_hms_opt(4, 5, 6).unwrap()
+            ),
+            Value::Uuid(Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap()),
+            Value::Json(serde_json::json!({"a": 1})),
+            Value::BigDecimal(BigDecimal::from_str("12.5").unwrap()),
+            Value::Smallint(7),
+            Value::Text("x".into()),
+        ]);
+        assert_eq!(rows.data[1][0], Value::Nil);
+        assert_eq!(rows.data[1][1], Value::Text("yesterday".into()));
+
+        db.2 = Decoding::Strict;
+        assert!(matches!(
+            db.execute_sql_with_return("SELECT day FROM item", &[]),
+            Err(DbError::ConvertError(_))
+        ));
+        let rows = db.execute_sql_with_return("SELECT day || '' AS day FROM item", &[]).unwrap();
+        assert_eq!(rows.iter().count(), 2);
+    }
+
+    #[test]
+    fn integers_are_64_bits_and_query_errors_are_returned() {
+        let pool = init_pool(":memory:").unwrap();
+        let mut db = SqliteDB(
+            pool.get().unwrap(),
+            DecimalMode::default(),
+            Decoding::Strict,
+            TypeRegistry::default(),
+        );
+        db.0.execute_batch("CREATE TABLE counter (id INTEGER PRIMARY KEY, hits INT)").unwrap();
+        let big = 1_i64 << 40;
+        db.execute_sql_with_return("INSERT INTO counter VALUES (?1, 1)", &[&big.to_value()])
+            .unwrap();
+        let rows = db.execute_sql_with_return("SELECT id, hits FROM counter", &[]).unwrap();
+        assert_eq!(rows.data[0], vec![Value::Bigint(big), Value::Bigint(1)]);
+        assert!(db
+            .execute_sql_with_return("SELECT id FROM counter WHERE id = ?1", &[])
+            .is_err());
+    }
+
+    #[test]
+    fn custom_types_use_the_registered_conversions() {
+        use crate::{
+            Custom,
+            FromValue,
+        };
+
+        let pool = init_pool(":memory:").unwrap();
+        let mut registry = TypeRegistry::new();
+        registry.register_decoder("mood", |raw| {
+            let mood = String::from_utf8(raw.to_vec()).unwrap();
+            Ok(Custom::new("mood", mood.to_lowercase()).to_value())
+        });
+        registry.register_encoder("mood", |value| {
+            let mood = Custom::from_value(value).unwrap().value.to_string();
+            Ok(mood.to_uppercase().into_bytes())
+        });
+        let mut db = SqliteDB(
+            pool.get().unwrap(),
+            DecimalMode::default(),
+            Decoding::Strict,
+            registry,
+        );
+        db.0.execute_batch("CREATE TABLE person (name TEXT, mood MOOD(10))").unwrap();
+        let happy = Custom::new("mood", "happy");
+        db.execute_sql_with_return("INSERT INTO person VALUES ('x', ?1)", &[&happy.to_value()])
+            .unwrap();
+        let rows = db
+            .execute_sql_with_return("SELECT mood, mood || '' AS stored FROM person", &[])
+            .unwrap();
+        assert_eq!(rows.data[0], vec![happy.to_value(), Value::Text("HAPPY".into())]);
+
+        let table = db.get_table(&TableName::from("person")).unwrap().unwrap();
+        assert_eq!(
+            table.columns[1].specification.sql_type,
+            SqlType::Custom("mood".into())
+        );
+    }
+
+    #[test]
+    fn constraint_errors() {
+        let pool = init_pool(":memory:").unwrap();
+        let mut db = SqliteDB(
+            pool.get().unwrap(),
+            DecimalMode::default(),
+            Decoding::default(),
+            TypeRegistry::default(),
+        );
+        db.0.execute_batch(
+            "PRAGMA foreign_keys = ON;
+             CREATE TABLE parent (id INTEGER PRIMARY KEY, name TEXT NOT NULL UNIQUE);
+             CREATE TABLE child (id INTEGER PRIMARY KEY,
+                parent_id INTEGER REFERENCES parent(id),
+                age INTEGER CONSTRAINT positive_age CHECK (age > 0));
+             INSERT INTO parent VALUES (1, 'one');",
+        )
+        .unwrap();
+        let mut violate = |sql: &str| {
+            match db.execute_sql_with_return(sql, &[]) {
+                Err(DbError::DataOpError(DataOpError::ConstraintError {
+                    code,
+                    cause_table,
+                    column,
+                    constraint,
+                    sql: error_sql,
+                    ..
+                })) => {
+                    assert_eq!(error_sql, sql);
+                    (code, cause_table, column, constraint)
+                }
+                other => panic!("expecting a constraint error, got: {:?}", other),
+            }
+        };
+        assert_eq!(
+            violate("INSERT INTO parent VALUES (2, 'one')"),
+            (
+                "SQLITE_CONSTRAINT_UNIQUE".to_string(),
+                Some("parent".to_string()),
+                Some("name".to_string()),
+                None
+            )
+        );
+        assert_eq!(
+            violate("INSERT INTO parent VALUES (1, 'uno')").0,
+            "SQLITE_CONSTRAINT_PRIMARYKEY"
+        );
+        assert_eq!(
+            violate("INSERT INTO parent (id) VALUES (3)"),
+            (
+                "SQLITE_CONSTRAINT_NOTNULL".to_string(),
+                Some("parent".to_string()),
+                Some("name".to_string()),
+                None
+            )
+        );
+        assert_eq!(
+            violate("INSERT INTO child VALUES (1, 42, 1)").0,
+            "SQLITE_CONSTRAINT_FOREIGNKEY"
+        );
+        assert_eq!(
+            violate("INSERT INTO child VALUES (1, 1, -1)"),
+            (
+                "SQLITE_CONSTRAINT_CHECK".to_string(),
+                None,
+                None,
+                Some("positive_age".to_string())
+            )
+        );
+    }
+
+    #[test]
+    fn explain_query_plan() {
+        let mut pool = Pool::new();
+        let mut em = pool.em("sqlite://sakila.db").unwrap();
+        let options = crate::ExplainOptions::new();
+        let plan = em
+            .explain("SELECT * FROM actor WHERE actor_id = ?", &[&1], &options)
+            .unwrap();
+        assert!(!plan.has_sequential_scan(0.0));
+        assert_eq!(plan.root.children[0].index.as_deref(), Some("rowid"));
+        let plan = em
+            .explain("SELECT * FROM actor WHERE first_name = ?", &[&"PENELOPE"], &options)
+            .unwrap();
+        let scans = plan.sequential_scans(0.0);
+        assert_eq!(scans[0].relation.as_deref(), Some("actor"));
+        assert!(em
+            .explain("SELECT * FROM actor", &[], &options.analyze(true))
+            .is_err());
+    }
+
+    #[test]
+    fn arrays_are_stored_as_json() {
+        let mut pool = Pool::new();
+        let mut dm = pool.dm("sqlite://sakila.db").unwrap();
+        let tags = vec![Some("a".to_string()), None];
+        let matrix = vec![vec![1i64, 2], vec![3, 4]];
+        let rows = dm
+            .execute_sql_with_return("SELECT ?1 AS tags, ?2 AS matrix", &[
+                &tags.to_value(),
+                &matrix.to_value(),
+            ])
+            .unwrap();
+        let row = rows.iter().next().unwrap();
+        assert_eq!(row.get::<String>("tags").unwrap(), r#"["a",null]"#);
+        assert_eq!(row.get::<Vec<Option<String>>>("tags").unwrap(), tags);
+        assert_eq!(row.get::<Vec<Vec<i64>>>("matrix").unwrap(), matrix);
+    }
+
+    #[test]
+    fn json_is_stored_as_text() {
+        let mut pool = Pool::new();
+        let mut dm = pool.dm("sqlite://sakila.db").unwrap();
+        let document = serde_json::json!({"tags": ["a", null]});
+        let rows = dm
+            .execute_sql_with_return("SELECT json_extract(?1, '$.tags') AS tags, ?1 AS document", &[
+                &document.to_value(),
+            ])
+            .unwrap();
+        let row = rows.iter().next().unwrap();
+        assert_eq!(row.get::<Vec<Option<String>>>("tags").unwrap(), vec![
+            Some("a".to_string()),
+            None
+        ]);
+        let document_json: crate::Json<serde_json::Value> = row.get("document").unwrap();
+        assert_eq!(document_json.into_inner(), document);
+    }
+
+    #[test]
+    fn temporal_values_are_stored_as_text() {
+        use chrono::{
+            DateTime,
+            FixedOffset,
+            NaiveTime,
+            Utc,
+        };
+
+        let mut pool = Pool::new();
+        let mut dm = pool.dm("sqlite://sakila.db").unwrap();
+        let stamp = DateTime::parse_from_rfc3339("2021-01-31T10:20:30.5+05:30").unwrap();
+        let time = NaiveTime::from_hms_opt(10, 20, 30).unwrap();
+        let time = (time, FixedOffset::east_opt(0).unwrap());
+        let rows = dm
+            .execute_sql_with_return("SELECT ?1 AS stamp, ?2 AS time, ?3 AS utc", &[
+                &stamp.to_value(),
+                &time.to_value(),
+                &stamp.with_timezone(&Utc).to_value(),
+            ])
+            .unwrap();
+        let row = rows.iter().next().unwrap();
+        assert_eq!(row.get::<String>("stamp").unwrap(), "2021-01-31T10:20:30.500+05:30");
+        assert_eq!(row.get::<DateTime<FixedOffset>>("stamp").unwrap(), stamp);
+        assert_eq!(row.get::<(NaiveTime, FixedOffset)>("time").unwrap(), time);
+        assert_eq!(row.get::<DateTime<Utc>>("utc").unwrap(), stamp);
+    }
+
+    #[test]
+    fn intervals_are_stored_as_iso_8601() {
+        use clia_rustorm_dao::Interval;
+        use std::time::Duration;
+
+        let mut pool = Pool::new();
+        let mut dm = pool.dm("sqlite://sakila.db").unwrap();
+        let interval = Interval::new(4 * 3_600_000_000 + 500_000, 3, 14);
+        let rows = dm
+            .execute_sql_with_return("SELECT ?1 AS interval, ?2 AS duration", &[
+                &interval.to_value(),
+                &Interval::new(90_000_000, 0, 0).to_value(),
+            ])
+            .unwrap();
+        let row = rows.iter().next().unwrap();
+        assert_eq!(row.get::<String>("interval").unwrap(), "P1Y2M3DT4H0.5S");
+        assert_eq!(row.get::<Interval>("interval").unwrap(), interval);
+        assert_eq!(row.get::<Duration>("duration").unwrap(), Duration::from_secs(90));
+    }
+
+    #[test]
+    fn network_range_bit_and_hstore_are_stored_as_text() {
+        use chrono::NaiveDate;
+        use clia_rustorm_dao::{
+            BitString,
+            IpNetwork,
+        };
+        use std::{
+            collections::BTreeMap,
+            ops::Range,
+        };
+
+        let mut pool = Pool::new();
+        let mut dm = pool.dm("sqlite://sakila.db").unwrap();
+        let inet: IpNetwork = "10.0.0.1/8".parse().unwrap();
+        let bits: BitString = "0110".parse().unwrap();
+        let start = NaiveDate::from_ymd_opt(2021, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2021, 2, 1).unwrap();
+        let mut hstore = BTreeMap::new();
+        hstore.insert("a".to_string(), None);
+        let rows = dm
+            .execute_sql_with_return(
+                "SELECT ?1 AS inet, ?2 AS bits, ?3 AS dates, ?4 AS hstore, ?5 AS money",
+                &[
+                    &inet.to_value(),
+                    &bits.to_value(),
+                    &(start..end).to_value(),
+                    &hstore.to_value(),
+                    &Value::Money(1234),
+                ],
+            )
+            .unwrap();
+        let row = rows.iter().next().unwrap();
+        assert_eq!(row.get::<String>("inet").unwrap(), "10.0.0.1/8");
+        assert_eq!(row.get::<IpNetwork>("inet").unwrap(), inet);
+        assert_eq!(row.get::<BitString>("bits").unwrap(), bits);
+        assert_eq!(row.get::<Range<NaiveDate>>("dates").unwrap(), start..end);
+        assert_eq!(row.get::<BTreeMap<String, Option<String>>>("hstore").unwrap(), hstore);
+        assert_eq!(row.get::<i64>("money").unwrap(), 1234);
+    }
+
+    #[test]
+    fn composites_are_stored_as_json() {
+        use crate::Dao;
+
+        let mut pool = Pool::new();
+        let mut dm = pool.dm("sqlite://sakila.db").unwrap();
+        let mut address = Dao::new();
+        address.insert("address", "47 MySakila Drive");
+        address.insert("postal_code", None::<String>);
+        let rows = dm
+            .execute_sql_with_return("SELECT ?1 AS address", &[&address.to_value()])
+            .unwrap();
+        let row = rows.iter().next().unwrap();
+        assert_eq!(row.get::<Dao>("address").unwrap(), address);
+    }
+
+    #[test]
+    fn identifiers_are_quoted() {
+        let mut pool = Pool::new();
+        let mut em = pool.em("sqlite://sakila.db").unwrap();
+        em.db()
+            .execute_sql_with_return("CREATE TEMP TABLE \"order\" (\"group\" INTEGER)", &[])
+            .unwrap();
+        let order = em.db().get_table(&TableName::from("order")).unwrap().unwrap();
+        assert_eq!(order.columns[0].name.name, "group");
+        match em.db().get_table(&TableName::from("actor\n")) {
+            Err(DbError::SqlInjectionAttempt(_)) => (),
+            other => panic!("expecting an injection attempt, got {:?}", other),
+        }
+        assert!(em.db().get_table(&TableName::from("actor")).unwrap().is_some());
+    }
+
+    #[test]
+    fn statement_cache_hits() {
+        let mut pool = Pool::new();
+        pool.set_statement_cache_capacity(2);
+        let mut dm = pool.dm("sqlite://sakila.db").unwrap();
+        for sql in &["SELECT 1", "SELECT 1", "SELECT 2", "SELECT 1", "SELECT 3", "SELEC 4"] {
+            let _ = dm.execute_sql_with_return(sql, &[]);
+        }
+        let stats = dm.statement_cache_stats();
+        assert_eq!((stats.hits, stats.misses, stats.evictions), (2, 4, 1));
+        assert_eq!((stats.len, stats.capacity), (2, 2));
+    }
+
+    #[test]
+    fn statement_timeout() {
+        let mut pool = Pool::new();
+        let mut dm = pool.dm("sqlite://sakila.db").unwrap();
+        let sql = "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c LIMIT 1000000000)
+            SELECT count(*) FROM c";
+        let timeout = Duration::from_millis(100);
+        let result = dm.with_timeout(timeout, |dm| dm.execute_sql_with_return(sql, &[]));
+        match result {
+            Err(DbError::Timeout { timeout: t, .. }) => assert_eq!(t, timeout),
+            other => panic!("expecting a timeout, got {:?}", other),
+        }
+        let rows = dm.execute_sql_with_return("SELECT 1", &[]).unwrap();
+        assert_eq!(rows.data.len(), 1);
+    }
 }
@@ -0,0 +1,191 @@
+//! A bounded cache of the prepared statements of a pooled connection, so that the statements
+//! executed repeatedly are parsed and planned only once per connection.
+//!
+//! The cache is kept in the extensions of the pooled connection, so it lives as long as the
+//! connection and is reused each time the connection is checked out of the pool.
+//! Postgresql and mysql statements are kept in this cache while sqlite uses the cache of
+//! rusqlite, in which case this cache only tracks the statements to count the hits and misses.
+use r2d2::{
+    ManageConnection,
+    PooledConnection,
+};
+use std::collections::HashMap;
+
+/// the number of statements cached per connection unless set with
+/// `Pool::set_statement_cache_capacity`
+pub const DEFAULT_CAPACITY: usize = 64;
+
+/// The counters of the prepared statement cache of a connection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatementCacheStats {
+    /// the statements found in the cache
+    pub hits: u64,
+    /// the statements which had to be prepared
+    pub misses: u64,
+    /// the statements removed from the cache to make room for another
+    pub evictions: u64,
+    /// the number of statements currently cached
+    pub len: usize,
+    pub capacity: usize,
+}
+
+impl StatementCacheStats {
+    /// the fraction of the statements found in the cache, 0 when nothing was executed
+    pub fn hit_ratio(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            0.0
+        } else {
+            self.hits as f64 / total as f64
+        }
+    }
+}
+
+/// A least recently used cache of the prepared statements, keyed by sql
+pub(crate) struct StatementCache<S> {
+    entries: HashMap<String, (S, u64)>,
+    /// incremented on each use, the entry with the lowest tick is the least recently used
+    tick: u64,
+    stats: StatementCacheStats,
+}
+
+impl<S: Clone> StatementCache<S> {
+    pub(crate) fn new(capacity: usize) -> Self {
+        StatementCache {
+            entries: HashMap::new(),
+            tick: 0,
+            stats: StatementCacheStats {
+                capacity,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// the cached statement of this sql, counted as a hit or a miss
+    pub(crate) fn get(&mut self, sql: &str) -> Option<S> {
+        self.tick += 1;
+        match self.entries.get_mut(sql) {
+            Some((stmt, tick)) => {
+                *tick = self.tick;
+                self.stats.hits += 1;
+                Some(stmt.clone())
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// cache the statement prepared after a miss, returns the evicted statements
+    pub(crate) fn insert(&mut self, sql: &str, stmt: S) -> Vec<S> {
+        if self.stats.capacity > 0 {
+            let evicted = self.evict(self.stats.capacity - 1);
+            self.entries.insert(sql.to_string(), (stmt, self.tick));
+            evicted
+        } else {
+            vec![]
+        }
+    }
+
+    /// remove the statement of this sql, ie: when it is no longer valid
+    pub(crate) fn remove(&mut self, sql: &str) { self.entries.remove(sql); }
+
+    /// returns the evicted statements
+    pub(crate) fn set_capacity(&mut self, capacity: usize) -> Vec<S> {
+        self.stats.capacity = capacity;
+        self.evict(capacity)
+    }
+
+    pub(crate) fn stats(&self) -> StatementCacheStats {
+        StatementCacheStats {
+            len: self.entries.len(),
+            ..self.stats
+        }
+    }
+
+    /// evict the least recently used statements until at most `len` remain
+    fn evict(&mut self, len: usize) -> Vec<S> {
+        let mut evicted = vec![];
+        while self.entries.len() > len {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, tick))| *tick)
+                .map(|(sql, _)| sql.clone());
+            if let Some((stmt, _)) = oldest.and_then(|oldest| self.entries.remove(&oldest)) {
+                evicted.push(stmt);
+                self.stats.evictions += 1;
+            }
+        }
+        evicted
+    }
+}
+
+/// the statement cache of this pooled connection, created on first use
+pub(crate) fn of<M, S>(conn: &mut PooledConnection<M>) -> &mut StatementCache<S>
+where
+    M: ManageConnection,
+    S: Clone + Send + Sync + 'static,
+{
+    let extensions = PooledConnection::extensions_mut(conn);
+    if extensions.get::<StatementCache<S>>().is_none() {
+        extensions.insert(StatementCache::<S>::new(DEFAULT_CAPACITY));
+    }
+    extensions
+        .get_mut::<StatementCache<S>>()
+        .expect("must have a statement cache")
+}
+
+/// the stats of the statement cache of this pooled connection
+pub(crate) fn stats_of<M, S>(conn: &PooledConnection<M>) -> StatementCacheStats
+where
+    M: ManageConnection,
+    S: Clone + Send + Sync + 'static,
+{
+    PooledConnection::extensions(conn)
+        .get::<StatementCache<S>>()
+        .map(StatementCache::stats)
+        .unwrap_or(StatementCacheStats {
+            capacity: DEFAULT_CAPACITY,
+            ..Default::default()
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// whether the statement was cached, caching it otherwise
+    fn prepare(cache: &mut StatementCache<()>, sql: &str) -> bool {
+        let cached = cache.get(sql).is_some();
+        if !cached {
+            cache.insert(sql, ());
+        }
+        cached
+    }
+
+    #[test]
+    fn least_recently_used_is_evicted() {
+        let mut cache = StatementCache::new(2);
+        assert!(!prepare(&mut cache, "SELECT 1"));
+        assert!(!prepare(&mut cache, "SELECT 2"));
+        assert!(prepare(&mut cache, "SELECT 1"));
+        assert!(!prepare(&mut cache, "SELECT 3"));
+        assert!(prepare(&mut cache, "SELECT 1"));
+        assert!(!prepare(&mut cache, "SELECT 2"));
+        assert_eq!(cache.stats(), StatementCacheStats {
+            hits: 2,
+            misses: 4,
+            evictions: 2,
+            len: 2,
+            capacity: 2,
+        });
+        assert!((cache.stats().hit_ratio() - 1.0 / 3.0).abs() < f64::EPSILON);
+
+        cache.set_capacity(0);
+        assert!(!prepare(&mut cache, "SELECT 1"));
+        assert!(!prepare(&mut cache, "SELECT 1"));
+        assert_eq!(cache.stats().len, 0);
+    }
+}
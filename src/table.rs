@@ -24,10 +24,6 @@ pub struct TableDef {
 impl TableDef {
     pub fn complete_name(&self) -> String { self.name.complete_name() }
 
-    pub fn safe_name(&self) -> String { self.name.safe_name() }
-
-    pub fn safe_complete_name(&self) -> String { self.name.safe_complete_name() }
-
     pub fn get_primary_column_names(&self) -> Vec<&ColumnName> {
         let mut primary: Vec<&ColumnName> = vec![];
         for key in &self.table_key {
@@ -0,0 +1,66 @@
+//! Cancel the statements running longer than a timeout.
+use std::{
+    sync::mpsc::{
+        self,
+        RecvTimeoutError,
+    },
+    thread,
+    time::Duration,
+};
+
+/// execute the statement while a watchdog thread waits for it, calling `cancel` when the
+/// statement takes longer than `timeout`.
+/// Returns the result of the statement and whether it was cancelled.
+pub(crate) fn execute<T, C, F>(timeout: Duration, cancel: C, execute: F) -> (T, bool)
+where
+    C: FnOnce() + Send + 'static,
+    F: FnOnce() -> T,
+{
+    let (done, finished) = mpsc::channel::<()>();
+    let watchdog = thread::spawn(move || {
+        match finished.recv_timeout(timeout) {
+            Err(RecvTimeoutError::Timeout) => {
+                cancel();
+                true
+            }
+            _ => false,
+        }
+    });
+    let result = execute();
+    drop(done);
+    // waiting for the watchdog ensures the cancel is not sent during the next statement
+    let cancelled = watchdog.join().unwrap_or(false);
+    (result, cancelled)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::{
+        atomic::{
+            AtomicBool,
+            Ordering,
+        },
+        Arc,
+    };
+
+    #[test]
+    fn cancel_slow_statement() {
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let flag = interrupted.clone();
+        let (result, cancelled) = execute(
+            Duration::from_millis(10),
+            move || flag.store(true, Ordering::SeqCst),
+            || {
+                while !interrupted.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                "interrupted"
+            },
+        );
+        assert_eq!((result, cancelled), ("interrupted", true));
+
+        let (result, cancelled) = execute(Duration::from_secs(10), || panic!("cancelled"), || 1);
+        assert_eq!((result, cancelled), (1, false));
+    }
+}
@@ -0,0 +1,124 @@
+//! The conversions of the database types unknown to rustorm, registered by the application.
+//!
+//! A decoder turns the raw value of a column into a `Value`, either a `Value::Custom` or one
+//! of the known variants. An encoder turns a parameter into the raw value of its type.
+//! The raw value is the binary format of postgresql and the text or blob stored by sqlite.
+use crate::{
+    error::ConvertError,
+    Value,
+};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::Arc,
+};
+
+/// decode the raw value of a custom type
+pub type Decoder = dyn Fn(&[u8]) -> Result<Value, ConvertError> + Send + Sync;
+
+/// encode a value into the raw value of a custom type
+pub type Encoder = dyn Fn(&Value) -> Result<Vec<u8>, ConvertError> + Send + Sync;
+
+/// The database type a conversion is registered for
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TypeKey {
+    /// the name of the type, ie: the name of a postgresql enum or the declared type in sqlite
+    Name(String),
+    /// the oid of a postgresql type
+    Oid(u32),
+}
+
+impl From<&str> for TypeKey {
+    fn from(name: &str) -> Self { TypeKey::Name(name.to_string()) }
+}
+
+impl From<String> for TypeKey {
+    fn from(name: String) -> Self { TypeKey::Name(name) }
+}
+
+impl From<u32> for TypeKey {
+    fn from(oid: u32) -> Self { TypeKey::Oid(oid) }
+}
+
+/// The decoders and encoders of the custom types, set to a `Pool` or an `EntityManager`.
+///
+/// The decoders and encoders apply to postgresql and sqlite. Mysql has no user defined types,
+/// only the encoders of the custom parameters apply and the decoders are ignored.
+///
+/// The conversions registered for the oid of a type take precedence over the ones registered
+/// for its name. The registered conversions also take precedence over the built-in ones.
+#[derive(Clone, Default)]
+pub struct TypeRegistry {
+    decoders: BTreeMap<TypeKey, Arc<Decoder>>,
+    encoders: BTreeMap<TypeKey, Arc<Encoder>>,
+}
+
+impl TypeRegistry {
+    pub fn new() -> Self { Default::default() }
+
+    pub fn register_decoder<K, D>(&mut self, key: K, decoder: D)
+    where
+        K: Into<TypeKey>,
+        D: Fn(&[u8]) -> Result<Value, ConvertError> + Send + Sync + 'static,
+    {
+        self.decoders.insert(key.into(), Arc::new(decoder));
+    }
+
+    pub fn register_encoder<K, E>(&mut self, key: K, encoder: E)
+    where
+        K: Into<TypeKey>,
+        E: Fn(&Value) -> Result<Vec<u8>, ConvertError> + Send + Sync + 'static,
+    {
+        self.encoders.insert(key.into(), Arc::new(encoder));
+    }
+
+    pub fn is_empty(&self) -> bool { self.decoders.is_empty() && self.encoders.is_empty() }
+
+    /// the decoder of the type with this name and oid
+    pub fn decoder(&self, name: &str, oid: Option<u32>) -> Option<&Decoder> {
+        lookup(&self.decoders, name, oid)
+    }
+
+    /// the encoder of the type with this name and oid
+    pub fn encoder(&self, name: &str, oid: Option<u32>) -> Option<&Encoder> {
+        lookup(&self.encoders, name, oid)
+    }
+}
+
+fn lookup<'a, T: ?Sized>(
+    conversions: &'a BTreeMap<TypeKey, Arc<T>>,
+    name: &str,
+    oid: Option<u32>,
+) -> Option<&'a T> {
+    oid.and_then(|oid| conversions.get(&TypeKey::Oid(oid)))
+        .or_else(|| conversions.get(&TypeKey::Name(name.to_string())))
+        .map(|conversion| &**conversion)
+}
+
+impl fmt::Debug for TypeRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TypeRegistry")
+            .field("decoders", &self.decoders.keys().collect::<Vec<_>>())
+            .field("encoders", &self.encoders.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn oid_takes_precedence_over_name() {
+        let mut registry = TypeRegistry::new();
+        assert!(registry.is_empty());
+        registry.register_decoder("mood", |_| Ok(Value::Text("by name".into())));
+        registry.register_decoder(16385, |_| Ok(Value::Text("by oid".into())));
+        let decode = |oid| registry.decoder("mood", oid).map(|decoder| decoder(&[]).unwrap());
+        assert_eq!(decode(Some(16385)), Some(Value::Text("by oid".into())));
+        assert_eq!(decode(Some(1)), Some(Value::Text("by name".into())));
+        assert_eq!(decode(None), Some(Value::Text("by name".into())));
+        assert!(registry.decoder("weather", None).is_none());
+        assert!(registry.encoder("mood", None).is_none());
+    }
+}
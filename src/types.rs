@@ -1,5 +1,6 @@
 use clia_rustorm_dao::{
     value::Array,
+    Range,
     Value,
 };
 use serde::{
@@ -44,15 +45,124 @@ pub enum SqlType {
     Interval,
 
     IpAddress,
+    Cidr,
+    MacAddr,
+
+    Money,
+    Bit,
+    VarBit,
 
     Point,
+    // the geometric types of postgresql
+    Lseg,
+    Box,
+    Path,
+    Polygon,
+    /// the geometry of PostGIS and the spatial types of mysql, with the srid of the column
+    Geometry(GeometryType, Option<i32>),
+    /// the geography of PostGIS
+    Geography(GeometryType, Option<i32>),
+    Hstore,
 
     // enum list with the choices value
     Enum(String, Vec<String>),
     Array(Box<SqlType>),
+    /// a range of the element type, ie: `int4range`
+    Range(Box<SqlType>),
+    /// a type unknown to rustorm, with the name of the type in the database
+    Custom(String),
+    /// a postgresql composite type, with its name
+    Composite(String),
+}
+
+/// the type of the geometries of a spatial column
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum GeometryType {
+    /// any type of geometry
+    Geometry,
+    Point,
+    LineString,
+    Polygon,
+    MultiPoint,
+    MultiLineString,
+    MultiPolygon,
+    GeometryCollection,
+}
+
+impl GeometryType {
+    /// the type from its name, ie: `POINT` or `MultiPolygon`.
+    /// The `Z`, `M` and `ZM` suffixes of the dimensions are accepted, ie: `PointZ`. The values
+    /// read only keep the x and y coordinates and can not be written back to such a column,
+    /// since PostGIS rejects a geometry of fewer dimensions
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        let name = ["zm", "z", "m"]
+            .iter()
+            .filter_map(|suffix| name.strip_suffix(suffix))
+            .map(str::trim_end)
+            .find(|name| Self::from_base_name(name).is_some())
+            .unwrap_or(&name);
+        Self::from_base_name(name)
+    }
+
+    fn from_base_name(name: &str) -> Option<Self> {
+        let ty = match name {
+            "geometry" => GeometryType::Geometry,
+            "point" => GeometryType::Point,
+            "linestring" => GeometryType::LineString,
+            "polygon" => GeometryType::Polygon,
+            "multipoint" => GeometryType::MultiPoint,
+            "multilinestring" => GeometryType::MultiLineString,
+            "multipolygon" => GeometryType::MultiPolygon,
+            "geometrycollection" | "geomcollection" => GeometryType::GeometryCollection,
+            _ => return None,
+        };
+        Some(ty)
+    }
+
+    fn of(geometry: &geo_types::Geometry<f64>) -> Self {
+        match geometry {
+            geo_types::Geometry::Point(_) => GeometryType::Point,
+            geo_types::Geometry::Line(_) | geo_types::Geometry::LineString(_) => {
+                GeometryType::LineString
+            }
+            geo_types::Geometry::Polygon(_)
+            | geo_types::Geometry::Rect(_)
+            | geo_types::Geometry::Triangle(_) => GeometryType::Polygon,
+            geo_types::Geometry::MultiPoint(_) => GeometryType::MultiPoint,
+            geo_types::Geometry::MultiLineString(_) => GeometryType::MultiLineString,
+            geo_types::Geometry::MultiPolygon(_) => GeometryType::MultiPolygon,
+            geo_types::Geometry::GeometryCollection(_) => GeometryType::GeometryCollection,
+        }
+    }
 }
 
 impl SqlType {
+    /// the spatial type of PostGIS from its name, ie: `geometry(Point,4326)` or `geography`
+    pub fn from_spatial_name(name: &str) -> Option<Self> {
+        let (base, modifiers) = match name.find('(') {
+            Some(start) => (&name[..start], Some(name[start + 1..].trim_end_matches(')'))),
+            None => (name, None),
+        };
+        let (ty, srid) = match modifiers {
+            Some(modifiers) => {
+                let mut parts = modifiers.split(',').map(str::trim);
+                let ty = GeometryType::from_name(parts.next()?)?;
+                let srid = match parts.next() {
+                    Some(srid) => Some(srid.parse().ok()?),
+                    None => None,
+                };
+                (ty, srid)
+            }
+            None => (GeometryType::Geometry, None),
+        };
+        match &*base.trim().to_lowercase() {
+            "geometry" => Some(SqlType::Geometry(ty, srid)),
+            "geography" => Some(SqlType::Geography(ty, srid)),
+            _ => None,
+        }
+    }
+
     pub fn is_array_type(&self) -> bool { matches!(*self, SqlType::Array(_)) }
 
     pub fn is_integer_type(&self) -> bool {
@@ -141,19 +251,62 @@ impl HasType for Value {
             Value::Uuid(_) => Some(SqlType::Uuid),
             Value::Date(_) => Some(SqlType::Date),
             Value::Time(_) => Some(SqlType::Time),
+            Value::TimeTz(..) => Some(SqlType::TimeTz),
             Value::DateTime(_) => Some(SqlType::Timestamp),
             Value::Timestamp(_) => Some(SqlType::Timestamp),
+            Value::TimestampTz(_) => Some(SqlType::TimestampTz),
             Value::Interval(_) => Some(SqlType::Interval),
             Value::Point(_) => Some(SqlType::Point),
-            Value::Array(Array::Int(_)) => Some(SqlType::Array(Box::new(SqlType::Int))),
-            Value::Array(Array::Float(_)) => Some(SqlType::Array(Box::new(SqlType::Float))),
-            Value::Array(Array::Text(_)) => Some(SqlType::Array(Box::new(SqlType::Text))),
+            Value::Geometry(v) => {
+                Some(SqlType::Geometry(GeometryType::of(&v.geometry), v.srid))
+            }
+            Value::Array(array) => element_type(array).map(|ty| SqlType::Array(Box::new(ty))),
+            Value::Inet(_) => Some(SqlType::IpAddress),
+            Value::Cidr(_) => Some(SqlType::Cidr),
+            Value::MacAddr(_) => Some(SqlType::MacAddr),
+            Value::Money(_) => Some(SqlType::Money),
+            Value::Bit(_) => Some(SqlType::VarBit),
+            Value::Range(range) => {
+                let bound = match range.as_ref() {
+                    Range::Bounds(lower, upper) => lower.value().or_else(|| upper.value()),
+                    Range::Empty => None,
+                };
+                bound
+                    .and_then(Value::get_type)
+                    .map(|ty| SqlType::Range(Box::new(ty)))
+            }
+            Value::Hstore(_) => Some(SqlType::Hstore),
+            Value::Custom(v) => Some(SqlType::Custom(v.type_name.clone())),
+            // the name of the composite type is not known from its fields
+            Value::Composite(_) => None,
         }
     }
 }
 
+/// the type of the elements of the array, the arrays of any dimension have the same type
+fn element_type(array: &Array) -> Option<SqlType> {
+    match array {
+        Array::Bool(_) => Some(SqlType::Bool),
+        Array::Smallint(_) => Some(SqlType::Smallint),
+        Array::Int(_) => Some(SqlType::Int),
+        Array::Bigint(_) => Some(SqlType::Bigint),
+        Array::Float(_) => Some(SqlType::Float),
+        Array::Double(_) => Some(SqlType::Double),
+        Array::BigDecimal(_) => Some(SqlType::Numeric),
+        Array::Text(_) => Some(SqlType::Text),
+        Array::Uuid(_) => Some(SqlType::Uuid),
+        Array::Date(_) => Some(SqlType::Date),
+        Array::Timestamp(_) => Some(SqlType::Timestamp),
+        Array::Nullable(values) => values.iter().find_map(Value::get_type),
+        Array::Nested(arrays) => arrays.iter().find_map(element_type),
+    }
+}
+
 impl SqlType {
     pub fn same_type(&self, value: &Value) -> bool {
+        if let (SqlType::Composite(_), Value::Composite(_)) = (self, value) {
+            return true;
+        }
         if let Some(simple_type) = value.get_type() {
             if simple_type == *self {
                 return true;
@@ -161,6 +314,14 @@ impl SqlType {
             match (self, value) {
                 (SqlType::Varchar, Value::Text(_)) => true,
                 (SqlType::TimestampTz, Value::Timestamp(_)) => true,
+                (SqlType::Bit, Value::Bit(_)) => true,
+                (SqlType::Range(_), Value::Range(_)) => true,
+                (SqlType::Geometry(..), Value::Geometry(_) | Value::Point(_)) => true,
+                (SqlType::Geography(..), Value::Geometry(_) | Value::Point(_)) => true,
+                (
+                    SqlType::Lseg | SqlType::Box | SqlType::Path | SqlType::Polygon,
+                    Value::Geometry(_),
+                ) => true,
                 (_, _) => false,
             }
         } else {
@@ -168,3 +329,39 @@ impl SqlType {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn spatial_names() {
+        assert_eq!(
+            SqlType::from_spatial_name("geometry(Point,4326)"),
+            Some(SqlType::Geometry(GeometryType::Point, Some(4326)))
+        );
+        assert_eq!(
+            SqlType::from_spatial_name("geography(MultiPolygon)"),
+            Some(SqlType::Geography(GeometryType::MultiPolygon, None))
+        );
+        assert_eq!(
+            SqlType::from_spatial_name("geometry"),
+            Some(SqlType::Geometry(GeometryType::Geometry, None))
+        );
+        assert_eq!(
+            SqlType::from_spatial_name("geometry(PointZ,4326)"),
+            Some(SqlType::Geometry(GeometryType::Point, Some(4326)))
+        );
+        assert_eq!(
+            SqlType::from_spatial_name("geometry(MultiLineStringM,3857)"),
+            Some(SqlType::Geometry(GeometryType::MultiLineString, Some(3857)))
+        );
+        assert_eq!(
+            SqlType::from_spatial_name("geography(PolygonZM,4326)"),
+            Some(SqlType::Geography(GeometryType::Polygon, Some(4326)))
+        );
+        assert_eq!(GeometryType::from_name("POINT Z"), Some(GeometryType::Point));
+        assert_eq!(SqlType::from_spatial_name("geometry(PointX,4326)"), None);
+        assert_eq!(SqlType::from_spatial_name("numeric(4,2)"), None);
+    }
+}